@@ -95,6 +95,14 @@ pub fn run_daemon() -> io::Result<()> {
                 let response = handle_exec(request.id, &program, &args);
                 write_response(&mut stdout, &response)?;
             }
+            DaemonCommand::ExecWithInput {
+                program,
+                args,
+                input,
+            } => {
+                let response = handle_exec_with_input(request.id, &program, &args, &input);
+                write_response(&mut stdout, &response)?;
+            }
             DaemonCommand::WriteFile { path, content } => {
                 let response = handle_write_file(request.id, &path, &content);
                 write_response(&mut stdout, &response)?;
@@ -143,6 +151,45 @@ fn handle_exec(id: u64, program: &str, args: &[String]) -> DaemonResponse {
     }
 }
 
+fn handle_exec_with_input(id: u64, program: &str, args: &[String], input: &str) -> DaemonResponse {
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return error_response(id, format!("Failed to execute command: {}", e)),
+    };
+
+    // Write the input and close stdin so the program sees EOF
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(e) = stdin.write_all(input.as_bytes())
+    {
+        let _ = child.kill();
+        let _ = child.wait();
+        return error_response(id, format!("Failed to write command input: {}", e));
+    }
+
+    match child.wait_with_output() {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let exit_code = output.status.code().unwrap_or(-1);
+            DaemonResponse {
+                id,
+                success: exit_code == 0,
+                exit_code,
+                stdout,
+                stderr,
+                error: None,
+            }
+        }
+        Err(e) => error_response(id, format!("Failed to execute command: {}", e)),
+    }
+}
+
 fn handle_write_file(id: u64, path: &str, content: &str) -> DaemonResponse {
     match fs::write(path, content) {
         Ok(()) => success_response(id),
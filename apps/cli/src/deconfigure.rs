@@ -0,0 +1,61 @@
+//! `deconfigure` command: removes a drive's managed mount configuration.
+//!
+//! The device is looked up by its configured mount point. Removing the
+//! configuration needs root, so unless the CLI already runs as root the
+//! privileged commands go through `sudo`. With `--remove-steam-libraries`,
+//! the drive's libraries are also removed from Steam's libraryfolders.vdf;
+//! a running Steam client is stopped first and brought back with the
+//! `--restart` strategy afterwards. Run through `sudo`, the Steam
+//! installation of the user who ran it is used, not root's.
+
+use std::path::Path;
+
+use steamos_mount_core::{Error, ExecutionContext, ListDevicesConfig, mount_unit, preset, steam};
+
+use crate::library::RestartArg;
+
+/// Deconfigures the device managed at the given mount point.
+pub fn run(
+    mount_point: &Path,
+    remove_steam_libraries: bool,
+    restart: RestartArg,
+) -> steamos_mount_core::Result<()> {
+    let config = ListDevicesConfig::new().without_usage();
+    let devices = steamos_mount_core::list_devices(&config)?;
+    let device = devices
+        .iter()
+        .find(|d| {
+            d.fstab_entry
+                .as_ref()
+                .is_some_and(|e| e.mount_point == mount_point)
+        })
+        .ok_or_else(|| Error::InvalidMountPath {
+            path: mount_point.to_path_buf(),
+            reason: "no managed mount configuration".to_string(),
+        })?;
+
+    let mut ctx = if preset::current_uid() == 0 {
+        ExecutionContext::default()
+    } else {
+        ExecutionContext::with_sudo()
+    };
+    steamos_mount_core::device::deconfigure_device_with_ctx(
+        device,
+        Path::new(mount_unit::SYSTEMD_UNIT_DIR),
+        &mut ctx,
+    )?;
+    println!("Removed the configuration of {}", mount_point.display());
+
+    if !remove_steam_libraries || device.steam_libraries.is_empty() {
+        return Ok(());
+    }
+
+    let install = steam::default_steam_install()?;
+    let removed =
+        steam::edit_while_stopped_with_ctx(&install, restart.strategy(&install), &mut ctx, |_| {
+            steamos_mount_core::device::remove_steam_libraries(device, &install.vdf_path)
+        })?;
+    println!("Removed {} Steam libraries", removed);
+
+    Ok(())
+}
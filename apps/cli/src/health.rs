@@ -0,0 +1,107 @@
+//! `health` command: SMART health of the disks holding the devices.
+//!
+//! smartctl needs root, so unless the CLI already runs as root the queries
+//! go through `sudo`. Each disk is printed once with the devices on it;
+//! values crossing the default thresholds are flagged as warnings.
+
+use serde::Serialize;
+use steamos_mount_core::health::{DiskHealth, HealthThresholds, HealthWarning};
+use steamos_mount_core::{ExecutionContext, ListDevicesConfig, preset};
+
+/// A disk's health report with the devices on it, as printed with `--json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiskReport<'a> {
+    #[serde(flatten)]
+    health: &'a DiskHealth,
+    warnings: Vec<HealthWarning>,
+    devices: Vec<&'a str>,
+}
+
+/// Prints the health of the disks of all online devices.
+pub fn run(json: bool) -> steamos_mount_core::Result<()> {
+    let config = ListDevicesConfig::new().without_steam().without_usage();
+    let mut devices = steamos_mount_core::list_devices(&config)?;
+    let mut ctx = if preset::current_uid() == 0 {
+        ExecutionContext::default()
+    } else {
+        ExecutionContext::with_sudo()
+    };
+    steamos_mount_core::device::attach_health_with_ctx(&mut devices, &mut ctx)?;
+
+    let thresholds = HealthThresholds::default();
+    let mut reports: Vec<DiskReport> = Vec::new();
+    for device in &devices {
+        let Some(health) = &device.health else {
+            continue;
+        };
+        match reports.iter_mut().find(|r| r.health.disk == health.disk) {
+            Some(report) => report.devices.push(&device.name),
+            None => reports.push(DiskReport {
+                health,
+                warnings: health.warnings(&thresholds),
+                devices: vec![&device.name],
+            }),
+        }
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&reports).expect("Failed to serialize health reports")
+        );
+        return Ok(());
+    }
+
+    if reports.is_empty() {
+        println!("No health data available (SMART not supported or no devices)");
+    }
+    for report in &reports {
+        print_report(report);
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &DiskReport) {
+    let health = report.health;
+    let status = match health.passed {
+        Some(true) => "PASSED",
+        Some(false) => "FAILED",
+        None => "UNKNOWN",
+    };
+    println!(
+        "{} ({}): {}",
+        health.disk.display(),
+        health.model.as_deref().unwrap_or("unknown model"),
+        status
+    );
+    println!("  devices: {}", report.devices.join(", "));
+
+    let values = [
+        (
+            "temperature",
+            health.temperature_celsius.map(|t| format!("{}°C", t)),
+        ),
+        ("used", health.percentage_used.map(|p| format!("{}%", p))),
+        ("media errors", health.media_errors.map(|c| c.to_string())),
+        (
+            "reallocated sectors",
+            health.reallocated_sectors.map(|c| c.to_string()),
+        ),
+        (
+            "pending sectors",
+            health.pending_sectors.map(|c| c.to_string()),
+        ),
+        ("power on", health.power_on_hours.map(|h| format!("{}h", h))),
+    ];
+    for (name, value) in values {
+        if let Some(value) = value {
+            println!("  {}: {}", name, value);
+        }
+    }
+
+    for warning in &report.warnings {
+        println!("  WARNING: {}", warning);
+    }
+}
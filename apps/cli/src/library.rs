@@ -0,0 +1,76 @@
+//! `add-library` command: adds a drive's Steam library to Steam.
+//!
+//! The library folder is created on the mounted drive, or an existing one
+//! there is adopted, and registered in libraryfolders.vdf. A running Steam
+//! client is stopped for the edit and brought back with the `--restart`
+//! strategy, which by default is picked from the session: in Game Mode
+//! gamescope-session restarts Steam by itself. Run through `sudo`, the
+//! library is added to the Steam installation of the user who ran it.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+use steamos_mount_core::{ExecutionContext, RestartStrategy, SteamInstall, preset, steam};
+
+/// How to bring Steam back after editing its configuration.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum RestartArg {
+    /// Detect from the session.
+    #[default]
+    Auto,
+    /// Launch Steam again (Desktop Mode).
+    Relaunch,
+    /// Let the session supervisor restart Steam (Game Mode).
+    Supervisor,
+    /// Restart the display manager.
+    DisplayManager,
+    /// Leave Steam stopped.
+    Ask,
+}
+
+impl RestartArg {
+    /// Resolves the strategy for an installation.
+    pub fn strategy(self, install: &SteamInstall) -> RestartStrategy {
+        match self {
+            Self::Auto => RestartStrategy::detect(install),
+            Self::Relaunch => RestartStrategy::Relaunch,
+            Self::Supervisor => RestartStrategy::Supervisor,
+            Self::DisplayManager => RestartStrategy::RestartDisplayManager,
+            Self::Ask => RestartStrategy::AskUser,
+        }
+    }
+}
+
+/// Creates or adopts the library on the drive mounted at `mount_point` and
+/// registers it with Steam.
+pub fn run(
+    mount_point: &Path,
+    library_dir: Option<&Path>,
+    restart: RestartArg,
+) -> steamos_mount_core::Result<()> {
+    let mut ctx = if preset::current_uid() == 0 {
+        ExecutionContext::default()
+    } else {
+        ExecutionContext::with_sudo()
+    };
+    let library = steam::initialize_library_with_ctx(mount_point, library_dir, &mut ctx)?;
+
+    let install = steam::default_steam_install()?;
+    let strategy = restart.strategy(&install);
+    let was_running = install.is_running;
+    steam::edit_while_stopped_with_ctx(&install, strategy, &mut ctx, |_| {
+        steam::register_library(&install.vdf_path, &library)
+    })?;
+
+    let action = if library.adopted {
+        "Adopted"
+    } else {
+        "Created"
+    };
+    println!("{} Steam library {}", action, library.path.display());
+    if was_running && strategy == RestartStrategy::AskUser {
+        println!("Steam was stopped; start it again to use the library");
+    }
+
+    Ok(())
+}
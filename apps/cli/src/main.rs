@@ -4,7 +4,13 @@
 //! privileged session execution.
 
 mod daemon;
+mod deconfigure;
+mod health;
+mod library;
 mod protocol;
+mod session;
+mod usage;
+mod watch;
 
 use clap::{Parser, Subcommand};
 
@@ -25,6 +31,61 @@ enum Commands {
     /// allowing the parent process to execute multiple privileged
     /// commands without repeated authentication.
     Daemon,
+
+    /// Detect the current session and switch between Game Mode and Desktop Mode.
+    Session {
+        /// Print the result as JSON.
+        #[arg(long, global = true)]
+        json: bool,
+
+        #[command(subcommand)]
+        action: session::SessionAction,
+    },
+
+    /// Watch for device hotplug and mount changes, printing JSON lines.
+    Watch,
+
+    /// List devices by free space, most room first.
+    Usage {
+        /// Print the devices as JSON.
+        #[arg(long)]
+        json: bool,
+        /// Also compute the disk usage of each Steam library (slow).
+        #[arg(long)]
+        libraries: bool,
+    },
+
+    /// Remove the managed mount configuration of a drive.
+    Deconfigure {
+        /// Configured mount point of the drive.
+        mount_point: std::path::PathBuf,
+        /// Also remove the drive's Steam libraries from Steam.
+        #[arg(long)]
+        remove_steam_libraries: bool,
+        /// How to bring Steam back after removing its libraries.
+        #[arg(long, value_enum, default_value_t)]
+        restart: library::RestartArg,
+    },
+
+    /// Create or adopt a Steam library on a mounted drive and add it to Steam.
+    AddLibrary {
+        /// Mount point of the drive.
+        mount_point: std::path::PathBuf,
+        /// Library directory relative to the mount point (defaults to an
+        /// existing library on the drive, else SteamLibrary).
+        #[arg(long)]
+        library_dir: Option<std::path::PathBuf>,
+        /// How to bring Steam back after the edit.
+        #[arg(long, value_enum, default_value_t)]
+        restart: library::RestartArg,
+    },
+
+    /// Show the SMART health of the disks, with warnings for failing drives.
+    Health {
+        /// Print the reports as JSON.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() {
@@ -37,5 +98,49 @@ fn main() {
                 std::process::exit(1);
             }
         }
+        Commands::Session { json, action } => {
+            if let Err(e) = session::run(action, json) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Watch => {
+            if let Err(e) = watch::run() {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Usage { json, libraries } => {
+            if let Err(e) = usage::run(json, libraries) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Deconfigure {
+            mount_point,
+            remove_steam_libraries,
+            restart,
+        } => {
+            if let Err(e) = deconfigure::run(&mount_point, remove_steam_libraries, restart) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::AddLibrary {
+            mount_point,
+            library_dir,
+            restart,
+        } => {
+            if let Err(e) = library::run(&mount_point, library_dir.as_deref(), restart) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Health { json } => {
+            if let Err(e) = health::run(json) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
 }
@@ -0,0 +1,100 @@
+//! Session subcommands: detect the current session and switch between
+//! Game Mode and Desktop Mode with a scheduled return.
+//!
+//! With `--json`, results are printed as a single JSON object so that the
+//! Decky backend can consume them.
+
+use std::time::Duration;
+
+use clap::Subcommand;
+use serde::Serialize;
+use steamos_mount_core::session::{self, SessionInfo};
+use steamos_mount_core::syscall;
+
+/// Session actions.
+#[derive(Subcommand)]
+pub enum SessionAction {
+    /// Show the current session (Game Mode or Desktop Mode).
+    Status,
+    /// Switch to Desktop Mode and return to Game Mode after a delay.
+    Desktop {
+        /// Seconds to stay in Desktop Mode before returning to Game Mode.
+        #[arg(long, default_value_t = 300)]
+        return_after: u64,
+        /// Open Steam's storage settings once the desktop has started.
+        #[arg(long)]
+        open_storage: bool,
+    },
+    /// Switch to Game Mode now.
+    GameMode,
+    /// Schedule a return to Game Mode without switching sessions.
+    ScheduleReturn {
+        /// Seconds until the switch to Game Mode.
+        #[arg(long)]
+        after: u64,
+    },
+    /// Cancel a scheduled return to Game Mode.
+    CancelReturn,
+}
+
+/// Result of a session action.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionStatus {
+    /// The current session.
+    session: SessionInfo,
+    /// Whether a return to Game Mode is scheduled.
+    return_scheduled: bool,
+}
+
+/// Runs a session action and prints the resulting status.
+pub fn run(action: SessionAction, json: bool) -> steamos_mount_core::Result<()> {
+    match action {
+        SessionAction::Status => {}
+        SessionAction::Desktop {
+            return_after,
+            open_storage,
+        } => {
+            session::switch_to_desktop_and_return(Duration::from_secs(return_after), open_storage)?;
+        }
+        SessionAction::GameMode => {
+            session::cancel_return_to_game_mode()?;
+            syscall::switch_to_game_mode()?;
+        }
+        SessionAction::ScheduleReturn { after } => {
+            session::schedule_return_to_game_mode(Duration::from_secs(after))?;
+        }
+        SessionAction::CancelReturn => {
+            session::cancel_return_to_game_mode()?;
+        }
+    }
+
+    let status = SessionStatus {
+        session: session::detect_session()?,
+        return_scheduled: session::is_return_to_game_mode_scheduled()?,
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&status).expect("Failed to serialize session status")
+        );
+    } else {
+        println!(
+            "Session: {:?}{}",
+            status.session.kind,
+            status
+                .session
+                .session_id
+                .as_deref()
+                .map(|id| format!(" (session {})", id))
+                .unwrap_or_default()
+        );
+        println!(
+            "Return to Game Mode scheduled: {}",
+            if status.return_scheduled { "yes" } else { "no" }
+        );
+    }
+
+    Ok(())
+}
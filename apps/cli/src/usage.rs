@@ -0,0 +1,91 @@
+//! `usage` command: lists devices by free space, most room first.
+//!
+//! Offline devices are listed with their last-known usage. With `--json`,
+//! the full `Device` records are printed as a JSON array.
+
+use steamos_mount_core::{Device, ListDevicesConfig};
+
+/// Lists devices sorted by available space.
+pub fn run(json: bool, libraries: bool) -> steamos_mount_core::Result<()> {
+    let mut config = ListDevicesConfig::new();
+    if libraries {
+        config = config.with_library_usage();
+    }
+    let mut devices = steamos_mount_core::list_devices(&config)?;
+    // Devices with unknown usage go last
+    devices.sort_by_key(|d| std::cmp::Reverse(d.usage.map(|u| u.available)));
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&devices).expect("Failed to serialize devices")
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{:<24} {:>10} {:>10} {:>6}  MOUNT POINT",
+        "DEVICE", "FREE", "SIZE", "USED"
+    );
+    for device in &devices {
+        print_device(device);
+        for library in &device.library_usage {
+            println!(
+                "  library {} ({} apps): {} reported, {} on disk",
+                library.path.display(),
+                library.app_count,
+                format_bytes(library.reported_bytes),
+                library
+                    .actual_bytes
+                    .map(format_bytes)
+                    .unwrap_or_else(|| "?".to_string()),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_device(device: &Device) {
+    let (free, used) = match device.usage {
+        Some(usage) => (
+            format_bytes(usage.available),
+            format!("{:.0}%", usage.used_percent()),
+        ),
+        None => ("-".to_string(), "-".to_string()),
+    };
+    let stale = if device.usage_updated_at.is_some() {
+        " (last known)"
+    } else {
+        ""
+    };
+
+    println!(
+        "{:<24} {:>10} {:>10} {:>6}  {}{}",
+        device.name,
+        free,
+        format_bytes(device.size),
+        used,
+        device
+            .effective_mount_point()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default(),
+        stale
+    );
+}
+
+/// Formats a byte count with binary units (e.g., "931.5G").
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
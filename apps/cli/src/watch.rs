@@ -0,0 +1,26 @@
+//! `watch` command: prints device events as JSON lines.
+
+use std::io::{self, Write};
+
+use steamos_mount_core::ListDevicesConfig;
+use steamos_mount_core::monitor::DeviceMonitor;
+
+/// Watches for device changes and prints one JSON object per event.
+///
+/// Runs until monitoring fails or stdout is closed.
+pub fn run() -> steamos_mount_core::Result<()> {
+    let monitor = DeviceMonitor::new(ListDevicesConfig::new())?;
+    let mut stdout = io::stdout();
+
+    for event in monitor {
+        let line = serde_json::to_string(&event?).expect("Failed to serialize device event");
+        if writeln!(stdout, "{}", line)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    Ok(())
+}
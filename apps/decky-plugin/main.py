@@ -1,6 +1,78 @@
-def main():
-    print("Hello from decky-plugin!")
+"""Decky backend for steamos-mount.
 
+The backend runs as root; session commands are delegated to the bundled
+``steamos-mount-cli`` running as the Decky user, since session detection and
+the scheduled return to Game Mode act on the user's session and systemd
+user manager.
+"""
 
-if __name__ == "__main__":
-    main()
+import asyncio
+import json
+import os
+import pwd
+
+import decky
+
+CLI_PATH = os.path.join(decky.DECKY_PLUGIN_DIR, "bin", "steamos-mount-cli")
+
+
+def _user_env() -> dict[str, str]:
+    """Environment for running commands in the Decky user's session."""
+    uid = pwd.getpwnam(decky.DECKY_USER).pw_uid
+    runtime_dir = f"/run/user/{uid}"
+    return {
+        "HOME": decky.DECKY_USER_HOME,
+        "USER": decky.DECKY_USER,
+        "PATH": os.environ.get("PATH", "/usr/bin:/bin"),
+        "XDG_RUNTIME_DIR": runtime_dir,
+        "DBUS_SESSION_BUS_ADDRESS": f"unix:path={runtime_dir}/bus",
+    }
+
+
+async def _run_session_command(*args: str) -> dict:
+    """Runs ``steamos-mount-cli session <args> --json`` as the Decky user."""
+    process = await asyncio.create_subprocess_exec(
+        CLI_PATH,
+        "session",
+        *args,
+        "--json",
+        user=decky.DECKY_USER,
+        env=_user_env(),
+        stdout=asyncio.subprocess.PIPE,
+        stderr=asyncio.subprocess.PIPE,
+    )
+    stdout, stderr = await process.communicate()
+    if process.returncode != 0:
+        message = stderr.decode().strip()
+        decky.logger.error(f"session {' '.join(args)} failed: {message}")
+        raise RuntimeError(message)
+    return json.loads(stdout)
+
+
+class Plugin:
+    async def get_session(self) -> dict:
+        """Returns the current session and whether a return is scheduled."""
+        return await _run_session_command("status")
+
+    async def switch_to_desktop(
+        self, return_after_secs: int = 300, open_storage: bool = True
+    ) -> dict:
+        """Switches to Desktop Mode and schedules the return to Game Mode."""
+        args = ["desktop", "--return-after", str(return_after_secs)]
+        if open_storage:
+            args.append("--open-storage")
+        return await _run_session_command(*args)
+
+    async def schedule_return_to_game_mode(self, after_secs: int) -> dict:
+        """Schedules a return to Game Mode without switching sessions."""
+        return await _run_session_command("schedule-return", "--after", str(after_secs))
+
+    async def cancel_return_to_game_mode(self) -> dict:
+        """Cancels a scheduled return to Game Mode."""
+        return await _run_session_command("cancel-return")
+
+    async def _main(self):
+        decky.logger.info("steamos-mount backend loaded")
+
+    async def _unload(self):
+        pass
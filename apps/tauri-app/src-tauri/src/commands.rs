@@ -16,15 +16,20 @@ use std::process::Stdio;
 use snafu::OptionExt;
 use snafu::ResultExt;
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri::command;
 
-use steamos_mount_core::{fstab, mount, preset, steam};
+use steamos_mount_core::format::{FormatOptions, FormatPlan};
+use steamos_mount_core::usage::LibraryUsage;
+use steamos_mount_core::{crypt, format, fstab, mount, mount_unit, preset, steam, udisks};
 
 use crate::types::{
-    DeviceInfo, FstabPreview, MountConfig, SteamInjectionConfig, SteamInjectionMode,
+    DeviceInfo, FstabPreview, MountConfig, PersistenceBackend, SteamInjectionConfig,
+    SteamInjectionMode,
 };
 
 use crate::context::{command_in_non_privileged_context, command_in_privileged_context};
+use crate::library_wait;
 
 // ============================================================================
 // Tauri commands
@@ -34,13 +39,47 @@ use crate::context::{command_in_non_privileged_context, command_in_privileged_co
 #[command]
 pub async fn list_devices() -> Result<Vec<DeviceInfo>, String> {
     command_in_non_privileged_context(|_| {
-        let config = steamos_mount_core::ListDevicesConfig::new();
+        let config = steamos_mount_core::ListDevicesConfig::new()
+            .with_installed_apps()
+            .with_compatdata();
         let devices = steamos_mount_core::list_devices(&config)?;
 
         Ok(devices.iter().map(DeviceInfo::from).collect())
     })
 }
 
+/// Computes the usage of every Steam library on a mounted device.
+///
+/// Walks each library's `steamapps/common`, so this is kept separate from
+/// `list_devices`.
+#[command]
+pub async fn get_library_usage() -> Result<Vec<LibraryUsage>, String> {
+    command_in_non_privileged_context(|_| {
+        let config = steamos_mount_core::ListDevicesConfig::new().with_library_usage();
+        let devices = steamos_mount_core::list_devices(&config)?;
+
+        Ok(devices
+            .into_iter()
+            .flat_map(|device| device.library_usage)
+            .collect())
+    })
+}
+
+/// Lists all devices with the SMART health of their disks attached.
+///
+/// Reading SMART data requires privileges, so this is kept separate from
+/// `list_devices` and only run on request.
+#[command]
+pub async fn check_disk_health(app: AppHandle) -> Result<Vec<DeviceInfo>, String> {
+    command_in_privileged_context(&app, |ctx, _| {
+        let config = steamos_mount_core::ListDevicesConfig::new();
+        let mut devices = steamos_mount_core::list_devices(&config)?;
+        steamos_mount_core::device::attach_health_with_ctx(&mut devices, ctx)?;
+
+        Ok(devices.iter().map(DeviceInfo::from).collect())
+    })
+}
+
 /// Gets detailed information about a specific device by UUID.
 #[command]
 pub async fn get_device_info(uuid: String) -> Result<Option<DeviceInfo>, String> {
@@ -169,18 +208,41 @@ pub async fn mount_device(app: AppHandle, config: MountConfig) -> Result<(), Str
         // Create mount point with smart privilege handling
         mount::create_mount_point_smart(&mount_point, ctx, !force_root_creation)?;
 
-        // Backup fstab with privilege escalation
-        let fstab_path = std::path::Path::new(fstab::FSTAB_PATH);
-        fstab::backup_fstab_with_ctx(fstab_path, ctx)?;
+        // Keep the SteamOS automounter away from the device
+        if config.ignore_udisks {
+            udisks::add_ignore_rule_with_ctx(
+                std::path::Path::new(udisks::UDEV_RULES_PATH),
+                &config.uuid,
+                ctx,
+            )?;
+        }
+
+        // Release a udisks automount (/run/media/...) before activating ours
+        udisks::release_mounts_with_ctx(&device.path, &device.mountpoints, ctx)?;
 
-        // Write fstab with privilege escalation
-        fstab::add_managed_entries_with_ctx(fstab_path, &[entry], ctx)?;
+        match config.persistence {
+            PersistenceBackend::Fstab => {
+                // Backup fstab with privilege escalation
+                let fstab_path = std::path::Path::new(fstab::FSTAB_PATH);
+                fstab::backup_fstab_with_ctx(fstab_path, ctx)?;
 
-        // Reload systemd daemon
-        mount::reload_systemd_daemon_with_ctx(ctx)?;
+                // Write fstab with privilege escalation
+                fstab::add_managed_entries_with_ctx(fstab_path, &[entry], ctx)?;
 
-        // Mount the device
-        mount::mount_device_with_ctx(&device, &mount_point, ctx)?;
+                // Reload systemd daemon
+                mount::reload_systemd_daemon_with_ctx(ctx)?;
+
+                // Mount the device
+                mount::mount_device_with_ctx(&device, &mount_point, ctx)?;
+            }
+            PersistenceBackend::Systemd => {
+                // Write .mount/.automount units and activate them
+                let unit = mount_unit::MountUnit::from_fstab_entry(&entry);
+                let unit_dir = std::path::Path::new(mount_unit::SYSTEMD_UNIT_DIR);
+                mount_unit::install_units_with_ctx(&unit, unit_dir, ctx)?;
+                mount_unit::enable_with_ctx(&unit, ctx)?;
+            }
+        }
 
         Ok(())
     })
@@ -208,23 +270,142 @@ pub async fn unmount_device(app: AppHandle, mount_point: String) -> Result<(), S
                 .is_some_and(|e| e.mount_point == path)
         }) {
             // Device has a managed fstab entry, deconfigure it using the unified API
-            steamos_mount_core::device::deconfigure_device_with_ctx(device, privileged_ctx)?;
+            steamos_mount_core::device::deconfigure_device_with_ctx(
+                device,
+                std::path::Path::new(mount_unit::SYSTEMD_UNIT_DIR),
+                privileged_ctx,
+            )?;
         }
 
         Ok(())
     })
 }
 
+/// Resolves a mount conflict by unmounting the conflicting mount point.
+///
+/// Only udisks automount points (under /run/media) are accepted; the
+/// configured mount point and its fstab entry are left untouched.
+#[command]
+pub async fn resolve_mount_conflict(app: AppHandle, mount_point: String) -> Result<(), String> {
+    let path = std::path::PathBuf::from(&mount_point);
+    if !mount::is_udisks_mount_point(&path) {
+        return Err(format!("{} is not a udisks mount point", mount_point));
+    }
+    command_in_privileged_context(&app, |privileged_ctx, _| {
+        mount::unmount_device_with_ctx(&path, privileged_ctx)
+    })
+}
+
+/// Unlocks a LUKS or BitLocker volume and returns the mapped device path.
+///
+/// The passphrase is handed to `cryptsetup` on stdin through the privileged
+/// session. With `persist`, a managed `/etc/crypttab` entry is written so the
+/// mapping is known to the system.
+#[command]
+pub async fn unlock_device(
+    app: AppHandle,
+    uuid: String,
+    passphrase: String,
+    persist: bool,
+) -> Result<String, String> {
+    command_in_privileged_context(&app, |ctx, _| {
+        let device = steamos_mount_core::find_online_block_device_by_uuid(&uuid)?
+            .with_whatever_context(|| format!("Device with UUID {} not found", uuid))?;
+
+        let mapped = crypt::unlock_with_ctx(&device, &passphrase, ctx)?;
+
+        if persist && let Some(entry) = crypt::CrypttabEntry::for_device(&device) {
+            crypt::add_managed_entry_with_ctx(
+                std::path::Path::new(crypt::CRYPTTAB_PATH),
+                &entry,
+                ctx,
+            )?;
+        }
+
+        Ok(mapped.display().to_string())
+    })
+}
+
+/// Changes the volume label of a device and returns the refreshed device.
+///
+/// With `rename_mount_point`, a managed mount point follows the new label.
+/// With `update_steam`, Steam libraries under a renamed mount point are
+/// relocated in libraryfolders.vdf; Steam is shut down for that and restarted
+/// afterwards if it was running.
+#[command]
+pub async fn relabel_device(
+    app: AppHandle,
+    uuid: String,
+    label: String,
+    rename_mount_point: bool,
+    update_steam: bool,
+) -> Result<DeviceInfo, String> {
+    command_in_privileged_context(&app, |ctx, _| {
+        let config = steamos_mount_core::ListDevicesConfig::new();
+        let devices = steamos_mount_core::list_devices(&config)?;
+        let device = steamos_mount_core::device::find_device_by_uuid(&devices, &uuid)
+            .with_whatever_context(|| format!("Device with UUID {} not found", uuid))?;
+
+        let mut options = steamos_mount_core::RelabelOptions::new();
+        if rename_mount_point {
+            options = options.with_mount_point_rename();
+        }
+
+        if !(rename_mount_point && update_steam && !device.steam_libraries.is_empty()) {
+            let relabeled =
+                steamos_mount_core::device::relabel_device_with_ctx(device, &label, &options, ctx)?;
+            return Ok(DeviceInfo::from(&relabeled));
+        }
+
+        let install = steam::default_steam_install()?;
+        options = options.with_steam_vdf_path(&install.vdf_path);
+        let strategy = steam::RestartStrategy::detect(&install);
+        let relabeled = steam::edit_while_stopped_with_ctx(&install, strategy, ctx, |ctx| {
+            steamos_mount_core::device::relabel_device_with_ctx(device, &label, &options, ctx)
+        })?;
+
+        Ok(DeviceInfo::from(&relabeled))
+    })
+}
+
+/// Checks a device for formatting and returns the plan to confirm.
+///
+/// Refuses the system disk and anything mounted or in use.
+#[command]
+pub async fn plan_format(device: String, options: FormatOptions) -> Result<FormatPlan, String> {
+    command_in_non_privileged_context(|_| {
+        format::plan_format(std::path::Path::new(&device), &options)
+    })
+}
+
+/// Formats a device according to a confirmed plan.
+///
+/// Returns the new filesystem, to be handed to the mount dialog.
+#[command]
+pub async fn format_device(
+    app: AppHandle,
+    plan: FormatPlan,
+    token: String,
+) -> Result<DeviceInfo, String> {
+    command_in_privileged_context(&app, |ctx, _| {
+        let device = format::format_with_ctx(&plan, &token, ctx)?;
+        Ok(DeviceInfo::from(&device))
+    })
+}
+
 /// Removes the fstab configuration for a device (online or offline).
 ///
 /// Uses fs_spec + mount_point for precise matching, supporting scenarios where
-/// a single block device has multiple mount points configured.
+/// a single block device has multiple mount points configured. With
+/// `remove_steam_libraries`, the device's Steam libraries are removed from
+/// libraryfolders.vdf too (Steam is stopped and restarted around the edit).
 #[command]
 #[allow(dead_code)] // Used by Tauri invoke handler
 pub async fn deconfigure_device(
     app: AppHandle,
     fs_spec: String,
     mount_point: String,
+    remove_steam_libraries: Option<bool>,
 ) -> Result<(), String> {
     let mount_path = std::path::PathBuf::from(&mount_point);
     command_in_privileged_context(&app, |ctx, _| {
@@ -248,7 +429,21 @@ pub async fn deconfigure_device(
             })?;
 
         // Deconfigure using the unified API
-        steamos_mount_core::device::deconfigure_device_with_ctx(device, ctx)
+        steamos_mount_core::device::deconfigure_device_with_ctx(
+            device,
+            std::path::Path::new(mount_unit::SYSTEMD_UNIT_DIR),
+            ctx,
+        )?;
+
+        if remove_steam_libraries.unwrap_or(false) && !device.steam_libraries.is_empty() {
+            let install = steam::default_steam_install()?;
+            let strategy = steam::RestartStrategy::detect(&install);
+            steam::edit_while_stopped_with_ctx(&install, strategy, ctx, |_| {
+                steamos_mount_core::device::remove_steam_libraries(device, &install.vdf_path)
+            })?;
+        }
+
+        Ok(())
     })
 }
 
@@ -283,57 +478,182 @@ pub async fn detect_steam_library_vdf() -> Result<String, String> {
     })
 }
 
-/// Injects a Steam library folder.
+/// Lists the Steam installations of the current user.
 #[command]
-pub async fn inject_steam_library(config: SteamInjectionConfig) -> Result<(), String> {
-    command_in_non_privileged_context(|_| {
-        let library_path = match &config.library_path {
-            Some(path) => config.mount_point.join(path),
-            None => config.mount_point.join("SteamLibrary"),
-        };
-
-        match config.mode {
-            SteamInjectionMode::Auto => {
-                // Record Steam running state
-                let was_running = steam::is_steam_running();
+pub async fn list_steam_installs() -> Result<Vec<steam::SteamInstall>, String> {
+    command_in_non_privileged_context(|_| steam::discover_steam_installs())
+}
 
-                // Shutdown Steam if running
-                if was_running {
-                    steam::shutdown_steam()?;
+/// Injects a Steam library folder.
+///
+/// Unless the config sets a mode, a running Steam is left alone
+/// (semi-automatic mode) and automatic mode is only used when it is
+/// stopped. The mode used is returned.
+///
+/// In automatic mode the library is created on the drive first, or an
+/// existing library there is adopted. Creating it may need privileges when
+/// the drive root is owned by root.
+///
+/// In semi-automatic mode Steam's storage settings are opened instead and
+/// the command returns right away; the wait for Steam to add a library on
+/// the drive runs in the background without privileges and ends with
+/// [`STEAM_LIBRARY_ADDED_EVENT`](library_wait::STEAM_LIBRARY_ADDED_EVENT).
+/// It can be cancelled with [`cancel_steam_library_wait`].
+#[command]
+pub async fn inject_steam_library(
+    app: AppHandle,
+    config: SteamInjectionConfig,
+) -> Result<SteamInjectionMode, String> {
+    // Target the install owning the configured VDF, or the default one
+    let install = match &config.steam_vdf_path {
+        Some(path) => steam::SteamInstall::from_vdf_path(std::path::Path::new(path)),
+        None => steam::default_steam_install().ok(),
+    };
+    let mode = config
+        .mode
+        .unwrap_or_else(|| SteamInjectionMode::detect(install.as_ref()));
+
+    match mode {
+        SteamInjectionMode::Auto => command_in_privileged_context(&app, |ctx, _| {
+            let library = steam::initialize_library_with_ctx(
+                &config.mount_point,
+                config.library_path.as_deref().map(std::path::Path::new),
+                ctx,
+            )?;
+
+            // Get VDF path (custom or detected) and inject
+            let vdf_path = match (&config.steam_vdf_path, &install) {
+                (Some(path), _) => std::path::PathBuf::from(path),
+                (None, install) => install
+                    .as_ref()
+                    .map(|i| i.vdf_path.clone())
+                    .whatever_context("No Steam installation found")?,
+            };
+
+            // Steam is stopped around the edit and brought back the way
+            // its session expects
+            match install {
+                Some(install) => {
+                    let strategy = config
+                        .restart_strategy
+                        .unwrap_or_else(|| steam::RestartStrategy::detect(&install));
+                    steam::edit_while_stopped_with_ctx(&install, strategy, ctx, |_| {
+                        steam::register_library(&vdf_path, &library)
+                    })
                 }
+                None => steam::register_library(&vdf_path, &library),
+            }
+        })?,
+        SteamInjectionMode::Semi => command_in_non_privileged_context(|_| {
+            // The user adds the drive in Steam's storage settings; Steam
+            // keeps running and its write to the VDF confirms the library
+            let install = install.whatever_context("No Steam installation found")?;
+            library_wait::spawn_library_wait(app.clone(), install, config.mount_point.clone());
+            Ok(())
+        })?,
+        SteamInjectionMode::Manual => {
+            // Nothing to do, UI will show instructions
+        }
+    }
 
-                // Get VDF path (custom or detected) and inject
-                let vdf_path = if let Some(path) = &config.steam_vdf_path {
-                    std::path::PathBuf::from(path)
-                } else {
-                    steam::steam_library_vdf_path()?
-                };
+    Ok(mode)
+}
 
-                steam::inject_library_folder(&vdf_path, &library_path, "")?;
+/// Cancels waiting for Steam to add a library. Returns true if a wait was
+/// running.
+#[command]
+pub async fn cancel_steam_library_wait() -> Result<bool, String> {
+    Ok(library_wait::cancel_library_wait())
+}
 
-                // Restart Steam if it was running
-                if was_running {
-                    Command::new("steam")
-                        .spawn()
-                        .with_whatever_context(|e| format!("Failed to restart Steam: {}", e))?;
-                }
+/// Name of the event emitted with [`steam::MoveProgress`] while an app is moved.
+pub const APP_MOVE_PROGRESS_EVENT: &str = "app-move-progress";
 
-                Ok(())
-            }
-            SteamInjectionMode::Semi => {
-                // Open Steam storage settings
-                Command::new("steam")
-                    .arg("steam://open/settings/storage")
-                    .spawn()
-                    .with_whatever_context(|e| format!("Failed to open Steam settings: {}", e))?;
-
-                Ok(())
-            }
-            SteamInjectionMode::Manual => {
-                // Nothing to do, UI will show instructions
-                Ok(())
-            }
+/// Moves an installed game to another Steam library.
+///
+/// Files are copied while Steam keeps running; Steam is only stopped to
+/// update libraryfolders.vdf and restarted afterwards. Progress is emitted
+/// as [`APP_MOVE_PROGRESS_EVENT`]. Originals that could not be deleted are
+/// listed in the result.
+#[command]
+pub async fn move_steam_app(
+    app: AppHandle,
+    appid: u32,
+    source_library: String,
+    target_library: String,
+    steam_vdf_path: Option<String>,
+) -> Result<steam::MovedApp, String> {
+    command_in_privileged_context(&app, |ctx, _| {
+        let source = std::path::Path::new(&source_library);
+        let installed = steam::list_installed_apps(source)?
+            .into_iter()
+            .find(|a| a.appid == appid)
+            .with_whatever_context(|| format!("App {} not found in {}", appid, source_library))?;
+
+        let install = match &steam_vdf_path {
+            Some(path) => steam::SteamInstall::from_vdf_path(std::path::Path::new(path))
+                .with_whatever_context(|| format!("No Steam installation owns {}", path))?,
+            None => steam::default_steam_install()?,
+        };
+
+        let mut prepared = steam::prepare_move(
+            &install.vdf_path,
+            &installed,
+            std::path::Path::new(&target_library),
+            |progress| {
+                let _ = app.emit(APP_MOVE_PROGRESS_EVENT, progress);
+            },
+        )?;
+
+        let strategy = steam::RestartStrategy::detect(&install);
+        let moved = steam::edit_while_stopped_with_ctx(&install, strategy, ctx, |_| {
+            steam::finish_move(&install.vdf_path, &mut prepared)
+        });
+        if moved.is_err() {
+            prepared.abort();
         }
+        moved
+    })
+}
+
+/// Moves the Proton prefixes of a library on NTFS/exFAT/FAT to internal
+/// storage and links them back into the library.
+///
+/// Steam is stopped while the prefixes move and restarted afterwards.
+#[command]
+pub async fn redirect_compatdata(
+    app: AppHandle,
+    library: String,
+) -> Result<steam::compatdata::CompatdataStatus, String> {
+    command_in_privileged_context(&app, |ctx, _| {
+        let target_root = steam::compatdata::default_target_root()?;
+        let install = steam::default_steam_install()?;
+        let strategy = steam::RestartStrategy::detect(&install);
+        steam::edit_while_stopped_with_ctx(&install, strategy, ctx, |ctx| {
+            steam::compatdata::redirect_compatdata_with_ctx(
+                std::path::Path::new(&library),
+                &target_root,
+                ctx,
+            )
+        })
+    })
+}
+
+/// Returns how Steam would be brought back after an edit, or None if it is
+/// not running.
+#[command]
+pub async fn detect_restart_strategy(
+    steam_vdf_path: Option<String>,
+) -> Result<Option<steam::RestartStrategy>, String> {
+    command_in_non_privileged_context(|_| {
+        let install = match &steam_vdf_path {
+            Some(path) => steam::SteamInstall::from_vdf_path(std::path::Path::new(path)),
+            None => steam::default_steam_install().ok(),
+        };
+
+        Ok(install
+            .filter(|install| install.is_running)
+            .map(|install| steam::RestartStrategy::detect(&install)))
     })
 }
 
@@ -402,7 +722,8 @@ pub async fn get_mount_config_suggestion(
         let suggestion = steamos_mount_core::preset::suggest_preset_config(
             fs,
             Some(device.rota),
-            Some(device.removable),
+            // Hot-pluggable drives (USB, SD slots) may report rm=0
+            Some(device.removable || device.hotplug),
             device.transport.as_deref(),
         );
 
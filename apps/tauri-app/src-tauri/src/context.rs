@@ -173,7 +173,7 @@ impl DaemonSpawner for TauriPkexecSpawner {
 ///
 /// This function provides detailed, actionable error messages for different
 /// error scenarios, making it easier for users to understand and resolve issues.
-fn error_to_user_message(error: &steamos_mount_core::Error) -> String {
+pub fn error_to_user_message(error: &steamos_mount_core::Error) -> String {
     match error {
         steamos_mount_core::Error::SidecarNotFound { path } => {
             format!(
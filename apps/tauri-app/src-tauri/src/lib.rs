@@ -4,13 +4,17 @@
 
 mod commands;
 mod context;
+mod library_wait;
+mod monitor;
 mod types;
 
 use commands::{
-    check_dirty_volume, copy_to_clipboard, deconfigure_device, detect_steam_library_vdf,
-    get_default_mount_point, get_device_info, get_mount_config_suggestion, get_steam_state,
-    inject_steam_library, list_devices, mount_device, preview_mount_options, repair_dirty_volume,
-    unmount_device,
+    cancel_steam_library_wait, check_dirty_volume, check_disk_health, copy_to_clipboard,
+    deconfigure_device, detect_restart_strategy, detect_steam_library_vdf, format_device,
+    get_default_mount_point, get_device_info, get_library_usage, get_mount_config_suggestion,
+    get_steam_state, inject_steam_library, list_devices, list_steam_installs, mount_device,
+    move_steam_app, plan_format, preview_mount_options, redirect_compatdata, relabel_device,
+    repair_dirty_volume, resolve_mount_conflict, unlock_device, unmount_device,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -20,17 +24,33 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            monitor::spawn_device_monitor(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             list_devices,
+            get_library_usage,
+            check_disk_health,
             get_device_info,
             get_default_mount_point,
             preview_mount_options,
             mount_device,
             unmount_device,
+            resolve_mount_conflict,
+            unlock_device,
+            relabel_device,
+            plan_format,
+            format_device,
             deconfigure_device,
+            detect_restart_strategy,
             check_dirty_volume,
             repair_dirty_volume,
             inject_steam_library,
+            cancel_steam_library_wait,
+            list_steam_installs,
+            move_steam_app,
+            redirect_compatdata,
             detect_steam_library_vdf,
             get_steam_state,
             get_mount_config_suggestion,
@@ -0,0 +1,70 @@
+//! Waits in the background for Steam to add a library the user was asked to
+//! add in its storage settings.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tauri::{AppHandle, Emitter};
+
+use steamos_mount_core::steam::{self, SteamInstall};
+
+use crate::context::error_to_user_message;
+use crate::types::SteamLibraryAddedPayload;
+
+/// Name of the event emitted when a wait finishes.
+pub const STEAM_LIBRARY_ADDED_EVENT: &str = "steam-library-added";
+
+/// Cancellation flag of the running wait.
+static CURRENT_WAIT: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+/// Opens Steam's storage settings and starts a background thread that emits
+/// [`STEAM_LIBRARY_ADDED_EVENT`] once Steam added a library below
+/// `mount_point`, or the wait failed.
+///
+/// A wait that is still running is cancelled first.
+pub fn spawn_library_wait(app: AppHandle, install: SteamInstall, mount_point: PathBuf) {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    if let Some(previous) = CURRENT_WAIT.lock().unwrap().replace(Arc::clone(&cancelled)) {
+        previous.store(true, Ordering::Relaxed);
+    }
+
+    std::thread::spawn(move || {
+        let added = steam::client::add_library_via_client(
+            &install,
+            &mount_point,
+            steam::client::DEFAULT_ADD_TIMEOUT,
+            &cancelled,
+        );
+
+        let mut current = CURRENT_WAIT.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, &cancelled))
+        {
+            *current = None;
+        }
+        drop(current);
+
+        let payload = SteamLibraryAddedPayload {
+            mount_point: mount_point.display().to_string(),
+            library: added.as_ref().ok().map(|l| l.path.display().to_string()),
+            error: added.err().map(|e| error_to_user_message(&e)),
+            cancelled: cancelled.load(Ordering::Relaxed),
+        };
+        if let Err(e) = app.emit(STEAM_LIBRARY_ADDED_EVENT, payload) {
+            eprintln!("Failed to emit Steam library event: {}", e);
+        }
+    });
+}
+
+/// Cancels the running wait. Returns true if one was running.
+pub fn cancel_library_wait() -> bool {
+    match CURRENT_WAIT.lock().unwrap().take() {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,40 @@
+//! Forwards hotplug and mount events from the core monitor to the frontend.
+
+use tauri::{AppHandle, Emitter};
+
+use steamos_mount_core::ListDevicesConfig;
+use steamos_mount_core::monitor::DeviceMonitor;
+
+use crate::types::DeviceEventPayload;
+
+/// Name of the event emitted for every device change.
+pub const DEVICE_EVENT: &str = "device-event";
+
+/// Starts a background thread that emits [`DEVICE_EVENT`] for every device change.
+///
+/// If monitoring is unavailable the UI keeps working with manual refreshes.
+pub fn spawn_device_monitor(app: AppHandle) {
+    std::thread::spawn(move || {
+        let monitor = match DeviceMonitor::new(ListDevicesConfig::new()) {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                eprintln!("Device monitor unavailable: {}", e);
+                return;
+            }
+        };
+
+        for event in monitor {
+            match event {
+                Ok(event) => {
+                    if let Err(e) = app.emit(DEVICE_EVENT, DeviceEventPayload::from(&event)) {
+                        eprintln!("Failed to emit device event: {}", e);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Device monitor stopped: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+}
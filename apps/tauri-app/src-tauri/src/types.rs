@@ -4,6 +4,12 @@
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use steamos_mount_core::health::{DiskHealth, HealthThresholds, HealthWarning};
+use steamos_mount_core::usage::SpaceUsage;
+use steamos_mount_core::{
+    BlockMetadata, CompatdataStatus, DeviceKind, Encryption, InstalledApp, MountConflict,
+    RestartStrategy,
+};
 
 /// Device information for UI display.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +31,10 @@ pub struct DeviceInfo {
     pub size: u64,
     /// Current mount point (if mounted)
     pub mountpoint: Option<String>,
+    /// All paths the device is currently mounted at
+    pub mountpoints: Vec<String>,
+    /// Mount conflicts the user can resolve (e.g. udisks automounts)
+    pub conflicts: Vec<MountConflict>,
     /// Whether the device is currently mounted
     pub is_mounted: bool,
     /// Whether the device has a dirty NTFS volume
@@ -33,16 +43,36 @@ pub struct DeviceInfo {
     pub is_offline: bool,
     /// Managed fstab configuration if available
     pub managed_entry: Option<ManagedEntryInfo>,
+    /// Where the managed configuration is persisted (fstab or systemd units)
+    pub persistence: Option<PersistenceBackend>,
     /// The fstab fs_spec (e.g. "UUID=...", "LABEL=...", or device path)
     pub fs_spec: Option<String>,
     /// Steam libraries under this device's mount point
     pub steam_libraries: Vec<SteamLibraryInfo>,
+    /// Games installed in those libraries (mounted devices only)
+    pub installed_apps: Vec<InstalledApp>,
+    /// Proton prefix state of those libraries (mounted devices only)
+    pub compatdata: Vec<CompatdataStatus>,
     /// Whether the device is rotational (HDD)
     pub rota: Option<bool>,
     /// Whether the device is removable
     pub removable: Option<bool>,
     /// Transport type (e.g., "usb", "nvme")
     pub transport: Option<String>,
+    /// Kind of device (partition, whole disk, crypt, lvm, raid)
+    pub device_kind: Option<DeviceKind>,
+    /// Drive model/serial and partition-table metadata (online devices only)
+    pub metadata: Option<BlockMetadata>,
+    /// Encryption format of a locked container (must be unlocked before mounting)
+    pub encryption: Option<Encryption>,
+    /// Filesystem usage (live when mounted, last-known otherwise)
+    pub usage: Option<SpaceUsage>,
+    /// When size/usage were cached (Unix seconds); None for live values
+    pub usage_updated_at: Option<u64>,
+    /// SMART health of the physical disk (only from `check_disk_health`)
+    pub health: Option<DiskHealth>,
+    /// Health values crossing the default thresholds
+    pub health_warnings: Vec<HealthWarning>,
 }
 
 impl From<&steamos_mount_core::BlockDevice> for DeviceInfo {
@@ -55,49 +85,32 @@ impl From<&steamos_mount_core::BlockDevice> for DeviceInfo {
             partuuid: device.partuuid.clone(),
             fstype: device.fstype.clone().unwrap_or_default(),
             size: device.size,
-            mountpoint: device.mountpoint.clone(),
+            mountpoint: device.mountpoint().map(|p| p.display().to_string()),
+            mountpoints: device
+                .mountpoints
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+            conflicts: Vec::new(),
             is_mounted: device.is_mounted(),
             is_dirty: false,     // Will be checked separately
             is_offline: false,   // Online device
             managed_entry: None, // Will be populated separately
-            fs_spec: None,       // Will be populated if matched with fstab entry
+            persistence: None,
+            fs_spec: None, // Will be populated if matched with fstab entry
             steam_libraries: Vec::new(),
+            installed_apps: Vec::new(),
+            compatdata: Vec::new(),
             rota: Some(device.rota),
             removable: Some(device.removable),
             transport: device.transport.clone(),
-        }
-    }
-}
-
-impl From<&steamos_mount_core::OfflineDevice> for DeviceInfo {
-    fn from(device: &steamos_mount_core::OfflineDevice) -> Self {
-        Self {
-            name: device
-                .mount_point
-                .file_name()
-                .and_then(|n| n.to_str())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| device.fs_spec.clone()),
-            path: String::new(), // No path for offline devices
-            label: device.label.clone(),
-            uuid: device.uuid.clone(),
-            partuuid: device.partuuid.clone(),
-            fstype: steamos_mount_core::vfs_type_to_fstype(&device.vfs_type).to_string(),
-            size: 0,          // Unknown size for offline devices
-            mountpoint: None, // Not mounted
-            is_mounted: false,
-            is_dirty: false,
-            is_offline: true, // Offline device
-            managed_entry: Some(ManagedEntryInfo {
-                mount_point: device.mount_point.display().to_string(),
-                options: device.mount_options.clone(),
-                raw_content: device.to_fstab_line(),
-            }),
-            fs_spec: Some(device.fs_spec.clone()),
-            steam_libraries: Vec::new(),
-            rota: None,
-            removable: None,
-            transport: None,
+            device_kind: Some(device.device_kind),
+            metadata: Some(BlockMetadata::from(device)),
+            encryption: device.encryption().filter(|_| device.is_locked()),
+            usage: None,
+            usage_updated_at: None,
+            health: None,
+            health_warnings: Vec::new(),
         }
     }
 }
@@ -120,6 +133,12 @@ impl From<&steamos_mount_core::Device> for DeviceInfo {
             mountpoint: device
                 .effective_mount_point()
                 .map(|p| p.display().to_string()),
+            mountpoints: device
+                .mountpoints
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+            conflicts: device.conflicts.clone(),
             is_mounted: device.is_mounted,
             is_dirty: device.is_dirty,
             is_offline: device.is_offline(),
@@ -128,15 +147,65 @@ impl From<&steamos_mount_core::Device> for DeviceInfo {
                 options: e.mount_options.clone(),
                 raw_content: e.to_fstab_line(),
             }),
+            persistence: device.persistence.map(PersistenceBackend::from),
             fs_spec: device.fs_spec.clone(),
             steam_libraries: device
                 .steam_libraries
                 .iter()
                 .map(SteamLibraryInfo::from)
                 .collect(),
+            installed_apps: device.installed_apps.clone(),
+            compatdata: device.compatdata.clone(),
             rota: device.rota,
             removable: device.removable,
             transport: device.transport.clone(),
+            device_kind: device.device_kind,
+            metadata: device.metadata.clone(),
+            encryption: device.encryption,
+            usage: device.usage,
+            usage_updated_at: device.usage_updated_at,
+            health: device.health.clone(),
+            health_warnings: device.health_warnings(&HealthThresholds::default()),
+        }
+    }
+}
+
+/// Kind of device change reported by the monitor.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeviceEventKind {
+    DeviceAdded,
+    DeviceRemoved,
+    Mounted,
+    Unmounted,
+    BecameDirty,
+}
+
+/// Device change event emitted to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceEventPayload {
+    /// What changed
+    pub kind: DeviceEventKind,
+    /// The device in its updated state
+    pub device: DeviceInfo,
+}
+
+impl From<&steamos_mount_core::monitor::DeviceEvent> for DeviceEventPayload {
+    fn from(event: &steamos_mount_core::monitor::DeviceEvent) -> Self {
+        use steamos_mount_core::monitor::DeviceEvent;
+
+        let kind = match event {
+            DeviceEvent::DeviceAdded(_) => DeviceEventKind::DeviceAdded,
+            DeviceEvent::DeviceRemoved(_) => DeviceEventKind::DeviceRemoved,
+            DeviceEvent::Mounted(_) => DeviceEventKind::Mounted,
+            DeviceEvent::Unmounted(_) => DeviceEventKind::Unmounted,
+            DeviceEvent::BecameDirty(_) => DeviceEventKind::BecameDirty,
+        };
+
+        Self {
+            kind,
+            device: DeviceInfo::from(event.device()),
         }
     }
 }
@@ -206,6 +275,26 @@ impl From<DeviceType> for steamos_mount_core::preset::DeviceType {
     }
 }
 
+/// Persistence backend for managed mounts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistenceBackend {
+    /// Managed block in /etc/fstab
+    #[default]
+    Fstab,
+    /// Native systemd .mount/.automount units
+    Systemd,
+}
+
+impl From<steamos_mount_core::PersistenceBackend> for PersistenceBackend {
+    fn from(backend: steamos_mount_core::PersistenceBackend) -> Self {
+        match backend {
+            steamos_mount_core::PersistenceBackend::Fstab => Self::Fstab,
+            steamos_mount_core::PersistenceBackend::SystemdUnit => Self::Systemd,
+        }
+    }
+}
+
 /// Mount configuration from UI.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -230,6 +319,12 @@ pub struct MountConfig {
     pub inject_steam: bool,
     /// Steam library path (relative to mount point)
     pub steam_library_path: Option<String>,
+    /// Where to persist the mount configuration (defaults to fstab)
+    #[serde(default)]
+    pub persistence: PersistenceBackend,
+    /// Whether to install a udev rule that keeps udisks2 from automounting the device
+    #[serde(default)]
+    pub ignore_udisks: bool,
 }
 
 impl MountConfig {
@@ -267,24 +362,59 @@ pub struct FstabPreview {
 pub enum SteamInjectionMode {
     /// Automatic: shutdown Steam, modify VDF, restore state
     Auto,
-    /// Semi-automatic: open Steam settings for manual add
+    /// Semi-automatic: open Steam settings for manual add, wait in the
+    /// background until Steam has added the library
     Semi,
     /// Manual: just show instructions
     Manual,
 }
 
+impl SteamInjectionMode {
+    /// Picks the mode that leaves a running client of `install` alone.
+    ///
+    /// Automatic mode stops Steam, including a game in progress, so it is
+    /// only picked when the client is not running; the user has to ask for
+    /// it otherwise.
+    pub fn detect(install: Option<&steamos_mount_core::steam::SteamInstall>) -> Self {
+        if install.is_some_and(|install| install.check_running()) {
+            Self::Semi
+        } else {
+            Self::Auto
+        }
+    }
+}
+
 /// Steam injection configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SteamInjectionConfig {
     /// Mount point path
     pub mount_point: PathBuf,
-    /// Library path (defaults to {mount_point}/SteamLibrary)
+    /// Library path relative to the mount point (defaults to an existing
+    /// library on the drive, else {mount_point}/SteamLibrary)
     pub library_path: Option<String>,
     /// Path to libraryfolders.vdf (optional, overrides default detection)
     pub steam_vdf_path: Option<String>,
-    /// Injection mode
-    pub mode: SteamInjectionMode,
+    /// Injection mode (detected from the session if not set)
+    pub mode: Option<SteamInjectionMode>,
+    /// How to bring Steam back after the edit (Auto mode; detected from the
+    /// session if not set)
+    pub restart_strategy: Option<RestartStrategy>,
+}
+
+/// Outcome of a semi-automatic injection, emitted once Steam added the
+/// library or the wait ended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SteamLibraryAddedPayload {
+    /// Mount point the library was expected on
+    pub mount_point: String,
+    /// Path of the library Steam added
+    pub library: Option<String>,
+    /// Why no library was added
+    pub error: Option<String>,
+    /// Whether the wait was cancelled
+    pub cancelled: bool,
 }
 
 /// Status of the Steam library configuration.
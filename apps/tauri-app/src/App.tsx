@@ -1,20 +1,34 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { RefreshCw, HardDrive, Settings2, AlertCircle } from "lucide-react";
+import { listen } from "@tauri-apps/api/event";
+import { RefreshCw, HardDrive, Settings2, AlertCircle, HeartPulse } from "lucide-react";
 import { useDevices } from "@/hooks/use-devices";
 import { DeviceCard } from "@/components/device-card";
 import { Button } from "@/components/ui/button";
 import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from "@/components/ui/tooltip";
 import { toast } from "sonner";
 import { useConfirm } from "@/hooks/use-confirm";
-import type { DeviceInfo } from "@/types";
+import type { DeviceInfo, MountConflict, RestartStrategy, SteamInjectionConfig, SteamInjectionMode, SteamLibraryAddedPayload } from "@/types";
 import "@/index.css";
 
 import { MountSettingsDialog } from "@/components/mount-dialog";
+import { UnlockDialog } from "@/components/unlock-dialog";
+import { RenameDialog } from "@/components/rename-dialog";
+import { FormatDialog } from "@/components/format-dialog";
+import { MoveAppDialog } from "@/components/move-app-dialog";
 import { SettingsDialog } from "@/components/settings-dialog";
 import { useAtom } from "jotai";
 import { tauriStoreAtom, appVersionAtom, fetchSteamState, steamStateAtom } from "./store";
 
+/** Lists the games that become unavailable when a device goes away, for confirm dialogs. */
+function describeUnavailableGames(device: DeviceInfo): string {
+  const apps = device.installedApps ?? [];
+  if (apps.length === 0) return "";
+  const names = apps.slice(0, 5).map(app => app.name).join(", ");
+  const more = apps.length > 5 ? ` and ${apps.length - 5} more` : "";
+  return ` ${apps.length} installed ${apps.length === 1 ? "game" : "games"} will be unavailable: ${names}${more}.`;
+}
+
 function App() {
   const { devices, loading, error, refresh } = useDevices();
   const { confirm } = useConfirm();
@@ -22,6 +36,13 @@ function App() {
   const [selectedDevice, setSelectedDevice] = useState<DeviceInfo | null>(null);
   const [dialogOpen, setDialogOpen] = useState(false);
   const [settingsOpen, setSettingsOpen] = useState(false);
+  const [unlockDevice, setUnlockDevice] = useState<DeviceInfo | null>(null);
+  const [renameDevice, setRenameDevice] = useState<DeviceInfo | null>(null);
+  const [formatDevice, setFormatDevice] = useState<DeviceInfo | null>(null);
+  const [moveGamesDevice, setMoveGamesDevice] = useState<DeviceInfo | null>(null);
+  // Health needs privileges, so it is fetched on request and kept across refreshes
+  const [healthByPath, setHealthByPath] = useState<Record<string, Pick<DeviceInfo, "health" | "healthWarnings">>>({});
+  const [checkingHealth, setCheckingHealth] = useState(false);
 
   const [appVersion] = useAtom(appVersionAtom);
   const [tauriStore] = useAtom(tauriStoreAtom);
@@ -45,7 +66,7 @@ function App() {
     // Use the global confirm dialog
     const confirmed = await confirm({
       title: "Confirm Unmount",
-      description: `Are you sure you want to unmount ${device.label || device.name}? This might interrupt running applications.`,
+      description: `Are you sure you want to unmount ${device.label || device.name}? This might interrupt running applications.${describeUnavailableGames(device)}`,
       variant: "default",
     });
 
@@ -61,6 +82,16 @@ function App() {
     }
   };
 
+  const handleResolveConflict = async (conflict: MountConflict) => {
+    try {
+      await invoke("resolve_mount_conflict", { mountPoint: conflict.path });
+      toast.success(`Unmounted ${conflict.path}`);
+      refresh();
+    } catch (e) {
+      toast.error(`Unmount failed: ${e}`);
+    }
+  };
+
   const handleRepair = async (device: DeviceInfo) => {
     if (!device.uuid) return;
 
@@ -73,6 +104,28 @@ function App() {
     }
   };
 
+  const handleFixCompatdata = async (device: DeviceInfo) => {
+    const broken = device.compatdata?.filter(status => status.needsRedirect && !status.redirection) ?? [];
+
+    const confirmed = await confirm({
+      title: "Fix Proton Prefixes",
+      description: `${device.fstype.toUpperCase()} cannot hold Proton prefixes. They will be moved to internal storage and linked back into the library. Steam will be restarted.`,
+      variant: "default",
+    });
+
+    if (!confirmed) return;
+
+    try {
+      for (const status of broken) {
+        await invoke("redirect_compatdata", { library: status.library });
+      }
+      toast.success("Proton prefixes moved to internal storage");
+      refresh();
+    } catch (e) {
+      toast.error(`Failed to fix Proton prefixes: ${e}`);
+    }
+  };
+
   const handleDeconfigure = async (device: DeviceInfo) => {
     // Device must have fsSpec and mountpoint to be deconfigured
     if (!device.fsSpec || !device.mountpoint) {
@@ -83,17 +136,27 @@ function App() {
     // Use the global confirm dialog
     const confirmed = await confirm({
       title: "Confirm Deconfigure",
-      description: `Are you sure you want to remove the fstab configuration for ${device.label || device.name}? This will remove the auto-mount entry.`,
+      description: `Are you sure you want to remove the fstab configuration for ${device.label || device.name}? This will remove the auto-mount entry.${describeUnavailableGames(device)}`,
       variant: "default",
     });
 
     if (!confirmed) return;
 
+    // Offer to drop the drive's libraries so Steam does not list them as missing
+    const removeSteamLibraries = device.steamLibraries.length > 0 && await confirm({
+      title: "Remove Steam Libraries",
+      description: `${device.label || device.name} holds ${device.steamLibraries.length} Steam ${device.steamLibraries.length === 1 ? "library" : "libraries"}. Remove ${device.steamLibraries.length === 1 ? "it" : "them"} from Steam as well? Steam will be restarted if it is running. Game files on the drive are kept.`,
+      variant: "default",
+      confirmText: "Remove",
+      cancelText: "Keep",
+    });
+
     try {
       // Unified deconfigure command using fs_spec + mount_point
       await invoke("deconfigure_device", {
         fsSpec: device.fsSpec,
         mountPoint: device.mountpoint,
+        removeSteamLibraries,
       });
       toast.success("Device configuration removed successfully");
       refresh();
@@ -104,31 +167,101 @@ function App() {
 
   const handleConfigureSteam = async (device: DeviceInfo) => {
     if (!device.mountpoint) return;
+    const mountPoint = device.mountpoint;
+
+    const finish = () => {
+      refresh();
+      fetchSteamState();
+    };
 
+    // A running Steam is only restarted if the user agrees; in Game Mode the
+    // session would bring it back before the library is saved
+    let mode: SteamInjectionMode | undefined;
     try {
-      await invoke("inject_steam_library", {
-        config: {
-          mountPoint: device.mountpoint,
-          mode: "semi",
-        }
-      });
+      const strategy = await invoke<RestartStrategy | null>("detect_restart_strategy", { steamVdfPath: null });
+      if (strategy === "supervisor") {
+        mode = "semi";
+      } else if (strategy) {
+        const restart = await confirm({
+          title: "Steam Is Running",
+          description: "The library can be added in Steam's storage settings while Steam keeps running, or automatically by restarting Steam. Restarting closes any game in progress.",
+          variant: "default",
+          confirmText: "Restart Steam",
+          cancelText: "Use Steam Settings",
+        });
+        mode = restart ? "auto" : "semi";
+      }
+    } catch (e) {
+      toast.error(`Failed to check Steam: ${e}`);
+      return;
+    }
+
+    // In semi-automatic mode the drive is added in Steam's storage settings
+    // and the outcome arrives as an event once Steam has written
+    // libraryfolders.vdf
+    const waiting = toast.loading("Adding Steam library...");
+    let settled = false;
+    const unlisten = await listen<SteamLibraryAddedPayload>("steam-library-added", (event) => {
+      if (event.payload.mountPoint !== mountPoint) return;
+      settled = true;
+      unlisten();
+      if (event.payload.library) {
+        toast.success("Steam library added", { id: waiting });
+        finish();
+      } else if (event.payload.cancelled) {
+        toast.dismiss(waiting);
+      } else {
+        toast.error(`Failed to add Steam library: ${event.payload.error}`, { id: waiting });
+      }
+    });
 
-      // Steam command is fire-and-forget, so we ask user to confirm completion
-      const confirmed = await confirm({
-        title: "Configure Steam Library",
-        description: "Steam Storage Manager has been opened. Please add the drive in Steam, then click Confirm here to refresh.",
-        variant: "default",
-        confirmText: "Refresh",
-        cancelText: "Cancel"
+    try {
+      const config: SteamInjectionConfig = { mountPoint, mode };
+      const used = await invoke<SteamInjectionMode>("inject_steam_library", { config });
+      if (used === "manual") {
+        unlisten();
+        toast.info("Add the drive in Steam's storage settings", { id: waiting });
+        return;
+      }
+      if (used === "auto") {
+        unlisten();
+        toast.success("Steam library added", { id: waiting });
+        finish();
+        return;
+      }
+
+      // A library already on the drive is reported before the command returns
+      if (settled) return;
+      toast.loading("Add the drive in Steam's storage settings", {
+        id: waiting,
+        action: {
+          label: "Cancel",
+          onClick: () => { invoke("cancel_steam_library_wait"); },
+        },
       });
+    } catch (e) {
+      unlisten();
+      toast.error(`Failed to add Steam library: ${e}`, { id: waiting });
+    }
+  };
 
-      if (confirmed) {
-        refresh();
-        fetchSteamState();
-        toast.success("Refreshed device information");
+  const handleCheckHealth = async () => {
+    setCheckingHealth(true);
+    try {
+      const result = await invoke<DeviceInfo[]>("check_disk_health");
+      setHealthByPath(Object.fromEntries(
+        result.filter((d) => d.path).map((d) => [d.path, { health: d.health, healthWarnings: d.healthWarnings }])
+      ));
+      const warnings = result.reduce((n, d) => n + (d.healthWarnings?.length ?? 0), 0);
+      if (warnings > 0) {
+        toast.warning("Some drives report health problems");
+      } else {
+        toast.success("No drive health problems found");
       }
     } catch (e) {
-      toast.error(`Failed to open Steam settings: ${e}`);
+      toast.error(`Failed to check drive health: ${e}`);
+    } finally {
+      setCheckingHealth(false);
     }
   };
 
@@ -149,6 +282,10 @@ function App() {
                 />
               </Button>
 
+              <Button variant="outline" size="icon" onClick={handleCheckHealth} disabled={checkingHealth} title="Check drive health">
+                <HeartPulse className={`h-4 w-4 ${checkingHealth ? "animate-pulse" : ""}`} />
+              </Button>
+
               <Tooltip>
                 <TooltipTrigger asChild>
                   <div className="inline-block">
@@ -199,18 +336,27 @@ function App() {
             </div>
           ) : (
             <div className="grid gap-4" style={{ gridTemplateColumns: 'repeat(auto-fill, minmax(450px, 1fr))' }}>
-              {devices.map((device) => (
-                <DeviceCard
-                  key={device.uuid || device.name}
-                  device={device}
-                  steamLibraries={steamState?.libraries}
-                  onMount={handleMountClick}
-                  onUnmount={handleUnmount}
-                  onDeconfigure={handleDeconfigure}
-                  onRepair={handleRepair}
-                  onConfigureSteam={handleConfigureSteam}
-                />
-              ))}
+              {/* Keep partitions of the same physical disk next to each other */}
+              {[...devices]
+                .sort((a, b) => (a.metadata?.diskId ?? "").localeCompare(b.metadata?.diskId ?? ""))
+                .map((device) => (
+                  <DeviceCard
+                    key={device.uuid || device.name}
+                    device={{ ...device, ...healthByPath[device.path] }}
+                    steamLibraries={steamState?.libraries}
+                    onMount={handleMountClick}
+                    onUnlock={setUnlockDevice}
+                    onRename={setRenameDevice}
+                    onMoveGames={setMoveGamesDevice}
+                    onFixCompatdata={handleFixCompatdata}
+                    onFormat={setFormatDevice}
+                    onUnmount={handleUnmount}
+                    onResolveConflict={handleResolveConflict}
+                    onDeconfigure={handleDeconfigure}
+                    onRepair={handleRepair}
+                    onConfigureSteam={handleConfigureSteam}
+                  />
+                ))}
             </div>
           )}
         </main>
@@ -223,6 +369,36 @@ function App() {
         onSuccess={() => { refresh(); fetchSteamState(); }}
       />
 
+      <UnlockDialog
+        device={unlockDevice}
+        open={unlockDevice !== null}
+        onOpenChange={(open) => !open && setUnlockDevice(null)}
+        onSuccess={refresh}
+      />
+
+      <RenameDialog
+        device={renameDevice}
+        open={renameDevice !== null}
+        onOpenChange={(open) => !open && setRenameDevice(null)}
+        onSuccess={() => { refresh(); fetchSteamState(); }}
+      />
+
+      <MoveAppDialog
+        device={moveGamesDevice}
+        libraries={steamState?.libraries ?? []}
+        steamVdfPath={steamState?.vdfPath}
+        open={moveGamesDevice !== null}
+        onOpenChange={(open) => !open && setMoveGamesDevice(null)}
+        onSuccess={() => { refresh(); fetchSteamState(); }}
+      />
+
+      <FormatDialog
+        device={formatDevice}
+        open={formatDevice !== null}
+        onOpenChange={(open) => !open && setFormatDevice(null)}
+        onSuccess={(device) => { refresh(); handleMountClick(device); }}
+      />
+
       <SettingsDialog
         open={settingsOpen}
         onOpenChange={setSettingsOpen}
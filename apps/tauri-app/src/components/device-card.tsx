@@ -1,34 +1,62 @@
-import { HardDrive, AlertTriangle, CheckCircle2, ChevronDown, ChevronUp, Gamepad2, X, CloudOff } from "lucide-react";
+import { HardDrive, AlertTriangle, CheckCircle2, ChevronDown, ChevronUp, Gamepad2, X, CloudOff, Lock, Pencil, Eraser, ArrowRightLeft, Wrench } from "lucide-react";
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { Badge } from "@/components/ui/badge";
 import { Button } from "@/components/ui/button";
 import { Collapsible, CollapsibleContent, CollapsibleTrigger } from "@/components/ui/collapsible";
 import { formatBytes } from "@/lib/utils";
-import type { DeviceInfo } from "@/types";
+import type { DeviceInfo, HealthWarning, MountConflict } from "@/types";
 import { useState } from "react";
 
 interface DeviceCardProps {
     device: DeviceInfo;
     steamLibraries?: string[];
     onMount?: (device: DeviceInfo) => void;
+    onUnlock?: (device: DeviceInfo) => void;
+    onRename?: (device: DeviceInfo) => void;
+    onMoveGames?: (device: DeviceInfo) => void;
+    onFixCompatdata?: (device: DeviceInfo) => void;
+    onFormat?: (device: DeviceInfo) => void;
     onUnmount?: (device: DeviceInfo) => void;
+    onResolveConflict?: (conflict: MountConflict) => void;
     onDeconfigure?: (device: DeviceInfo) => void;
     onRepair?: (device: DeviceInfo) => void;
     onConfigureSteam?: (device: DeviceInfo) => void;
 }
 
+function describeHealthWarning(warning: HealthWarning): string {
+    switch (warning.type) {
+        case "smartFailed":
+            return "SMART self-assessment failed";
+        case "highTemperature":
+            return `Running hot (${warning.celsius}°C)`;
+        case "worn":
+            return `${warning.percentageUsed}% of rated endurance used`;
+        case "mediaErrors":
+            return `${warning.count} media errors`;
+        case "badSectors":
+            return `${warning.reallocated} reallocated, ${warning.pending} pending sectors`;
+    }
+}
+
 export function DeviceCard({
     device,
     steamLibraries,
     onMount,
+    onUnlock,
+    onRename,
+    onMoveGames,
+    onFixCompatdata,
+    onFormat,
     onUnmount,
+    onResolveConflict,
     onDeconfigure,
     onRepair,
     onConfigureSteam,
 }: DeviceCardProps) {
     const displayName = device.label || device.name;
-    const fsLabel = device.fstype.toUpperCase();
+    const fsLabel = device.encryption === "bitlocker" ? "BITLOCKER" : device.encryption ? "LUKS" : device.fstype.toUpperCase();
     const [isOpen, setIsOpen] = useState(false);
+    const brokenCompatdata = device.compatdata?.filter(status => status.needsRedirect && !status.redirection) ?? [];
 
     // Check for Steam library match
     const mountpoint = device.mountpoint || device.managedEntry?.mountPoint;
@@ -44,6 +72,14 @@ export function DeviceCard({
                 </Badge>
             );
         }
+        if (device.encryption) {
+            return (
+                <Badge variant="outline" className="border-blue-500 text-blue-600 dark:text-blue-400">
+                    <Lock className="h-3 w-3 mr-1" />
+                    Locked
+                </Badge>
+            );
+        }
         if (device.isMounted) {
             return <Badge variant="success">Mounted</Badge>;
         }
@@ -84,6 +120,11 @@ export function DeviceCard({
                                 Removable
                             </Badge>
                         )}
+                        {device.deviceKind && device.deviceKind !== "partition" && (
+                            <Badge variant="outline" className="px-1.5 h-5 text-[10px] font-mono uppercase bg-background">
+                                {device.deviceKind === "disk" ? "whole disk" : device.deviceKind}
+                            </Badge>
+                        )}
                         <Badge variant="outline" className="px-1.5 h-5 text-[10px] font-mono bg-background">{fsLabel}</Badge>
                         {renderStatusBadge()}
                     </div>
@@ -99,14 +140,49 @@ export function DeviceCard({
                         </>
                     )}
 
-                    {/* Size - only show for online devices with valid size */}
-                    {!device.isOffline && device.size > 0 && (
+                    {/* Drive - tells apart identically labelled partitions */}
+                    {device.metadata?.model && (
+                        <>
+                            <span className="text-muted-foreground">Drive</span>
+                            <span className="text-right">
+                                {[device.metadata.vendor, device.metadata.model].filter(Boolean).join(" ")}
+                                {device.metadata.serial && (
+                                    <span className="font-mono text-xs text-muted-foreground"> ({device.metadata.serial})</span>
+                                )}
+                            </span>
+                        </>
+                    )}
+
+                    {/* Size - offline devices show the last-known size */}
+                    {device.size > 0 && (
                         <>
                             <span className="text-muted-foreground">Size</span>
                             <span className="text-right">{formatBytes(device.size)}</span>
                         </>
                     )}
 
+                    {device.usage && (
+                        <>
+                            <span className="text-muted-foreground">Free</span>
+                            <div className="flex flex-col items-end gap-1">
+                                <span className="text-right">
+                                    {formatBytes(device.usage.available)} of {formatBytes(device.usage.total)}
+                                    {device.usageUpdatedAt && (
+                                        <span className="text-xs text-muted-foreground">
+                                            {" "}(as of {new Date(device.usageUpdatedAt * 1000).toLocaleDateString()})
+                                        </span>
+                                    )}
+                                </span>
+                                <div className="h-1.5 w-full rounded-full bg-muted overflow-hidden">
+                                    <div
+                                        className="h-full bg-primary"
+                                        style={{ width: `${device.usage.total > 0 ? (device.usage.used / device.usage.total) * 100 : 0}%` }}
+                                    />
+                                </div>
+                            </div>
+                        </>
+                    )}
+
                     {
                         device.mountpoint && (
                             <>
@@ -116,6 +192,50 @@ export function DeviceCard({
                         )
                     }
 
+                    {/* Second mount made by udisks next to the configured one */}
+                    {device.conflicts?.map((conflict) => (
+                        <div key={conflict.path} className="contents">
+                            <span className="text-yellow-600 dark:text-yellow-400 flex items-center gap-1">
+                                <AlertTriangle className="h-3 w-3" />
+                                Conflict
+                            </span>
+                            <div className="flex items-center justify-end gap-2">
+                                <span className="font-mono text-xs truncate" title={conflict.path}>
+                                    Also automounted at {conflict.path}
+                                </span>
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    className="h-6 px-2 text-xs"
+                                    onClick={() => onResolveConflict?.(conflict)}
+                                >
+                                    Unmount
+                                </Button>
+                            </div>
+                        </div>
+                    ))}
+
+                    {device.health && (
+                        <>
+                            <span className="text-muted-foreground">Health</span>
+                            <span className="text-xs text-right">
+                                {device.health.passed === false ? "Failing" : device.health.passed ? "OK" : "Unknown"}
+                                {device.health.temperatureCelsius != null && ` · ${device.health.temperatureCelsius}°C`}
+                                {device.health.percentageUsed != null && ` · ${device.health.percentageUsed}% used`}
+                            </span>
+                        </>
+                    )}
+
+                    {device.healthWarnings?.map((warning) => (
+                        <div key={warning.type} className="contents">
+                            <span className="text-destructive flex items-center gap-1">
+                                <AlertTriangle className="h-3 w-3" />
+                                Drive
+                            </span>
+                            <span className="text-xs text-right text-destructive">{describeHealthWarning(warning)}</span>
+                        </div>
+                    ))}
+
                     {device.uuid && (
                         <>
                             <span className="text-muted-foreground">UUID</span>
@@ -139,6 +259,31 @@ export function DeviceCard({
                             </span>
                         </>
                     )}
+
+                    {device.installedApps && device.installedApps.length > 0 && (
+                        <>
+                            <span className="text-muted-foreground">Games</span>
+                            <span
+                                className="text-right truncate"
+                                title={device.installedApps.map(app => app.name).join("\n")}
+                            >
+                                {device.installedApps.length} installed
+                            </span>
+                        </>
+                    )}
+
+                    {brokenCompatdata.map((status) => (
+                        <div key={status.library} className="contents">
+                            <span className="text-destructive flex items-center gap-1">
+                                <AlertTriangle className="h-3 w-3" />
+                                Proton
+                            </span>
+                            <span className="text-xs text-right text-destructive" title={status.library}>
+                                Prefixes on {status.fstype.toUpperCase()} will not work
+                                {status.affectedAppids.length > 0 && ` (${status.affectedAppids.length} games)`}
+                            </span>
+                        </div>
+                    ))}
                 </div>
 
                 {device.managedEntry?.rawContent && (
@@ -211,7 +356,60 @@ export function DeviceCard({
                                     Repair
                                 </Button>
                             )}
-                            {device.isMounted ? (
+                            {!device.encryption && (
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => onRename?.(device)}
+                                    title="Change volume label"
+                                >
+                                    <Pencil className="h-4 w-4 mr-1" />
+                                    Rename
+                                </Button>
+                            )}
+                            {device.installedApps && device.installedApps.length > 0 && (
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => onMoveGames?.(device)}
+                                    title="Move games to another Steam library"
+                                >
+                                    <ArrowRightLeft className="h-4 w-4 mr-1" />
+                                    Move Games
+                                </Button>
+                            )}
+                            {brokenCompatdata.length > 0 && (
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => onFixCompatdata?.(device)}
+                                    title="Keep Proton prefixes on internal storage"
+                                >
+                                    <Wrench className="h-4 w-4 mr-1" />
+                                    Fix Proton
+                                </Button>
+                            )}
+                            {!device.encryption && !device.isMounted && !device.managedEntry && (
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => onFormat?.(device)}
+                                    title="Erase and format"
+                                >
+                                    <Eraser className="h-4 w-4 mr-1" />
+                                    Format
+                                </Button>
+                            )}
+                            {device.encryption ? (
+                                <Button
+                                    size="sm"
+                                    className="flex-1"
+                                    onClick={() => onUnlock?.(device)}
+                                >
+                                    <Lock className="h-4 w-4 mr-1" />
+                                    Unlock
+                                </Button>
+                            ) : device.isMounted ? (
                                 <Button
                                     variant="outline"
                                     size="sm"
@@ -0,0 +1,158 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { AlertTriangle } from "lucide-react";
+import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, DialogTitle } from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Checkbox } from "@/components/ui/checkbox";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
+import { toast } from "sonner";
+import type { DeviceInfo, FormatFilesystem, FormatPlan } from "@/types";
+
+interface FormatDialogProps {
+    device: DeviceInfo | null;
+    open: boolean;
+    onOpenChange: (open: boolean) => void;
+    // Receives the formatted device, to continue with the mount dialog
+    onSuccess: (device: DeviceInfo) => void;
+}
+
+export function FormatDialog({ device, open, onOpenChange, onSuccess }: FormatDialogProps) {
+    const [filesystem, setFilesystem] = useState<FormatFilesystem>("exfat");
+    const [label, setLabel] = useState("");
+    const [wholeDisk, setWholeDisk] = useState(false);
+    const [plan, setPlan] = useState<FormatPlan | null>(null);
+    const [loading, setLoading] = useState(false);
+
+    useEffect(() => {
+        if (open) {
+            setLabel(device?.label ?? "");
+            setWholeDisk(false);
+            setPlan(null);
+        }
+    }, [open, device]);
+
+    // Any change invalidates a plan that was already shown
+    useEffect(() => setPlan(null), [filesystem, label, wholeDisk]);
+
+    const pkname = device?.metadata?.pkname;
+    const canRepartition = device?.deviceKind === "disk" || (device?.deviceKind === "partition" && Boolean(pkname));
+    const target = wholeDisk && device?.deviceKind === "partition" ? `/dev/${pkname}` : device?.path;
+
+    const handlePlan = async () => {
+        if (!target || !label.trim()) return;
+
+        setLoading(true);
+        try {
+            const result = await invoke<FormatPlan>("plan_format", {
+                device: target,
+                options: { filesystem, label: label.trim(), partitionTable: wholeDisk },
+            });
+            setPlan(result);
+        } catch (e) {
+            toast.error(`Cannot format: ${e}`);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const handleFormat = async () => {
+        if (!plan) return;
+
+        setLoading(true);
+        try {
+            const formatted = await invoke<DeviceInfo>("format_device", { plan, token: plan.token });
+            toast.success(`Formatted ${formatted.path} as ${filesystem}`);
+            onOpenChange(false);
+            onSuccess(formatted);
+        } catch (e) {
+            toast.error(`Format failed: ${e}`);
+            setPlan(null);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    return (
+        <Dialog open={open} onOpenChange={onOpenChange}>
+            <DialogContent className="sm:max-w-[460px]">
+                <DialogHeader>
+                    <DialogTitle>Format Drive</DialogTitle>
+                    <DialogDescription>
+                        Prepare {device?.label || device?.name} for use as a Steam library
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="flex flex-col gap-4 py-2">
+                    <div className="space-y-2">
+                        <Label>Filesystem</Label>
+                        <Select value={filesystem} onValueChange={(v) => setFilesystem(v as FormatFilesystem)}>
+                            <SelectTrigger className="h-9">
+                                <SelectValue />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="exfat">exFAT (SD cards, works everywhere)</SelectItem>
+                                <SelectItem value="ntfs">NTFS (shared with Windows)</SelectItem>
+                                <SelectItem value="ext4">ext4 (SteamOS only)</SelectItem>
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    <div className="space-y-2">
+                        <Label htmlFor="format-label">Label</Label>
+                        <Input
+                            id="format-label"
+                            value={label}
+                            onChange={(e) => setLabel(e.target.value)}
+                            className="h-9"
+                        />
+                    </div>
+                    {canRepartition && (
+                        <div className="flex items-start gap-2">
+                            <Checkbox
+                                id="format-whole-disk"
+                                checked={wholeDisk}
+                                onCheckedChange={(checked) => setWholeDisk(checked === true)}
+                                className="mt-0.5"
+                            />
+                            <div className="space-y-1">
+                                <Label htmlFor="format-whole-disk" className="cursor-pointer">
+                                    Erase the whole drive
+                                </Label>
+                                <p className="text-[10px] text-muted-foreground leading-tight">
+                                    Creates a new GPT partition table with a single partition.
+                                </p>
+                            </div>
+                        </div>
+                    )}
+
+                    {plan && (
+                        <div className="rounded-md border border-destructive/50 bg-destructive/10 p-3 text-xs space-y-1">
+                            <div className="flex items-center gap-1 font-medium text-destructive">
+                                <AlertTriangle className="h-4 w-4" />
+                                All data on {plan.device} will be lost
+                            </div>
+                            {plan.affected.length > 0 && (
+                                <p className="text-muted-foreground">Erased: {plan.affected.join(", ")}</p>
+                            )}
+                            <p className="text-muted-foreground">New filesystem on {plan.target}</p>
+                        </div>
+                    )}
+                </div>
+
+                <DialogFooter>
+                    <Button variant="outline" onClick={() => onOpenChange(false)} className="h-9">Cancel</Button>
+                    {plan ? (
+                        <Button variant="destructive" onClick={handleFormat} disabled={loading} className="h-9">
+                            {loading ? "Formatting..." : "Erase and Format"}
+                        </Button>
+                    ) : (
+                        <Button onClick={handlePlan} disabled={loading || !label.trim()} className="h-9">
+                            Continue
+                        </Button>
+                    )}
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -4,13 +4,27 @@ import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, D
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
+import { Checkbox } from "@/components/ui/checkbox";
 import { Badge } from "@/components/ui/badge";
 import { useConfirm } from "@/hooks/use-confirm";
 import { toast } from "sonner";
 import { Info, CheckCircle2, Circle, HardDrive, Usb } from "lucide-react";
-import type { DeviceInfo, MountConfig, FstabPreview, MountConfigSuggestion } from "@/types";
+import type { DeviceInfo, MountConfig, FstabPreview, MountConfigSuggestion, PersistenceBackend } from "@/types";
 import { cn } from "@/lib/utils";
 
+const PERSISTENCE_OPTIONS: { value: PersistenceBackend; label: string; description: string }[] = [
+    {
+        value: "fstab",
+        label: "/etc/fstab",
+        description: "A managed entry in /etc/fstab.",
+    },
+    {
+        value: "systemd",
+        label: "systemd Units",
+        description: "Mount units in /etc/systemd/system, for systems where /etc/fstab is read-only or overwritten.",
+    },
+];
+
 interface MountSettingsDialogProps {
     device: DeviceInfo | null;
     open: boolean;
@@ -31,12 +45,20 @@ export function MountSettingsDialog({ device, open, onOpenChange, onSuccess }: M
     const [deviceTimeout, setDeviceTimeout] = useState(3);
     const [idleTimeout, setIdleTimeout] = useState(60);
 
+    // Keep the SteamOS automounter (udisks2) away from this drive
+    const [ignoreUdisks, setIgnoreUdisks] = useState(false);
+
+    // systemd units for systems whose /etc/fstab cannot be edited
+    const [persistence, setPersistence] = useState<PersistenceBackend>("fstab");
+
     const [preview, setPreview] = useState<FstabPreview | null>(null);
     const { confirm } = useConfirm();
 
     // Fetch suggestion and defaults when dialog opens
     useEffect(() => {
         if (open && device) {
+            setPersistence(device.persistence ?? "fstab");
+
             // Get suggestion from backend
             invoke<MountConfigSuggestion>("get_mount_config_suggestion", { uuid: device.uuid })
                 .then((sugg) => {
@@ -47,6 +69,7 @@ export function MountSettingsDialog({ device, open, onOpenChange, onSuccess }: M
                     setMediaType(sugg.defaultConfig.mediaType);
                     setDeviceTimeout(sugg.defaultConfig.deviceTimeoutSecs || 0);
                     setIdleTimeout(sugg.defaultConfig.idleTimeoutSecs || 0);
+                    setIgnoreUdisks(sugg.defaultConfig.deviceType === "removable");
                 })
                 .catch(err => {
                     console.error("Failed to get suggestion:", err);
@@ -107,6 +130,8 @@ export function MountSettingsDialog({ device, open, onOpenChange, onSuccess }: M
                 mountPoint,
                 forceRootCreation: forceRoot,
                 injectSteam: false,
+                persistence,
+                ignoreUdisks,
             };
 
             await invoke("mount_device", { config });
@@ -296,9 +321,52 @@ export function MountSettingsDialog({ device, open, onOpenChange, onSuccess }: M
                                 </div>
                             </div>
 
+                            {/* Persistence backend */}
+                            <div className="space-y-2">
+                                <Label>Save Configuration In</Label>
+                                <div className="grid grid-cols-2 gap-3">
+                                    {PERSISTENCE_OPTIONS.map((opt) => (
+                                        <div
+                                            key={opt.value}
+                                            className={cn(
+                                                "flex flex-col gap-1 rounded-md border p-2 cursor-pointer transition-colors",
+                                                persistence === opt.value
+                                                    ? "border-primary bg-primary/10"
+                                                    : "border-muted hover:bg-muted/50"
+                                            )}
+                                            onClick={() => setPersistence(opt.value)}
+                                        >
+                                            <div className="flex items-center justify-between">
+                                                <span className="text-sm font-medium">{opt.label}</span>
+                                                {persistence === opt.value && <CheckCircle2 className="h-3 w-3 text-primary" />}
+                                            </div>
+                                            <p className="text-[10px] text-muted-foreground leading-tight">{opt.description}</p>
+                                        </div>
+                                    ))}
+                                </div>
+                            </div>
+
+                            {/* udisks2 coexistence */}
+                            <div className="flex items-start gap-2">
+                                <Checkbox
+                                    id="ignore-udisks"
+                                    checked={ignoreUdisks}
+                                    onCheckedChange={(checked) => setIgnoreUdisks(checked === true)}
+                                    className="mt-0.5"
+                                />
+                                <div className="space-y-1">
+                                    <Label htmlFor="ignore-udisks" className="cursor-pointer">
+                                        Prevent SteamOS automount
+                                    </Label>
+                                    <p className="text-[10px] text-muted-foreground leading-tight">
+                                        Installs a udev rule so the system automounter no longer mounts this drive under /run/media.
+                                    </p>
+                                </div>
+                            </div>
+
                             {/* Preview */}
                             <div className="space-y-2">
-                                <Label>Fstab Entry Preview</Label>
+                                <Label>{persistence === "fstab" ? "Fstab Entry Preview" : "Mount Preview"}</Label>
                                 <div className="rounded-md bg-muted p-2.5 overflow-x-auto border border-border shadow-inner">
                                     {preview ? (
                                         <code className="text-[11px] font-mono whitespace-pre block min-w-max">
@@ -0,0 +1,135 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, DialogTitle } from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Label } from "@/components/ui/label";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
+import { toast } from "sonner";
+import type { DeviceInfo, InstalledApp, MovedApp, MoveProgress } from "@/types";
+
+interface MoveAppDialogProps {
+    device: DeviceInfo | null;
+    // All registered Steam library paths, from the Steam state
+    libraries: string[];
+    steamVdfPath?: string;
+    open: boolean;
+    onOpenChange: (open: boolean) => void;
+    onSuccess: () => void;
+}
+
+function formatGiB(bytes: number): string {
+    return `${(bytes / 1024 ** 3).toFixed(1)} GiB`;
+}
+
+export function MoveAppDialog({ device, libraries, steamVdfPath, open, onOpenChange, onSuccess }: MoveAppDialogProps) {
+    const [appid, setAppid] = useState("");
+    const [target, setTarget] = useState("");
+    const [progress, setProgress] = useState<MoveProgress | null>(null);
+    const [loading, setLoading] = useState(false);
+
+    const apps = device?.installedApps ?? [];
+    const app: InstalledApp | undefined = apps.find(a => String(a.appid) === appid);
+    const targets = libraries.filter(lib => lib !== app?.library);
+
+    useEffect(() => {
+        if (open) {
+            setAppid("");
+            setTarget("");
+            setProgress(null);
+        }
+    }, [open, device]);
+
+    useEffect(() => {
+        const unlisten = listen<MoveProgress>("app-move-progress", (event) => setProgress(event.payload));
+        return () => { unlisten.then(f => f()); };
+    }, []);
+
+    const handleMove = async () => {
+        if (!app || !target) return;
+
+        setLoading(true);
+        try {
+            const moved = await invoke<MovedApp>("move_steam_app", {
+                appid: app.appid,
+                sourceLibrary: app.library,
+                targetLibrary: target,
+                steamVdfPath: steamVdfPath || null,
+            });
+            if (moved.leftovers.length > 0) {
+                toast.warning(`Moved ${app.name}, but some original files could not be deleted: ${moved.leftovers.join(", ")}`);
+            } else {
+                toast.success(`Moved ${app.name}`);
+            }
+            onSuccess();
+            onOpenChange(false);
+        } catch (e) {
+            toast.error(`Move failed: ${e}`);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const percent = progress && progress.bytesTotal > 0 ? Math.round(progress.bytesDone * 100 / progress.bytesTotal) : 0;
+
+    return (
+        <Dialog open={open} onOpenChange={(next) => !loading && onOpenChange(next)}>
+            <DialogContent className="sm:max-w-[460px]">
+                <DialogHeader>
+                    <DialogTitle>Move Games</DialogTitle>
+                    <DialogDescription>
+                        Move a game from {device?.label || device?.name} to another Steam library. Steam is closed while the library is updated.
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="flex flex-col gap-4 py-2">
+                    <div className="space-y-2">
+                        <Label>Game</Label>
+                        <Select value={appid} onValueChange={setAppid} disabled={loading}>
+                            <SelectTrigger className="h-9">
+                                <SelectValue placeholder="Select a game" />
+                            </SelectTrigger>
+                            <SelectContent>
+                                {apps.map(a => (
+                                    <SelectItem key={a.appid} value={String(a.appid)}>
+                                        {a.name} ({formatGiB(a.sizeOnDisk)})
+                                    </SelectItem>
+                                ))}
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    <div className="space-y-2">
+                        <Label>Target Library</Label>
+                        <Select value={target} onValueChange={setTarget} disabled={loading || !app}>
+                            <SelectTrigger className="h-9 font-mono text-xs">
+                                <SelectValue placeholder="Select a library" />
+                            </SelectTrigger>
+                            <SelectContent>
+                                {targets.map(lib => (
+                                    <SelectItem key={lib} value={lib} className="font-mono text-xs">{lib}</SelectItem>
+                                ))}
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    {loading && progress && (
+                        <div className="space-y-1">
+                            <div className="h-2 w-full rounded bg-muted overflow-hidden">
+                                <div className="h-full bg-primary transition-all" style={{ width: `${percent}%` }} />
+                            </div>
+                            <p className="text-xs text-muted-foreground text-right">
+                                {formatGiB(progress.bytesDone)} / {formatGiB(progress.bytesTotal)} ({percent}%)
+                            </p>
+                        </div>
+                    )}
+                </div>
+
+                <DialogFooter>
+                    <Button variant="outline" onClick={() => onOpenChange(false)} disabled={loading}>Cancel</Button>
+                    <Button onClick={handleMove} disabled={loading || !app || !target}>
+                        {loading ? "Moving..." : "Move"}
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
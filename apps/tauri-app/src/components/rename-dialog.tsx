@@ -0,0 +1,131 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, DialogTitle } from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Checkbox } from "@/components/ui/checkbox";
+import { toast } from "sonner";
+import type { DeviceInfo } from "@/types";
+
+interface RenameDialogProps {
+    device: DeviceInfo | null;
+    open: boolean;
+    onOpenChange: (open: boolean) => void;
+    onSuccess: () => void;
+}
+
+// Mirrors disk::validate_label; the backend has the final say
+const LABEL_LIMITS: Record<string, number> = { ntfs: 128, exfat: 11, vfat: 11, ext2: 16, ext3: 16, ext4: 16 };
+
+export function RenameDialog({ device, open, onOpenChange, onSuccess }: RenameDialogProps) {
+    const [label, setLabel] = useState("");
+    const [renameMountPoint, setRenameMountPoint] = useState(true);
+    const [updateSteam, setUpdateSteam] = useState(true);
+    const [loading, setLoading] = useState(false);
+
+    useEffect(() => {
+        if (open) setLabel(device?.label ?? "");
+    }, [open, device]);
+
+    const maxLength = LABEL_LIMITS[device?.fstype ?? ""] ?? 11;
+    const trimmed = label.trim();
+    const unchanged = trimmed === (device?.label ?? "");
+
+    const handleRename = async () => {
+        if (!device?.uuid || !trimmed || unchanged) return;
+
+        setLoading(true);
+        try {
+            await invoke("relabel_device", {
+                uuid: device.uuid,
+                label: trimmed,
+                renameMountPoint: Boolean(device.managedEntry) && renameMountPoint,
+                updateSteam,
+            });
+            toast.success(`Renamed to ${trimmed}`);
+            onSuccess();
+            onOpenChange(false);
+        } catch (e) {
+            toast.error(`Rename failed: ${e}`);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    return (
+        <Dialog open={open} onOpenChange={onOpenChange}>
+            <DialogContent className="sm:max-w-[420px]">
+                <DialogHeader>
+                    <DialogTitle>Rename Volume</DialogTitle>
+                    <DialogDescription>
+                        Change the {device?.fstype.toUpperCase()} label of {device?.label || device?.name}
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="flex flex-col gap-4 py-2">
+                    <div className="space-y-2">
+                        <Label htmlFor="rename-label">Label</Label>
+                        <Input
+                            id="rename-label"
+                            autoFocus
+                            value={label}
+                            maxLength={maxLength}
+                            onChange={(e) => setLabel(e.target.value)}
+                            onKeyDown={(e) => e.key === "Enter" && handleRename()}
+                            className="h-9"
+                        />
+                        <p className="text-[10px] text-muted-foreground leading-tight">
+                            Up to {maxLength} characters. {device?.fstype === "ntfs" || device?.fstype === "exfat" || device?.fstype === "vfat"
+                                ? "The volume is unmounted briefly while the label is written."
+                                : ""}
+                        </p>
+                    </div>
+                    {device?.managedEntry && (
+                        <div className="flex items-start gap-2">
+                            <Checkbox
+                                id="rename-mount-point"
+                                checked={renameMountPoint}
+                                onCheckedChange={(checked) => setRenameMountPoint(checked === true)}
+                                className="mt-0.5"
+                            />
+                            <div className="space-y-1">
+                                <Label htmlFor="rename-mount-point" className="cursor-pointer">
+                                    Rename mount point
+                                </Label>
+                                <p className="text-[10px] text-muted-foreground leading-tight">
+                                    Moves {device.managedEntry.mountPoint} to match the new label.
+                                </p>
+                            </div>
+                        </div>
+                    )}
+                    {device?.managedEntry && renameMountPoint && device.steamLibraries.length > 0 && (
+                        <div className="flex items-start gap-2">
+                            <Checkbox
+                                id="rename-update-steam"
+                                checked={updateSteam}
+                                onCheckedChange={(checked) => setUpdateSteam(checked === true)}
+                                className="mt-0.5"
+                            />
+                            <div className="space-y-1">
+                                <Label htmlFor="rename-update-steam" className="cursor-pointer">
+                                    Update Steam libraries
+                                </Label>
+                                <p className="text-[10px] text-muted-foreground leading-tight">
+                                    Steam is restarted to pick up the new library paths.
+                                </p>
+                            </div>
+                        </div>
+                    )}
+                </div>
+
+                <DialogFooter>
+                    <Button variant="outline" onClick={() => onOpenChange(false)} className="h-9">Cancel</Button>
+                    <Button onClick={handleRename} disabled={loading || !trimmed || unchanged} className="h-9">
+                        {loading ? "Renaming..." : "Rename"}
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
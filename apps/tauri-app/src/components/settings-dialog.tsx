@@ -8,6 +8,7 @@ import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 import { toast } from "sonner";
 import { Save, RefreshCw, FolderOpen } from "lucide-react";
+import { SteamInstall } from "@/types";
 
 interface SettingsDialogProps {
     open: boolean;
@@ -21,6 +22,7 @@ const VDF_PATH_KEY = "steamLibraryVdfPath";
 export function SettingsDialog({ open, onOpenChange, onSaved, store }: SettingsDialogProps) {
     const [vdfPath, setVdfPath] = useState("");
     const [loading, setLoading] = useState(false);
+    const [installs, setInstalls] = useState<SteamInstall[]>([]);
 
     // Initialize from store
     useEffect(() => {
@@ -50,6 +52,9 @@ export function SettingsDialog({ open, onOpenChange, onSaved, store }: SettingsD
         };
 
         loadSettings();
+        invoke<SteamInstall[]>("list_steam_installs")
+            .then(found => { if (active) setInstalls(found); })
+            .catch(e => console.error("Failed to discover Steam installations:", e));
 
         return () => { active = false; };
     }, [open, store]);
@@ -136,6 +141,22 @@ export function SettingsDialog({ open, onOpenChange, onSaved, store }: SettingsD
                         <p className="text-xs text-muted-foreground">
                             Browse to select <code>libraryfolders.vdf</code>. Steam must be closed to modify it.
                         </p>
+                        {installs.length > 1 && (
+                            <div className="flex flex-wrap gap-2">
+                                {installs.map(install => (
+                                    <Button
+                                        key={install.root}
+                                        variant={install.vdfPath === vdfPath ? "secondary" : "outline"}
+                                        size="sm"
+                                        onClick={() => setVdfPath(install.vdfPath)}
+                                        title={install.root}
+                                    >
+                                        {install.flavour === "flatpak" ? "Flatpak Steam" : "Native Steam"}
+                                        {install.isRunning && " (running)"}
+                                    </Button>
+                                ))}
+                            </div>
+                        )}
                     </div>
                 </div>
 
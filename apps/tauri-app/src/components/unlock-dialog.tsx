@@ -0,0 +1,100 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, DialogTitle } from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Checkbox } from "@/components/ui/checkbox";
+import { toast } from "sonner";
+import type { DeviceInfo } from "@/types";
+
+interface UnlockDialogProps {
+    device: DeviceInfo | null;
+    open: boolean;
+    onOpenChange: (open: boolean) => void;
+    onSuccess: () => void;
+}
+
+export function UnlockDialog({ device, open, onOpenChange, onSuccess }: UnlockDialogProps) {
+    const [passphrase, setPassphrase] = useState("");
+    const [persist, setPersist] = useState(true);
+    const [loading, setLoading] = useState(false);
+
+    // Never keep the passphrase around after the dialog closes
+    useEffect(() => {
+        if (!open) setPassphrase("");
+    }, [open]);
+
+    const kind = device?.encryption === "bitlocker" ? "BitLocker" : "LUKS";
+
+    const handleUnlock = async () => {
+        if (!device?.uuid || !passphrase) return;
+
+        setLoading(true);
+        try {
+            const mapped = await invoke<string>("unlock_device", {
+                uuid: device.uuid,
+                passphrase,
+                persist,
+            });
+            toast.success(`Unlocked as ${mapped}. You can now mount it.`);
+            onSuccess();
+            onOpenChange(false);
+        } catch (e) {
+            toast.error(`Unlock failed: ${e}`);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    return (
+        <Dialog open={open} onOpenChange={onOpenChange}>
+            <DialogContent className="sm:max-w-[420px]">
+                <DialogHeader>
+                    <DialogTitle>Unlock {kind} Volume</DialogTitle>
+                    <DialogDescription>
+                        Enter the passphrase for {device?.label || device?.name}
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="flex flex-col gap-4 py-2">
+                    <div className="space-y-2">
+                        <Label htmlFor="unlock-passphrase">Passphrase</Label>
+                        <Input
+                            id="unlock-passphrase"
+                            type="password"
+                            autoFocus
+                            value={passphrase}
+                            onChange={(e) => setPassphrase(e.target.value)}
+                            onKeyDown={(e) => e.key === "Enter" && handleUnlock()}
+                            className="h-9"
+                        />
+                    </div>
+                    <div className="flex items-start gap-2">
+                        <Checkbox
+                            id="unlock-persist"
+                            checked={persist}
+                            onCheckedChange={(checked) => setPersist(checked === true)}
+                            className="mt-0.5"
+                        />
+                        <div className="space-y-1">
+                            <Label htmlFor="unlock-persist" className="cursor-pointer">
+                                Remember this volume
+                            </Label>
+                            <p className="text-[10px] text-muted-foreground leading-tight">
+                                Adds an entry to /etc/crypttab. The passphrase itself is never stored.
+                            </p>
+                        </div>
+                    </div>
+                </div>
+
+                <DialogFooter>
+                    <Button variant="outline" onClick={() => onOpenChange(false)} className="h-9">Cancel</Button>
+                    <Button onClick={handleUnlock} disabled={loading || !passphrase} className="h-9">
+                        {loading ? "Unlocking..." : "Unlock"}
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
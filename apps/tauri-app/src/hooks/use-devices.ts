@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import type { DeviceInfo } from "@/types";
+import { listen } from "@tauri-apps/api/event";
+import type { DeviceEventPayload, DeviceInfo } from "@/types";
 
 export function useDevices() {
     const [devices, setDevices] = useState<DeviceInfo[]>([]);
@@ -24,5 +25,15 @@ export function useDevices() {
         refresh();
     }, [refresh]);
 
+    // Reload the list whenever the backend monitor reports a change
+    useEffect(() => {
+        const unlisten = listen<DeviceEventPayload>("device-event", () => {
+            refresh();
+        });
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, [refresh]);
+
     return { devices, loading, error, refresh };
 }
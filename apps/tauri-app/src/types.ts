@@ -4,11 +4,94 @@ export interface ManagedEntryInfo {
     rawContent: string;
 }
 
+export interface InstalledApp {
+    appid: number;
+    name: string;
+    installdir: string;
+    sizeOnDisk: number;
+    stateFlags: number;
+    buildid: number;
+    lastUpdated: number;
+    library: string;
+}
+
+export interface CompatdataStatus {
+    library: string;
+    fstype: string;
+    needsRedirect: boolean;
+    redirection?: "symlink" | "bindMount";
+    target: string;
+    affectedAppids: number[];
+}
+
+export interface MovedApp {
+    app: InstalledApp;
+    leftovers: string[];
+}
+
+export interface MoveProgress {
+    bytesDone: number;
+    bytesTotal: number;
+}
+
 export interface SteamLibraryInfo {
     path: string;
     label: string;
 }
 
+export type DeviceKind = "partition" | "disk" | "crypt" | "lvm" | "raid";
+
+export type Encryption = "luks" | "bitlocker";
+
+export interface BlockMetadata {
+    diskId: string | null;
+    pkname: string | null;
+    model: string | null;
+    serial: string | null;
+    vendor: string | null;
+    wwn: string | null;
+    partlabel: string | null;
+    parttype: string | null;
+    hotplug: boolean;
+    logSec: number | null;
+    phySec: number | null;
+    discGran: number | null;
+}
+
+export interface SpaceUsage {
+    total: number;
+    used: number;
+    available: number;
+}
+
+export interface LibraryUsage {
+    path: string;
+    appCount: number;
+    reportedBytes: number;
+    actualBytes: number | null;
+}
+
+export interface DiskHealth {
+    disk: string;
+    model: string | null;
+    passed: boolean | null;
+    temperatureCelsius: number | null;
+    percentageUsed: number | null;
+    mediaErrors: number | null;
+    reallocatedSectors: number | null;
+    pendingSectors: number | null;
+    powerOnHours: number | null;
+}
+
+export type HealthWarning =
+    | { type: "smartFailed" }
+    | { type: "highTemperature"; celsius: number }
+    | { type: "worn"; percentageUsed: number }
+    | { type: "mediaErrors"; count: number }
+    | { type: "badSectors"; reallocated: number; pending: number };
+
+export type MountConflict = { type: "udisksAutomount"; path: string };
+
 // Device types matching the Rust backend
 export interface DeviceInfo {
     name: string;
@@ -19,17 +102,60 @@ export interface DeviceInfo {
     fstype: string;
     size: number;
     mountpoint: string | null;
+    mountpoints: string[];
+    conflicts: MountConflict[];
     isMounted: boolean;
     isDirty: boolean;
     isOffline: boolean;
     managedEntry?: ManagedEntryInfo;
+    persistence?: PersistenceBackend;
     fsSpec?: string;
     steamLibraries: SteamLibraryInfo[];
+    installedApps?: InstalledApp[];
+    compatdata?: CompatdataStatus[];
     rota?: boolean;
     removable?: boolean;
     transport?: string;
+    deviceKind?: DeviceKind;
+    metadata?: BlockMetadata;
+    encryption?: Encryption;
+    usage?: SpaceUsage;
+    usageUpdatedAt?: number;
+    health?: DiskHealth | null;
+    healthWarnings?: HealthWarning[];
+}
+
+export type FormatFilesystem = "exfat" | "ntfs" | "ext4";
+
+export interface FormatOptions {
+    filesystem: FormatFilesystem;
+    label: string;
+    partitionTable: boolean;
 }
 
+export interface FormatPlan {
+    device: string;
+    target: string;
+    options: FormatOptions;
+    wholeDisk: boolean;
+    affected: string[];
+    token: string;
+}
+
+export type DeviceEventKind =
+    | "deviceAdded"
+    | "deviceRemoved"
+    | "mounted"
+    | "unmounted"
+    | "becameDirty";
+
+export interface DeviceEventPayload {
+    kind: DeviceEventKind;
+    device: DeviceInfo;
+}
+
+export type PersistenceBackend = "fstab" | "systemd";
+
 export interface MountConfig {
     uuid: string;
     mediaType: "flash" | "rotational";
@@ -41,6 +167,8 @@ export interface MountConfig {
     forceRootCreation: boolean;
     injectSteam: boolean;
     steamLibraryPath?: string;
+    persistence?: PersistenceBackend;
+    ignoreUdisks?: boolean;
 }
 
 export interface OptionMetadata {
@@ -70,11 +198,32 @@ export interface FstabPreview {
     fstabLine: string;
 }
 
+export type RestartStrategy = "relaunch" | "supervisor" | "restartDisplayManager" | "askUser";
+
+export type SteamInjectionMode = "auto" | "semi" | "manual";
+
 export interface SteamInjectionConfig {
     mountPoint: string;
     libraryPath?: string;
     steamVdfPath?: string;
-    mode: "auto" | "semi" | "manual";
+    mode?: SteamInjectionMode;
+    restartStrategy?: RestartStrategy;
+}
+
+export interface SteamLibraryAddedPayload {
+    mountPoint: string;
+    library?: string;
+    error?: string;
+    cancelled: boolean;
+}
+
+export type SteamFlavour = "native" | "flatpak";
+
+export interface SteamInstall {
+    root: string;
+    vdfPath: string;
+    flavour: SteamFlavour;
+    isRunning: boolean;
 }
 
 export interface SteamState {
@@ -0,0 +1,316 @@
+//! Encrypted volume support (LUKS and BitLocker).
+//!
+//! Locked volumes are detected by their filesystem type (see
+//! [`BlockDevice::encryption`]). Unlocking runs `cryptsetup open` through the
+//! privileged context with the passphrase written to its stdin, so it never
+//! shows up on argv. The unlocked mapping appears as a
+//! [`DeviceKind::Crypt`](crate::disk::DeviceKind::Crypt) device and is mounted
+//! like any other device.
+//!
+//! For persistence, mappings are recorded in a managed block of
+//! `/etc/crypttab` (using the same markers as fstab). Entries use `none` as
+//! key file and `noauto,nofail`, so that the passphrase is never stored and
+//! boot never blocks on a prompt; the mapping is opened by the unlock flow or
+//! with `systemctl start systemd-cryptsetup@<name>`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::disk::{BlockDevice, Encryption};
+use crate::error::{CrypttabSnafu, Error, Result};
+use crate::executor::ExecutionContext;
+use crate::fstab;
+
+/// Default crypttab path.
+pub const CRYPTTAB_PATH: &str = "/etc/crypttab";
+
+/// cryptsetup exit code for a wrong passphrase ("no permission").
+const CRYPTSETUP_EXIT_NO_PERMISSION: i32 = 2;
+
+/// Returns the mapper name used when unlocking a device.
+///
+/// Follows the `luks-<UUID>` convention of udisks and systemd (`bitlk-<UUID>`
+/// for BitLocker), falling back to the kernel name if the volume has no UUID.
+pub fn mapper_name(device: &BlockDevice, encryption: Encryption) -> String {
+    let prefix = match encryption {
+        Encryption::Luks => "luks",
+        Encryption::BitLocker => "bitlk",
+    };
+    match &device.uuid {
+        Some(uuid) => format!("{}-{}", prefix, uuid),
+        None => format!("{}-{}", prefix, device.name),
+    }
+}
+
+/// Unlocks an encrypted volume with a passphrase.
+///
+/// Returns the path of the mapped device (`/dev/mapper/<name>`). If the
+/// volume is already unlocked, the existing mapping is returned.
+pub fn unlock_with_ctx(
+    device: &BlockDevice,
+    passphrase: &str,
+    ctx: &mut ExecutionContext,
+) -> Result<PathBuf> {
+    let encryption = device.encryption().ok_or_else(|| Error::Unlock {
+        device: device.path.display().to_string(),
+        message: "not an encrypted volume".to_string(),
+    })?;
+    if let Some(holder) = device.holders.first() {
+        return Ok(PathBuf::from(format!("/dev/mapper/{}", holder)));
+    }
+
+    let name = mapper_name(device, encryption);
+    let device_path = device.path.display().to_string();
+    let output = ctx.run_privileged_with_input(
+        "cryptsetup",
+        &[
+            "open",
+            "--type",
+            encryption.cryptsetup_type(),
+            "--key-file=-",
+            &device_path,
+            &name,
+        ],
+        passphrase,
+    )?;
+
+    match output.status.code() {
+        Some(0) => {}
+        Some(126) => return Err(Error::AuthenticationCancelled),
+        Some(CRYPTSETUP_EXIT_NO_PERMISSION) => {
+            return Err(Error::Unlock {
+                device: device_path,
+                message: "wrong passphrase".to_string(),
+            });
+        }
+        _ => {
+            return Err(Error::Unlock {
+                device: device_path,
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+    }
+
+    // Wait for udev to probe the new mapping, so that scans see its filesystem
+    let _ = ctx.run_privileged("udevadm", &["settle"]);
+
+    Ok(PathBuf::from(format!("/dev/mapper/{}", name)))
+}
+
+/// Closes an unlocked mapping (`cryptsetup close`).
+///
+/// The mapped device must be unmounted first.
+pub fn lock_with_ctx(name: &str, ctx: &mut ExecutionContext) -> Result<()> {
+    ctx.run_privileged_checked("cryptsetup", &["close", name])
+}
+
+/// A single `/etc/crypttab` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrypttabEntry {
+    /// Mapper name (the device appears as `/dev/mapper/<name>`).
+    pub name: String,
+    /// Encrypted device (e.g., "UUID=xxx").
+    pub device: String,
+    /// Key file, or "none" to ask for the passphrase.
+    pub key_file: String,
+    /// Options (e.g., "luks", "bitlk", "nofail").
+    pub options: Vec<String>,
+}
+
+impl CrypttabEntry {
+    /// Creates the managed entry for an encrypted volume.
+    ///
+    /// Returns None if the device is not encrypted or has no UUID/PARTUUID.
+    pub fn for_device(device: &BlockDevice) -> Option<Self> {
+        let encryption = device.encryption()?;
+        Some(Self {
+            name: mapper_name(device, encryption),
+            device: device.fstab_spec()?,
+            key_file: "none".to_string(),
+            options: vec![
+                encryption.cryptsetup_type().to_string(),
+                "noauto".to_string(),
+                "nofail".to_string(),
+            ],
+        })
+    }
+
+    /// Formats the entry as a crypttab line.
+    pub fn to_crypttab_line(&self) -> String {
+        format!(
+            "{}  {}  {}  {}",
+            self.name,
+            self.device,
+            self.key_file,
+            self.options.join(",")
+        )
+    }
+
+    /// Parses a single crypttab line. Returns None for comments, empty and
+    /// malformed lines.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if !(2..=4).contains(&parts.len()) {
+            return None;
+        }
+
+        Some(Self {
+            name: parts[0].to_string(),
+            device: parts[1].to_string(),
+            key_file: parts.get(2).unwrap_or(&"none").to_string(),
+            options: parts
+                .get(3)
+                .map(|options| options.split(',').map(|s| s.to_string()).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Parses the managed entries of a crypttab file. A missing file has none.
+pub fn parse_managed_entries(path: &Path) -> Result<Vec<CrypttabEntry>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(managed_entries(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).context(CrypttabSnafu { path }),
+    }
+}
+
+/// Extracts the entries inside the managed block of crypttab content.
+fn managed_entries(content: &str) -> Vec<CrypttabEntry> {
+    content
+        .lines()
+        .skip_while(|line| line.trim() != fstab::MANAGED_BLOCK_BEGIN)
+        .take_while(|line| line.trim() != fstab::MANAGED_BLOCK_END)
+        .filter_map(CrypttabEntry::from_line)
+        .collect()
+}
+
+/// Adds an entry to the managed block, replacing entries with the same
+/// name or device.
+pub fn add_managed_entry_with_ctx(
+    path: &Path,
+    entry: &CrypttabEntry,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let mut entries = parse_managed_entries(path)?;
+    entries.retain(|e| e.name != entry.name && e.device != entry.device);
+    entries.push(entry.clone());
+    write_managed_entries_with_ctx(path, &entries, ctx)
+}
+
+/// Removes managed entries for which `filter` returns true.
+///
+/// Returns the number of removed entries.
+pub fn remove_managed_entries_with_ctx<F>(
+    path: &Path,
+    ctx: &mut ExecutionContext,
+    mut filter: F,
+) -> Result<usize>
+where
+    F: FnMut(&CrypttabEntry) -> bool,
+{
+    let mut entries = parse_managed_entries(path)?;
+    let before = entries.len();
+    entries.retain(|e| !filter(e));
+    let removed = before - entries.len();
+
+    if removed > 0 {
+        write_managed_entries_with_ctx(path, &entries, ctx)?;
+    }
+    Ok(removed)
+}
+
+fn write_managed_entries_with_ctx(
+    path: &Path,
+    entries: &[CrypttabEntry],
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).context(CrypttabSnafu { path }),
+    };
+    let lines: Vec<String> = entries
+        .iter()
+        .map(CrypttabEntry::to_crypttab_line)
+        .collect();
+    let new_content = fstab::replace_managed_block(&content, &lines);
+    ctx.write_file_privileged(&path.display().to_string(), &new_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(fstype: &str, uuid: &str) -> BlockDevice {
+        BlockDevice {
+            name: "sda1".to_string(),
+            uuid: Some(uuid.to_string()),
+            fstype: Some(fstype.to_string()),
+            path: PathBuf::from("/dev/sda1"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_crypttab_entry_for_device() {
+        let luks = CrypttabEntry::for_device(&locked("crypto_LUKS", "abcd-ef")).unwrap();
+        assert_eq!(
+            luks.to_crypttab_line(),
+            "luks-abcd-ef  UUID=abcd-ef  none  luks,noauto,nofail"
+        );
+        assert_eq!(
+            CrypttabEntry::from_line(&luks.to_crypttab_line()),
+            Some(luks)
+        );
+
+        let bitlocker = CrypttabEntry::for_device(&locked("BitLocker", "1234")).unwrap();
+        assert_eq!(bitlocker.name, "bitlk-1234");
+        assert_eq!(bitlocker.options[0], "bitlk");
+
+        assert!(CrypttabEntry::for_device(&locked("ntfs", "1234")).is_none());
+        assert!(CrypttabEntry::from_line("# comment").is_none());
+        assert_eq!(
+            CrypttabEntry::from_line("home UUID=1").unwrap().key_file,
+            "none"
+        );
+    }
+
+    #[test]
+    fn test_managed_crypttab_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crypttab");
+        let mut ctx = ExecutionContext::default();
+
+        // A missing crypttab is created
+        let entry = CrypttabEntry::for_device(&locked("crypto_LUKS", "abcd")).unwrap();
+        add_managed_entry_with_ctx(&path, &entry, &mut ctx).unwrap();
+        assert_eq!(parse_managed_entries(&path).unwrap(), vec![entry.clone()]);
+
+        // Unmanaged entries are preserved
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, format!("root UUID=0000 none luks\n{}", content)).unwrap();
+        let other = CrypttabEntry::for_device(&locked("BitLocker", "1234")).unwrap();
+        add_managed_entry_with_ctx(&path, &other, &mut ctx).unwrap();
+        assert_eq!(parse_managed_entries(&path).unwrap().len(), 2);
+
+        let removed =
+            remove_managed_entries_with_ctx(&path, &mut ctx, |e| e.name == "luks-abcd").unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(parse_managed_entries(&path).unwrap(), vec![other]);
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("root UUID=0000 none luks\n")
+        );
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This module provides a unified `Device` type that combines information from:
 //! - `BlockDevice` (lsblk): Physical block device information
-//! - `FstabEntry` (fstab): Managed mount configuration
+//! - `FstabEntry` (fstab or systemd mount units): Managed mount configuration
 //! - `LibraryFolder` (Steam VDF): Steam library associations
 //!
 //! The `Device` type is the primary interface for UI/UX layers and serves as:
@@ -11,13 +11,24 @@
 
 use std::path::{Path, PathBuf};
 
-use crate::disk::{self, BlockDevice, OfflineDevice};
+use serde::{Deserialize, Serialize};
+
+use crate::crypt;
+use crate::disk::{self, BlockDevice, DeviceKind, Encryption, OfflineDevice};
 use crate::error::Result;
 use crate::fstab::{self, FstabEntry};
-use crate::steam::{self, LibraryFolder};
+use crate::health::{self, DiskHealth, HealthThresholds, HealthWarning};
+use crate::mount;
+use crate::mount_unit::{self, MountUnit};
+use crate::steam::compatdata::{self, CompatdataStatus};
+use crate::steam::{self, InstalledApp, LibraryFolder};
+use crate::sysfs;
+use crate::udisks;
+use crate::usage::{self, LibraryUsage, SpaceUsage, UsageCache};
 
 /// Represents the connection state of a device.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DeviceConnectionState {
     /// Device is currently connected and visible to the system.
     Online,
@@ -25,13 +36,102 @@ pub enum DeviceConnectionState {
     Offline,
 }
 
+/// Where a managed mount configuration is persisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PersistenceBackend {
+    /// Managed block in `/etc/fstab`.
+    #[default]
+    Fstab,
+    /// Native `.mount`/`.automount` units in `/etc/systemd/system`.
+    SystemdUnit,
+}
+
+/// A mount state of a managed device that conflicts with its configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MountConflict {
+    /// The device is also automounted by udisks (e.g. under `/run/media`).
+    ///
+    /// Resolved by unmounting `path`; the configured mount stays in place.
+    #[serde(rename_all = "camelCase")]
+    UdisksAutomount {
+        /// The udisks mount point.
+        path: PathBuf,
+    },
+}
+
+impl std::fmt::Display for MountConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UdisksAutomount { path } => {
+                write!(f, "also automounted by udisks at {}", path.display())
+            }
+        }
+    }
+}
+
+/// Drive and partition-table metadata of an online device.
+///
+/// Identifies the physical disk a device lives on, so that identically
+/// labelled partitions on different drives can be told apart and grouped
+/// per disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockMetadata {
+    /// Identifier of the physical disk (see [`BlockDevice::disk_id`]).
+    pub disk_id: Option<String>,
+    /// Kernel name of the parent device (e.g., "sda").
+    pub pkname: Option<String>,
+    /// Drive model.
+    pub model: Option<String>,
+    /// Drive serial number.
+    pub serial: Option<String>,
+    /// Drive vendor.
+    pub vendor: Option<String>,
+    /// Drive World Wide Name.
+    pub wwn: Option<String>,
+    /// Partition name from the partition table (GPT only).
+    pub partlabel: Option<String>,
+    /// Partition type GUID or MBR type code.
+    pub parttype: Option<String>,
+    /// Whether the device can be hot-plugged.
+    pub hotplug: bool,
+    /// Logical sector size in bytes.
+    pub log_sec: Option<u32>,
+    /// Physical sector size in bytes.
+    pub phy_sec: Option<u32>,
+    /// Discard (TRIM) granularity in bytes.
+    pub disc_gran: Option<u64>,
+}
+
+impl From<&BlockDevice> for BlockMetadata {
+    fn from(device: &BlockDevice) -> Self {
+        Self {
+            disk_id: device.disk_id(),
+            pkname: device.pkname.clone(),
+            model: device.model.clone(),
+            serial: device.serial.clone(),
+            vendor: device.vendor.clone(),
+            wwn: device.wwn.clone(),
+            partlabel: device.partlabel.clone(),
+            parttype: device.parttype.clone(),
+            hotplug: device.hotplug,
+            log_sec: device.log_sec,
+            phy_sec: device.phy_sec,
+            disc_gran: device.disc_gran,
+        }
+    }
+}
+
 /// Unified device information combining block device, fstab, and Steam data.
 ///
 /// This is the primary type for UI/UX interaction. It aggregates:
 /// - Physical device properties (from lsblk)
 /// - Mount configuration (from fstab)  
 /// - Steam library associations (from Steam VDF)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Device {
     // === Identification ===
     /// Display name for the device (label, mount point basename, or fs_spec).
@@ -59,11 +159,18 @@ pub struct Device {
     pub removable: Option<bool>,
     /// Transport type (e.g., "usb", "nvme"). None if unknown/none.
     pub transport: Option<String>,
+    /// Kind of device (partition, whole disk, crypt, ...). None if unknown (offline).
+    pub device_kind: Option<DeviceKind>,
+    /// Drive and partition-table metadata. None if unknown (offline).
+    pub metadata: Option<BlockMetadata>,
+    /// Encryption format of a locked container that has to be unlocked
+    /// before it can be mounted. None for regular and offline devices.
+    pub encryption: Option<Encryption>,
 
     // === State ===
-    /// Current mount point (if mounted).
-    mountpoint: Option<PathBuf>,
-    /// Whether the device is currently mounted.
+    /// Current mount points. Empty if not mounted.
+    pub mountpoints: Vec<PathBuf>,
+    /// Whether the device is currently mounted (anywhere).
     pub is_mounted: bool,
     /// Whether the device has a dirty NTFS volume (needs repair).
     pub is_dirty: bool,
@@ -72,10 +179,40 @@ pub struct Device {
 
     // === Associated Data (Full Information) ===
     /// The complete fstab entry if this device is managed.
+    ///
+    /// For devices persisted as systemd units this is the equivalent entry
+    /// (see [`MountUnit::to_fstab_entry`]).
     pub fstab_entry: Option<FstabEntry>,
+    /// Backend the managed configuration is persisted in. None for unmanaged devices.
+    pub persistence: Option<PersistenceBackend>,
     /// Steam libraries whose paths are under this device's mount point.
     /// Multiple libraries can exist under a single mount point.
     pub steam_libraries: Vec<LibraryFolder>,
+
+    // === Space Usage ===
+    /// Filesystem usage. Live for mounted devices, last-known otherwise.
+    pub usage: Option<SpaceUsage>,
+    /// When `size`/`usage` were recorded (seconds since the Unix epoch), if
+    /// they come from the usage cache rather than a live query.
+    pub usage_updated_at: Option<u64>,
+    /// Usage of each Steam library on this device.
+    /// Only computed with [`ListDevicesConfig::with_library_usage`].
+    pub library_usage: Vec<LibraryUsage>,
+    /// Apps installed in this device's Steam libraries (mounted devices only).
+    /// Only listed with [`ListDevicesConfig::with_installed_apps`].
+    pub installed_apps: Vec<InstalledApp>,
+    /// Proton prefix state of this device's Steam libraries (mounted devices
+    /// only). Only checked with [`ListDevicesConfig::with_compatdata`].
+    pub compatdata: Vec<CompatdataStatus>,
+
+    // === Health ===
+    /// SMART health of the physical disk. Only set by [`attach_health_with_ctx`],
+    /// as reading it requires privileges.
+    pub health: Option<DiskHealth>,
+
+    // === Conflicts ===
+    /// Mount conflicts the user should resolve (managed devices only).
+    pub conflicts: Vec<MountConflict>,
 }
 
 impl Device {
@@ -89,11 +226,57 @@ impl Device {
         self.fstab_entry.is_some()
     }
 
-    /// Returns the effective mount point (actual or configured target).
+    /// Returns the identifier of the physical disk this device lives on.
+    ///
+    /// None for offline devices and devices without drive identity.
+    pub fn disk_id(&self) -> Option<&str> {
+        self.metadata.as_ref().and_then(|m| m.disk_id.as_deref())
+    }
+
+    /// Returns the effective mount point.
+    ///
+    /// For managed devices this is the configured target, even if the device
+    /// is additionally mounted elsewhere. Otherwise it is the first actual
+    /// mount point, preferring ones outside udisks' `/run/media`.
     pub fn effective_mount_point(&self) -> Option<&Path> {
-        self.mountpoint
-            .as_deref()
-            .or_else(|| self.fstab_entry.as_ref().map(|e| e.mount_point.as_path()))
+        if let Some(entry) = &self.fstab_entry {
+            return Some(&entry.mount_point);
+        }
+        self.mountpoints
+            .iter()
+            .find(|p| !mount::is_udisks_mount_point(p))
+            .or_else(|| self.mountpoints.first())
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns the mount points managed by udisks (under `/run/media`).
+    pub fn udisks_mount_points(&self) -> impl Iterator<Item = &Path> {
+        self.mountpoints
+            .iter()
+            .filter(|p| mount::is_udisks_mount_point(p))
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns true if udisks (e.g. the SteamOS automounter) has mounted the device.
+    pub fn is_udisks_mounted(&self) -> bool {
+        self.udisks_mount_points().next().is_some()
+    }
+
+    /// Returns the health warnings of the device's disk for the given thresholds.
+    ///
+    /// Empty if no health data was attached.
+    pub fn health_warnings(&self, thresholds: &HealthThresholds) -> Vec<HealthWarning> {
+        self.health
+            .as_ref()
+            .map(|h| h.warnings(thresholds))
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the device is mounted at its configured target.
+    pub fn is_mounted_at_target(&self) -> bool {
+        self.fstab_entry
+            .as_ref()
+            .is_some_and(|e| self.mountpoints.contains(&e.mount_point))
     }
 
     /// Creates a Device from an online BlockDevice.
@@ -110,17 +293,28 @@ impl Device {
             rota: Some(device.rota),
             removable: Some(device.removable),
             transport: device.transport.clone(),
-            mountpoint: device.mountpoint.as_ref().map(PathBuf::from),
+            device_kind: Some(device.device_kind),
+            metadata: Some(BlockMetadata::from(device)),
+            encryption: device.encryption().filter(|_| device.is_locked()),
+            mountpoints: device.mountpoints.clone(),
             is_mounted: device.is_mounted(),
             is_dirty: false, // Will be checked separately
             connection_state: DeviceConnectionState::Online,
             fstab_entry: None,
+            persistence: None,
             steam_libraries: Vec::new(),
+            usage: None,
+            usage_updated_at: None,
+            library_usage: Vec::new(),
+            installed_apps: Vec::new(),
+            compatdata: Vec::new(),
+            health: None,
+            conflicts: Vec::new(),
         }
     }
 
     /// Creates a Device from an offline fstab entry.
-    fn from_offline_entry(entry: &FstabEntry) -> Self {
+    fn from_offline_entry(entry: &FstabEntry, backend: PersistenceBackend) -> Self {
         let offline = OfflineDevice::from_fstab_entry(entry);
 
         // Derive name from mount point basename or fs_spec
@@ -143,20 +337,81 @@ impl Device {
             rota: None,
             removable: None,
             transport: None,
-            mountpoint: Some(entry.mount_point.clone()),
+            device_kind: None,
+            metadata: None,
+            encryption: None,
+            mountpoints: Vec::new(),
             is_mounted: false,
             is_dirty: false,
             connection_state: DeviceConnectionState::Offline,
             fstab_entry: Some(entry.clone()),
+            persistence: Some(backend),
             steam_libraries: Vec::new(),
+            usage: None,
+            usage_updated_at: None,
+            library_usage: Vec::new(),
+            installed_apps: Vec::new(),
+            compatdata: Vec::new(),
+            health: None,
+            conflicts: Vec::new(),
         }
     }
 
     /// Attaches fstab entry information to this device.
-    fn attach_fstab_entry(&mut self, entry: &FstabEntry) {
+    fn attach_fstab_entry(&mut self, entry: &FstabEntry, backend: PersistenceBackend) {
         self.fstab_entry = Some(entry.clone());
+        self.persistence = Some(backend);
         self.fs_spec = Some(entry.fs_spec.clone());
-        self.mountpoint = Some(entry.mount_point.clone());
+        self.conflicts = self
+            .udisks_mount_points()
+            .filter(|p| *p != entry.mount_point)
+            .map(|p| MountConflict::UdisksAutomount {
+                path: p.to_path_buf(),
+            })
+            .collect();
+    }
+
+    /// Returns the key the device's size and usage are cached under.
+    ///
+    /// Managed devices use their fs_spec, so that the online and offline
+    /// views of a device share the entry.
+    fn usage_cache_key(&self) -> Option<String> {
+        self.fs_spec
+            .clone()
+            .or_else(|| self.uuid.as_ref().map(|uuid| format!("UUID={}", uuid)))
+            .or_else(|| {
+                self.partuuid
+                    .as_ref()
+                    .map(|partuuid| format!("PARTUUID={}", partuuid))
+            })
+    }
+
+    /// Attaches live usage, falling back to (and updating) the usage cache.
+    ///
+    /// Returns true if the cache changed.
+    fn attach_usage(&mut self, cache: &mut UsageCache) -> bool {
+        if let Some(mount_point) = self.mountpoints.first() {
+            self.usage = usage::filesystem_usage(mount_point).ok();
+        }
+
+        let Some(key) = self.usage_cache_key() else {
+            return false;
+        };
+        let changed = !self.is_offline() && cache.record(&key, self.size, self.usage);
+
+        if let Some(cached) = cache.get(&key) {
+            if self.is_offline() {
+                self.size = cached.size;
+            }
+            if self.usage.is_none() && cached.usage.is_some() {
+                self.usage = cached.usage;
+                self.usage_updated_at = Some(cached.updated_at);
+            } else if self.is_offline() {
+                self.usage_updated_at = Some(cached.updated_at);
+            }
+        }
+
+        changed
     }
 
     /// Attaches matching Steam libraries to this device.
@@ -198,10 +453,22 @@ pub fn device_matches_fstab_entry(device: &BlockDevice, entry: &FstabEntry) -> b
 pub struct ListDevicesConfig {
     /// Path to fstab file. Defaults to /etc/fstab.
     pub fstab_path: Option<PathBuf>,
-    /// Path to Steam's libraryfolders.vdf. If None, will attempt auto-detection.
+    /// Path to Steam's libraryfolders.vdf. If None, uses [`steam::default_steam_install`].
     pub steam_vdf_path: Option<PathBuf>,
     /// Whether to include Steam library information.
     pub include_steam: bool,
+    /// Directory scanned for managed systemd mount units. Defaults to /etc/systemd/system.
+    pub systemd_unit_dir: Option<PathBuf>,
+    /// Whether to include space usage (live or last-known).
+    pub include_usage: bool,
+    /// Path to the usage cache. If None, uses [`usage::default_cache_path`].
+    pub usage_cache_path: Option<PathBuf>,
+    /// Whether to compute per-library usage (walks `steamapps/common`, which can be slow).
+    pub include_library_usage: bool,
+    /// Whether to list the apps installed in Steam libraries.
+    pub include_installed_apps: bool,
+    /// Whether to check Proton prefixes of libraries on NTFS/exFAT/FAT.
+    pub include_compatdata: bool,
 }
 
 impl ListDevicesConfig {
@@ -211,6 +478,12 @@ impl ListDevicesConfig {
             fstab_path: None,
             steam_vdf_path: None,
             include_steam: true,
+            systemd_unit_dir: None,
+            include_usage: true,
+            usage_cache_path: None,
+            include_library_usage: false,
+            include_installed_apps: false,
+            include_compatdata: false,
         }
     }
 
@@ -220,20 +493,64 @@ impl ListDevicesConfig {
         self
     }
 
+    /// Sets the directory scanned for managed systemd mount units.
+    pub fn with_systemd_unit_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.systemd_unit_dir = Some(path.into());
+        self
+    }
+
+    /// Reads Steam libraries from a specific installation instead of the
+    /// default one.
+    pub fn with_steam_install(mut self, install: &steam::SteamInstall) -> Self {
+        self.steam_vdf_path = Some(install.vdf_path.clone());
+        self
+    }
+
     /// Disables Steam library detection.
     pub fn without_steam(mut self) -> Self {
         self.include_steam = false;
         self
     }
+
+    /// Sets the usage cache path.
+    pub fn with_usage_cache_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.usage_cache_path = Some(path.into());
+        self
+    }
+
+    /// Disables space usage reporting.
+    pub fn without_usage(mut self) -> Self {
+        self.include_usage = false;
+        self
+    }
+
+    /// Enables per-library usage for Steam libraries.
+    pub fn with_library_usage(mut self) -> Self {
+        self.include_library_usage = true;
+        self
+    }
+
+    /// Enables listing the apps installed in Steam libraries.
+    pub fn with_installed_apps(mut self) -> Self {
+        self.include_installed_apps = true;
+        self
+    }
+
+    /// Enables checking where Proton keeps the prefixes of Steam libraries.
+    pub fn with_compatdata(mut self) -> Self {
+        self.include_compatdata = true;
+        self
+    }
 }
 
 /// Lists all devices (online + offline managed), with fstab and Steam associations.
 ///
 /// This is the primary API for obtaining device information. It:
 /// 1. Scans for online block devices via lsblk
-/// 2. Parses fstab for managed entries
+/// 2. Parses fstab and managed systemd mount units
 /// 3. Merges online devices with offline entries (avoiding duplicates)
 /// 4. Attaches Steam library information based on mount point matching
+/// 5. Attaches space usage, updating the usage cache
 ///
 /// # Arguments
 /// * `config` - Configuration for device listing
@@ -245,15 +562,33 @@ pub fn list_devices(config: &ListDevicesConfig) -> Result<Vec<Device>> {
         .fstab_path
         .as_deref()
         .unwrap_or_else(|| Path::new(fstab::FSTAB_PATH));
+    let unit_dir = config
+        .systemd_unit_dir
+        .as_deref()
+        .unwrap_or_else(|| Path::new(mount_unit::SYSTEMD_UNIT_DIR));
 
     // Step 1: Get online block devices
     let online_devices = disk::list_block_devices()?;
     let mountable = disk::filter_mountable_devices(&online_devices);
 
-    // Step 2: Parse fstab for managed entries
-    let fstab_entries = fstab::parse_fstab(fstab_path)
+    // Step 2: Collect managed entries from fstab and systemd mount units
+    let mut managed_entries: Vec<(FstabEntry, PersistenceBackend)> = fstab::parse_fstab(fstab_path)
         .map(|parsed| parsed.managed_entries)
-        .unwrap_or_default();
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| (entry, PersistenceBackend::Fstab))
+        .collect();
+    managed_entries.extend(
+        mount_unit::list_managed_units(unit_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|installed| {
+                (
+                    installed.unit.to_fstab_entry(),
+                    PersistenceBackend::SystemdUnit,
+                )
+            }),
+    );
 
     // Step 3: Get Steam libraries if enabled
     let steam_libraries: Vec<(String, LibraryFolder)> = if config.include_steam {
@@ -280,11 +615,11 @@ pub fn list_devices(config: &ListDevicesConfig) -> Result<Vec<Device>> {
         let mut device = Device::from_block_device(block_device);
 
         // Check for matching fstab entry
-        if let Some(entry) = fstab_entries
+        if let Some((entry, backend)) = managed_entries
             .iter()
-            .find(|e| device_matches_fstab_entry(block_device, e))
+            .find(|(e, _)| device_matches_fstab_entry(block_device, e))
         {
-            device.attach_fstab_entry(entry);
+            device.attach_fstab_entry(entry, *backend);
             matched_entries.push(entry);
         }
 
@@ -294,15 +629,68 @@ pub fn list_devices(config: &ListDevicesConfig) -> Result<Vec<Device>> {
         devices.push(device);
     }
 
-    // Add offline devices (fstab entries without matching online devices)
-    for entry in &fstab_entries {
+    // Add offline devices (managed entries without matching online devices)
+    for (entry, backend) in &managed_entries {
         if !matched_entries.iter().any(|e| e.fs_spec == entry.fs_spec) {
-            let mut device = Device::from_offline_entry(entry);
+            let mut device = Device::from_offline_entry(entry, *backend);
             device.attach_steam_libraries(&steam_libraries);
             devices.push(device);
         }
     }
 
+    // Step 5: Attach space usage
+    if config.include_usage {
+        let cache_path = config
+            .usage_cache_path
+            .clone()
+            .or_else(usage::default_cache_path);
+        let mut cache = cache_path
+            .as_deref()
+            .map(UsageCache::load)
+            .unwrap_or_default();
+
+        let mut changed = false;
+        for device in &mut devices {
+            changed |= device.attach_usage(&mut cache);
+        }
+        // The cache is best-effort; failing to save it must not fail the listing
+        if changed && let Some(path) = &cache_path {
+            let _ = cache.save(path);
+        }
+    }
+    if config.include_library_usage {
+        for device in devices.iter_mut().filter(|d| d.is_mounted) {
+            device.library_usage = device
+                .steam_libraries
+                .iter()
+                .map(LibraryUsage::of)
+                .collect();
+        }
+    }
+    if config.include_installed_apps {
+        for device in devices.iter_mut().filter(|d| d.is_mounted) {
+            device.installed_apps = device
+                .steam_libraries
+                .iter()
+                .filter_map(|library| steam::list_installed_apps(&library.path).ok())
+                .flatten()
+                .collect();
+        }
+    }
+    if config.include_compatdata
+        && let Ok(target_root) = compatdata::default_target_root()
+    {
+        for device in devices.iter_mut().filter(|d| d.is_mounted) {
+            device.compatdata = device
+                .steam_libraries
+                .iter()
+                .filter_map(|library| {
+                    compatdata::check_library(&library.path, &device.fstype, &target_root).ok()
+                })
+                .collect();
+        }
+    }
+
     Ok(devices)
 }
 
@@ -354,12 +742,304 @@ pub fn find_online_block_device_by_path(path: &Path) -> Result<Option<BlockDevic
 }
 
 use crate::executor::ExecutionContext;
-use crate::mount;
 
-/// Deconfigures a device by removing its managed fstab entry.
+/// Releases a device from udisks before our mount is activated.
+///
+/// Unmounts the device's udisks automounts through the udisks2 D-Bus API,
+/// falling back to `umount` (see [`udisks::release_mounts_with_ctx`]).
+/// Does nothing for offline devices or devices without udisks mounts.
+pub fn release_udisks_mounts_with_ctx(device: &Device, ctx: &mut ExecutionContext) -> Result<()> {
+    match &device.path {
+        Some(path) if device.is_udisks_mounted() => {
+            udisks::release_mounts_with_ctx(path, &device.mountpoints, ctx)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads the SMART health of the devices' disks and attaches it.
+///
+/// Each physical disk is queried once (see [`health::read_health_with_ctx`]).
+/// Offline devices and disks without SMART support keep `health: None`.
+pub fn attach_health_with_ctx(devices: &mut [Device], ctx: &mut ExecutionContext) -> Result<()> {
+    let blocks = disk::list_block_devices()?;
+    let disk_of = |device: &Device| {
+        blocks
+            .iter()
+            .find(|b| device.path.as_ref() == Some(&b.path))
+            .map(|b| health::disk_path(b, &blocks))
+    };
+
+    let disks: Vec<PathBuf> = devices.iter().filter_map(disk_of).collect();
+    let reports = health::read_health_with_ctx(&disks, ctx)?;
+
+    for device in devices.iter_mut() {
+        device.health = disk_of(device).and_then(|disk| reports.get(&disk).cloned());
+    }
+    Ok(())
+}
+
+/// Options for [`relabel_device_with_ctx`].
+#[derive(Debug, Clone, Default)]
+pub struct RelabelOptions {
+    /// Whether to rename a managed mount point after the new label.
+    pub rename_mount_point: bool,
+    /// libraryfolders.vdf whose library paths follow a renamed mount point.
+    /// If None, Steam is not touched.
+    pub steam_vdf_path: Option<PathBuf>,
+}
+
+impl RelabelOptions {
+    /// Creates options that only change the label.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renames the managed mount point after the new label.
+    pub fn with_mount_point_rename(mut self) -> Self {
+        self.rename_mount_point = true;
+        self
+    }
+
+    /// Updates Steam library paths in the given libraryfolders.vdf.
+    pub fn with_steam_vdf_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.steam_vdf_path = Some(path.into());
+        self
+    }
+}
+
+/// Changes the volume label of a device and applies the follow-ups.
+///
+/// 1. Unmounts the device if the filesystem cannot be relabeled while mounted
+///    (or the mount point is renamed), stopping its mount units first
+/// 2. Writes the label with [`disk::set_label_with_ctx`]
+/// 3. With [`RelabelOptions::rename_mount_point`], moves the managed mount
+///    point to `<parent>/<sanitized label>` and updates the fstab entry or
+///    mount units. A `LABEL=` spec is replaced by the UUID/PARTUUID spec, as
+///    it would no longer match
+/// 4. With [`RelabelOptions::steam_vdf_path`], relocates Steam libraries under
+///    the renamed mount point (Steam must not be running)
+/// 5. Remounts the device if it was mounted at its target before
 ///
-/// This function removes the fstab entry associated with the device and reloads
-/// systemd daemon. Works for both online and offline devices.
+/// If a step fails, the completed ones are undone: the previous label,
+/// mount point, configuration and mounts are restored as far as possible.
+///
+/// Returns the refreshed device.
+///
+/// # Errors
+/// Returns error if the device is offline, the label is invalid for the
+/// filesystem, or any of the steps fail.
+pub fn relabel_device_with_ctx(
+    device: &Device,
+    label: &str,
+    options: &RelabelOptions,
+    ctx: &mut ExecutionContext,
+) -> Result<Device> {
+    use snafu::OptionExt;
+
+    let path = device
+        .path
+        .as_ref()
+        .whatever_context("Offline devices cannot be relabeled")?;
+    let mut block = find_online_block_device_by_path(path)?
+        .with_whatever_context(|| format!("Device {} not found", path.display()))?;
+    let fstype = block.fstype.as_deref().unwrap_or_default();
+    disk::validate_label(fstype, label)?;
+
+    // Work out the new configuration before touching anything
+    let old_entry = device.fstab_entry.as_ref();
+    let new_entry = old_entry.map(|entry| {
+        let mut new_entry = entry.clone();
+        if options.rename_mount_point
+            && let Some(parent) = entry.mount_point.parent()
+        {
+            new_entry.mount_point = parent.join(disk::sanitize_mount_name(label));
+        }
+        if entry.fs_spec.starts_with("LABEL=")
+            && let Some(spec) = block.fstab_spec()
+        {
+            new_entry.fs_spec = spec;
+        }
+        new_entry
+    });
+    let reconfigure = old_entry != new_entry.as_ref();
+    let needs_unmount = block.is_mounted() && (disk::label_requires_unmount(fstype) || reconfigure);
+    let was_mounted = device.is_mounted_at_target();
+    let units = old_entry
+        .filter(|_| device.persistence == Some(PersistenceBackend::SystemdUnit))
+        .map(MountUnit::from_fstab_entry);
+
+    // Each completed step records how to undo it, so that a failure leaves
+    // the device labeled, configured and mounted as before
+    let mut undo = Vec::new();
+    let mut apply = || -> Result<()> {
+        // Stop the mount units first, so an automount does not mount it again
+        if let Some(unit) = &units
+            && (needs_unmount || reconfigure)
+        {
+            undo.push(RelabelUndo::EnableUnits(unit.clone()));
+            mount_unit::disable_with_ctx(unit, ctx)?;
+        }
+        if needs_unmount {
+            for mount_point in &block.mountpoints {
+                if sysfs::is_mount_point(mount_point) {
+                    mount::unmount_device_with_ctx(mount_point, ctx)?;
+                    // The units mount their own target again
+                    if units.is_none() || old_entry.is_none_or(|e| e.mount_point != *mount_point) {
+                        undo.push(RelabelUndo::Remount(block.clone(), mount_point.clone()));
+                    }
+                }
+            }
+            block.mountpoints.clear();
+        }
+
+        disk::set_label_with_ctx(&block, label, ctx)?;
+        if let Some(old_label) = &block.label {
+            undo.push(RelabelUndo::Label(block.clone(), old_label.clone()));
+        }
+
+        if let (Some(old_entry), Some(new_entry)) = (old_entry, &new_entry)
+            && reconfigure
+        {
+            if new_entry.mount_point != old_entry.mount_point {
+                mount::create_mount_point_smart(&new_entry.mount_point, ctx, false)?;
+                // rmdir only removes the old mount point if it is empty
+                let _ =
+                    ctx.run_privileged("rmdir", &[&old_entry.mount_point.display().to_string()]);
+                undo.push(RelabelUndo::MountPoint {
+                    old: old_entry.mount_point.clone(),
+                    new: new_entry.mount_point.clone(),
+                });
+
+                if let Some(vdf_path) = &options.steam_vdf_path {
+                    steam::relocate_library_folders(
+                        vdf_path,
+                        &old_entry.mount_point,
+                        &new_entry.mount_point,
+                    )?;
+                    undo.push(RelabelUndo::Libraries {
+                        vdf_path: vdf_path.clone(),
+                        old: old_entry.mount_point.clone(),
+                        new: new_entry.mount_point.clone(),
+                    });
+                }
+            }
+
+            if let Some(unit) = &units {
+                let new_unit = MountUnit::from_fstab_entry(new_entry);
+                undo.push(RelabelUndo::Units {
+                    old: unit.clone(),
+                    new: new_unit.clone(),
+                });
+                let unit_dir = Path::new(mount_unit::SYSTEMD_UNIT_DIR);
+                mount_unit::remove_units_with_ctx(unit, unit_dir, ctx)?;
+                mount_unit::install_units_with_ctx(&new_unit, unit_dir, ctx)?;
+            } else {
+                let fstab_path = Path::new(fstab::FSTAB_PATH);
+                fstab::backup_fstab_with_ctx(fstab_path, ctx)?;
+                let old_entries = fstab::parse_fstab(fstab_path)?.managed_entries;
+                let entries: Vec<FstabEntry> = old_entries
+                    .iter()
+                    .map(|e| {
+                        if e == old_entry {
+                            new_entry.clone()
+                        } else {
+                            e.clone()
+                        }
+                    })
+                    .collect();
+                undo.push(RelabelUndo::Fstab(old_entries));
+                fstab::write_managed_entries_with_ctx(fstab_path, &entries, ctx)?;
+                mount::reload_systemd_daemon_with_ctx(ctx)?;
+            }
+        }
+
+        // Bring the device back where it was
+        if let Some(new_entry) = &new_entry {
+            if units.is_some() && (needs_unmount || reconfigure) {
+                mount_unit::enable_with_ctx(&MountUnit::from_fstab_entry(new_entry), ctx)?;
+            } else if units.is_none() && was_mounted && needs_unmount {
+                mount::mount_device_with_ctx(&block, &new_entry.mount_point, ctx)?;
+            }
+        }
+        Ok(())
+    };
+
+    if let Err(e) = apply() {
+        rollback_relabel(undo, ctx);
+        return Err(e);
+    }
+
+    list_devices(&ListDevicesConfig::new())?
+        .into_iter()
+        .find(|d| d.path.as_ref() == Some(path))
+        .with_whatever_context(|| format!("Device {} not found after relabeling", path.display()))
+}
+
+/// A completed relabel step, recorded so that it can be undone.
+#[derive(Debug)]
+enum RelabelUndo {
+    /// Units that were disabled; enabled again.
+    EnableUnits(MountUnit),
+    /// A mount point the device was unmounted from.
+    Remount(BlockDevice, PathBuf),
+    /// The label before relabeling. A volume without a label keeps the new
+    /// one, as an empty label cannot be written.
+    Label(BlockDevice, String),
+    /// A managed mount point that was moved.
+    MountPoint { old: PathBuf, new: PathBuf },
+    /// Steam libraries that were relocated below the new mount point.
+    Libraries {
+        vdf_path: PathBuf,
+        old: PathBuf,
+        new: PathBuf,
+    },
+    /// Mount units that were replaced.
+    Units { old: MountUnit, new: MountUnit },
+    /// Managed fstab entries before they were rewritten.
+    Fstab(Vec<FstabEntry>),
+}
+
+/// Undoes relabel steps in reverse order.
+///
+/// Runs as far as possible; failures are ignored, as the error that caused
+/// the rollback is the one reported.
+fn rollback_relabel(undo: Vec<RelabelUndo>, ctx: &mut ExecutionContext) {
+    let unit_dir = Path::new(mount_unit::SYSTEMD_UNIT_DIR);
+    for step in undo.into_iter().rev() {
+        let _ = match step {
+            RelabelUndo::EnableUnits(unit) => mount_unit::enable_with_ctx(&unit, ctx),
+            RelabelUndo::Remount(block, mount_point) => {
+                mount::mount_device_with_ctx(&block, &mount_point, ctx)
+            }
+            RelabelUndo::Label(block, label) => disk::set_label_with_ctx(&block, &label, ctx),
+            RelabelUndo::MountPoint { old, new } => {
+                let _ = ctx.run_privileged("rmdir", &[&new.display().to_string()]);
+                mount::create_mount_point_smart(&old, ctx, false)
+            }
+            RelabelUndo::Libraries { vdf_path, old, new } => {
+                steam::relocate_library_folders(&vdf_path, &new, &old).map(|_| ())
+            }
+            RelabelUndo::Units { old, new } => {
+                let _ = mount_unit::remove_units_with_ctx(&new, unit_dir, ctx);
+                mount_unit::install_units_with_ctx(&old, unit_dir, ctx)
+            }
+            RelabelUndo::Fstab(entries) => {
+                let fstab_path = Path::new(fstab::FSTAB_PATH);
+                fstab::write_managed_entries_with_ctx(fstab_path, &entries, ctx)
+                    .and_then(|()| mount::reload_systemd_daemon_with_ctx(ctx))
+            }
+        };
+    }
+}
+
+/// Deconfigures a device by removing its managed fstab entry or mount units.
+///
+/// This function removes the fstab entry (or the systemd mount units,
+/// depending on [`Device::persistence`]) associated with the device and reloads
+/// systemd daemon. Works for both online and offline devices. A `UDISKS_IGNORE`
+/// udev rule installed for the device's UUID and the managed crypttab entry
+/// of an unlocked volume are removed as well.
 ///
 /// # Matching Strategy
 /// Uses precise matching with both `fs_spec` AND `mount_point` from the device's
@@ -368,15 +1048,22 @@ use crate::mount;
 ///
 /// # Arguments
 /// * `device` - The device to deconfigure (must have fstab_entry)
+/// * `unit_dir` - Directory holding managed systemd mount units
+///   (normally [`mount_unit::SYSTEMD_UNIT_DIR`])
 /// * `ctx` - Execution context for privileged operations
 ///
 /// # Errors
 /// Returns error if:
 /// - Device has no fstab_entry (not managed)
+/// - The udisks ignore rule or crypttab entry cannot be removed
 /// - Fstab backup fails
 /// - Entry removal fails
 /// - Systemd daemon reload fails
-pub fn deconfigure_device_with_ctx(device: &Device, ctx: &mut ExecutionContext) -> Result<()> {
+pub fn deconfigure_device_with_ctx(
+    device: &Device,
+    unit_dir: &Path,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
     use snafu::OptionExt;
 
     // Device must have an fstab entry to deconfigure
@@ -385,6 +1072,28 @@ pub fn deconfigure_device_with_ctx(device: &Device, ctx: &mut ExecutionContext)
         .as_ref()
         .whatever_context("Device is not configured in fstab (no managed entry)")?;
 
+    // Forget the crypttab mapping of an unlocked volume
+    if device.device_kind == Some(DeviceKind::Crypt)
+        && let Some(name) = device.path.as_ref().and_then(|p| p.file_name())
+    {
+        crypt::remove_managed_entries_with_ctx(Path::new(crypt::CRYPTTAB_PATH), ctx, |e| {
+            name == e.name.as_str()
+        })?;
+    }
+
+    // Let udisks automount the device again
+    if let Some(uuid) = &device.uuid {
+        udisks::remove_ignore_rule_with_ctx(Path::new(udisks::UDEV_RULES_PATH), uuid, ctx)?;
+    }
+
+    // Prefix bind mounts are bound to the drive's mount unit
+    steam::compatdata::remove_bind_units_with_ctx(&entry.mount_point, unit_dir, ctx)?;
+
+    if device.persistence == Some(PersistenceBackend::SystemdUnit) {
+        let unit = MountUnit::from_fstab_entry(entry);
+        return mount_unit::remove_units_with_ctx(&unit, unit_dir, ctx);
+    }
+
     let fstab_path = Path::new(fstab::FSTAB_PATH);
 
     // Backup fstab with privilege escalation
@@ -404,6 +1113,59 @@ pub fn deconfigure_device_with_ctx(device: &Device, ctx: &mut ExecutionContext)
     Ok(())
 }
 
+/// Removes a device's Steam libraries from libraryfolders.vdf.
+///
+/// Used when a drive is deconfigured, so Steam does not keep showing its
+/// libraries as missing. Returns the number of removed libraries.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn remove_steam_libraries(device: &Device, vdf_path: &Path) -> Result<usize> {
+    let mut removed = 0;
+    for library in &device.steam_libraries {
+        if steam::remove_library_folder(vdf_path, &library.path)? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+impl Device {
+    /// Creates a minimal unmounted online device for tests in other modules.
+    pub(crate) fn new_for_test(uuid: &str) -> Self {
+        Self {
+            name: uuid.to_string(),
+            fs_spec: None,
+            path: Some(PathBuf::from("/dev/sda1")),
+            label: None,
+            uuid: Some(uuid.to_string()),
+            partuuid: None,
+            fstype: "ntfs".to_string(),
+            size: 0,
+            rota: None,
+            removable: None,
+            transport: None,
+            device_kind: None,
+            metadata: None,
+            encryption: None,
+            mountpoints: Vec::new(),
+            is_mounted: false,
+            is_dirty: false,
+            connection_state: DeviceConnectionState::Online,
+            fstab_entry: None,
+            persistence: None,
+            steam_libraries: Vec::new(),
+            usage: None,
+            usage_updated_at: None,
+            library_usage: Vec::new(),
+            installed_apps: Vec::new(),
+            compatdata: Vec::new(),
+            health: None,
+            conflicts: Vec::new(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,12 +1184,23 @@ mod tests {
             rota: None,
             removable: None,
             transport: None,
-            mountpoint: None,
+            device_kind: None,
+            metadata: None,
+            encryption: None,
+            mountpoints: Vec::new(),
             is_mounted: false,
             is_dirty: false,
             connection_state: DeviceConnectionState::Online,
             fstab_entry: None,
+            persistence: None,
             steam_libraries: Vec::new(),
+            usage: None,
+            usage_updated_at: None,
+            library_usage: Vec::new(),
+            installed_apps: Vec::new(),
+            compatdata: Vec::new(),
+            health: None,
+            conflicts: Vec::new(),
         };
 
         assert!(!device.is_offline());
@@ -448,12 +1221,23 @@ mod tests {
             rota: None,
             removable: None,
             transport: None,
-            mountpoint: Some(PathBuf::from("/mnt/games")),
+            device_kind: None,
+            metadata: None,
+            encryption: None,
+            mountpoints: vec![PathBuf::from("/mnt/games")],
             is_mounted: true,
             is_dirty: false,
             connection_state: DeviceConnectionState::Online,
             fstab_entry: None,
+            persistence: None,
             steam_libraries: Vec::new(),
+            usage: None,
+            usage_updated_at: None,
+            library_usage: Vec::new(),
+            installed_apps: Vec::new(),
+            compatdata: Vec::new(),
+            health: None,
+            conflicts: Vec::new(),
         };
 
         let libraries = vec![
@@ -496,4 +1280,125 @@ mod tests {
         assert!(device.steam_libraries.iter().any(|l| l.label == "Games"));
         assert!(device.steam_libraries.iter().any(|l| l.label == "Games2"));
     }
+
+    #[test]
+    fn test_attach_usage_from_cache() {
+        let usage = SpaceUsage {
+            total: 1000,
+            used: 400,
+            available: 600,
+        };
+        let mut cache = UsageCache::default();
+
+        // Online but unmounted: size is recorded, no usage yet
+        let mut online = Device::new_for_test("1234");
+        online.size = 4096;
+        assert!(online.attach_usage(&mut cache));
+        assert_eq!(online.usage, None);
+
+        // Usage seen while mounted is kept for later
+        cache.record("UUID=1234", 4096, Some(usage));
+
+        let entry = FstabEntry::new("UUID=1234", "/mnt/games", "ntfs3", "defaults", 0, 0);
+        let mut offline = Device::from_offline_entry(&entry, PersistenceBackend::Fstab);
+        assert!(!offline.attach_usage(&mut cache));
+        assert_eq!(offline.size, 4096);
+        assert_eq!(offline.usage, Some(usage));
+        assert!(offline.usage_updated_at.is_some());
+
+        // Unknown devices stay empty
+        let entry = FstabEntry::new("UUID=other", "/mnt/other", "ntfs3", "defaults", 0, 0);
+        let mut unknown = Device::from_offline_entry(&entry, PersistenceBackend::Fstab);
+        assert!(!unknown.attach_usage(&mut cache));
+        assert_eq!(unknown.size, 0);
+        assert_eq!(unknown.usage, None);
+    }
+
+    #[test]
+    fn test_udisks_automount_conflict() {
+        let entry = FstabEntry::new(
+            "UUID=1234",
+            "/home/deck/Drives/Games",
+            "ntfs3",
+            "defaults",
+            0,
+            0,
+        );
+        let mut device = Device::new_for_test("1234");
+        device.mountpoints = vec![
+            PathBuf::from("/run/media/deck/Games"),
+            PathBuf::from("/home/deck/Drives/Games"),
+        ];
+        device.is_mounted = true;
+        assert!(device.is_udisks_mounted());
+        assert_eq!(
+            device.udisks_mount_points().collect::<Vec<_>>(),
+            vec![Path::new("/run/media/deck/Games")]
+        );
+
+        // Unmanaged: the non-udisks mount point wins regardless of order
+        assert_eq!(
+            device.effective_mount_point(),
+            Some(Path::new("/home/deck/Drives/Games"))
+        );
+
+        device.attach_fstab_entry(&entry, PersistenceBackend::Fstab);
+        assert!(device.is_mounted_at_target());
+        assert_eq!(
+            device.conflicts,
+            vec![MountConflict::UdisksAutomount {
+                path: PathBuf::from("/run/media/deck/Games")
+            }]
+        );
+        assert_eq!(
+            device.conflicts[0].to_string(),
+            "also automounted by udisks at /run/media/deck/Games"
+        );
+
+        // Only automounted: still a conflict, and the target is not mounted
+        device.mountpoints = vec![PathBuf::from("/run/media/deck/Games")];
+        device.attach_fstab_entry(&entry, PersistenceBackend::Fstab);
+        assert!(!device.is_mounted_at_target());
+        assert_eq!(device.conflicts.len(), 1);
+        assert_eq!(
+            device.effective_mount_point(),
+            Some(Path::new("/home/deck/Drives/Games"))
+        );
+    }
+
+    #[test]
+    fn test_rollback_relabel() {
+        // Relabeling failed after moving the mount point and the library
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("Games");
+        let new = dir.path().join("Fast");
+        std::fs::create_dir(&new).unwrap();
+        let vdf_path = dir.path().join("libraryfolders.vdf");
+        std::fs::write(
+            &vdf_path,
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                new.join("SteamLibrary").display()
+            ),
+        )
+        .unwrap();
+
+        let undo = vec![
+            RelabelUndo::MountPoint {
+                old: old.clone(),
+                new: new.clone(),
+            },
+            RelabelUndo::Libraries {
+                vdf_path: vdf_path.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            },
+        ];
+        rollback_relabel(undo, &mut ExecutionContext::default());
+
+        assert!(old.is_dir());
+        assert!(!new.exists());
+        let folders = steam::parse_library_folders(&vdf_path).unwrap();
+        assert_eq!(folders[0].1.path, old.join("SteamLibrary"));
+    }
 }
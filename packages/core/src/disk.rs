@@ -2,17 +2,104 @@
 //!
 //! This module provides functionality to scan and list block devices
 //! on the system, filtering for NTFS and exFAT partitions that can be
-//! mounted by this tool.
+//! mounted by this tool. If `lsblk` is missing or too old, scanning falls
+//! back to [`crate::sysfs`].
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
+use crate::executor::ExecutionContext;
 
-/// Represents a block device (partition) on the system.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Kind of block device carrying a filesystem.
+///
+/// Most filesystems live on a partition, but a disk can also be formatted
+/// as a whole (a "superfloppy", common for SD cards and USB sticks) or the
+/// filesystem can sit on a mapped device (LUKS, LVM or md RAID).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceKind {
+    /// A partition of a disk (lsblk type "part").
+    #[default]
+    Partition,
+    /// A whole disk without a partition table (lsblk type "disk").
+    Disk,
+    /// A dm-crypt mapping, e.g. an unlocked LUKS volume (lsblk type "crypt").
+    Crypt,
+    /// An LVM logical volume (lsblk type "lvm").
+    Lvm,
+    /// An md RAID array (lsblk types "raid0", "raid1", ..., "linear", "md").
+    Raid,
+}
+
+impl DeviceKind {
+    /// Classifies an lsblk TYPE value. Returns None for unsupported types
+    /// (e.g. "rom", "loop").
+    pub fn from_lsblk_type(device_type: &str) -> Option<Self> {
+        match device_type {
+            "part" => Some(Self::Partition),
+            "disk" => Some(Self::Disk),
+            "crypt" => Some(Self::Crypt),
+            "lvm" => Some(Self::Lvm),
+            "md" | "linear" => Some(Self::Raid),
+            t if t.starts_with("raid") => Some(Self::Raid),
+            _ => None,
+        }
+    }
+
+    /// Returns true for device-mapper devices, whose nodes live in `/dev/mapper`.
+    pub fn is_device_mapper(&self) -> bool {
+        matches!(self, Self::Crypt | Self::Lvm)
+    }
+
+    /// Returns the device node path for a name as listed by lsblk.
+    ///
+    /// lsblk lists device-mapper devices by their mapper name (e.g.
+    /// "luks-1234"), which only exists under `/dev/mapper`.
+    pub fn device_path(&self, name: &str) -> PathBuf {
+        if self.is_device_mapper() {
+            PathBuf::from(format!("/dev/mapper/{}", name))
+        } else {
+            PathBuf::from(format!("/dev/{}", name))
+        }
+    }
+}
+
+/// Encryption format of a locked volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encryption {
+    /// LUKS1/LUKS2 (lsblk FSTYPE "crypto_LUKS").
+    Luks,
+    /// Windows BitLocker (lsblk FSTYPE "BitLocker").
+    BitLocker,
+}
+
+impl Encryption {
+    /// Classifies an lsblk/blkid FSTYPE value.
+    pub fn from_fstype(fstype: &str) -> Option<Self> {
+        match fstype {
+            "crypto_LUKS" => Some(Self::Luks),
+            "BitLocker" => Some(Self::BitLocker),
+            _ => None,
+        }
+    }
+
+    /// Returns the `cryptsetup open --type` value for this format.
+    pub fn cryptsetup_type(&self) -> &'static str {
+        match self {
+            Self::Luks => "luks",
+            Self::BitLocker => "bitlk",
+        }
+    }
+}
+
+/// Represents a block device carrying a filesystem on the system.
+///
+/// This is usually a partition; see [`DeviceKind`] for the other kinds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct BlockDevice {
     /// Device name (e.g., "nvme0n1p2", "sda1").
     pub name: String,
@@ -24,8 +111,9 @@ pub struct BlockDevice {
     pub partuuid: Option<String>,
     /// Filesystem type (e.g., "ntfs", "exfat").
     pub fstype: Option<String>,
-    /// Current mount point, if mounted.
-    pub mountpoint: Option<String>,
+    /// Current mount points. A device can be mounted more than once, e.g. by
+    /// fstab and by a udisks automount under `/run/media`.
+    pub mountpoints: Vec<PathBuf>,
     /// Size in bytes.
     pub size: u64,
     /// Full device path (e.g., "/dev/nvme0n1p2").
@@ -36,6 +124,33 @@ pub struct BlockDevice {
     pub removable: bool,
     /// Transport type (e.g., "usb", "nvme", "sata", "mmc").
     pub transport: Option<String>,
+    /// Kind of device (partition, whole disk, crypt, LVM or RAID).
+    pub device_kind: DeviceKind,
+    /// Drive model of the physical disk (e.g., "Samsung SSD 980 1TB").
+    pub model: Option<String>,
+    /// Serial number of the physical disk.
+    pub serial: Option<String>,
+    /// Vendor of the physical disk (often only set for SCSI/USB drives).
+    pub vendor: Option<String>,
+    /// Partition name from the partition table (GPT only).
+    pub partlabel: Option<String>,
+    /// Partition type GUID (GPT) or type code (MBR, e.g., "0x7").
+    pub parttype: Option<String>,
+    /// Kernel name of the parent device (e.g., "sda" for "sda1").
+    pub pkname: Option<String>,
+    /// World Wide Name of the physical disk.
+    pub wwn: Option<String>,
+    /// Whether the device can be hot-plugged (USB, SD card, ...).
+    pub hotplug: bool,
+    /// Logical sector size in bytes.
+    pub log_sec: Option<u32>,
+    /// Physical sector size in bytes.
+    pub phy_sec: Option<u32>,
+    /// Discard (TRIM) granularity in bytes. 0 if discard is not supported.
+    pub disc_gran: Option<u64>,
+    /// Mapper names of device-mapper devices stacked on this device (e.g.
+    /// the unlocked mapping of a LUKS volume).
+    pub holders: Vec<String>,
 }
 
 impl BlockDevice {
@@ -61,8 +176,6 @@ impl BlockDevice {
     /// Checks if `/dev/disk/by-uuid/<UUID>` or `/dev/disk/by-partuuid/<PARTUUID>`
     /// exists, as required for fstab mounting.
     pub fn validate_fstab_spec(&self) -> Result<()> {
-        use std::path::Path;
-
         if let Some(uuid) = &self.uuid {
             let uuid_path = Path::new("/dev/disk/by-uuid").join(uuid);
             if !uuid_path.exists() {
@@ -120,16 +233,60 @@ impl BlockDevice {
         self.is_ntfs() || self.is_exfat()
     }
 
+    /// Returns the encryption format if this is an encrypted container.
+    pub fn encryption(&self) -> Option<Encryption> {
+        self.fstype.as_deref().and_then(Encryption::from_fstype)
+    }
+
+    /// Returns true if this is an encrypted container that is not unlocked yet.
+    ///
+    /// Once unlocked, the mapping shows up as a separate [`DeviceKind::Crypt`]
+    /// device that goes through the normal mount pipeline.
+    pub fn is_locked(&self) -> bool {
+        self.encryption().is_some() && self.holders.is_empty()
+    }
+
     /// Returns true if this device is currently mounted.
     pub fn is_mounted(&self) -> bool {
-        self.mountpoint.is_some()
+        !self.mountpoints.is_empty()
+    }
+
+    /// Returns the first mount point, if mounted.
+    pub fn mountpoint(&self) -> Option<&Path> {
+        self.mountpoints.first().map(PathBuf::as_path)
+    }
+
+    /// Returns true if discard (TRIM) is supported by the device.
+    pub fn supports_discard(&self) -> bool {
+        self.disc_gran.is_some_and(|gran| gran > 0)
+    }
+
+    /// Returns an identifier of the physical disk this device lives on.
+    ///
+    /// Partitions of the same disk share the same ID, so it can be used to
+    /// group partitions per disk and to tell apart identically labelled
+    /// partitions on different drives. Uses the WWN if available, then
+    /// model and serial, and falls back to the parent's kernel name (which
+    /// is not stable across reboots).
+    pub fn disk_id(&self) -> Option<String> {
+        if let Some(wwn) = &self.wwn {
+            return Some(format!("wwn-{}", wwn));
+        }
+        if let Some(serial) = &self.serial {
+            let model = self.model.as_deref().unwrap_or("disk");
+            return Some(format!("{}_{}", model, serial).replace(' ', "_"));
+        }
+        match self.device_kind {
+            DeviceKind::Disk => Some(self.name.clone()),
+            _ => self.pkname.clone(),
+        }
     }
 }
 
 /// Sanitize a string for use as a mount point directory name.
 ///
 /// Replaces problematic characters with underscores.
-fn sanitize_mount_name(name: &str) -> String {
+pub(crate) fn sanitize_mount_name(name: &str) -> String {
     name.chars()
         .map(|c| {
             if c.is_alphanumeric() || c == '-' || c == '_' {
@@ -141,6 +298,129 @@ fn sanitize_mount_name(name: &str) -> String {
         .collect()
 }
 
+/// Characters FAT does not allow in volume labels.
+const FAT_LABEL_FORBIDDEN: &str = "\"*+,./:;<=>?[\\]|";
+
+/// Characters exFAT (like Windows file names) does not allow in volume labels.
+const EXFAT_LABEL_FORBIDDEN: &str = "\"*/:<>?\\|";
+
+/// Returns the labeling tool for a filesystem type.
+///
+/// Returns None if relabeling is not supported for the filesystem.
+pub fn label_tool(fstype: &str) -> Option<&'static str> {
+    match fstype {
+        "ntfs" => Some("ntfslabel"),
+        "exfat" => Some("exfatlabel"),
+        "vfat" => Some("fatlabel"),
+        "ext2" | "ext3" | "ext4" => Some("e2label"),
+        _ => None,
+    }
+}
+
+/// Returns true if the filesystem must be unmounted while its label is changed.
+///
+/// Only ext2/3/4 can be relabeled while mounted; the other tools write the
+/// on-disk label behind the kernel driver's back.
+pub fn label_requires_unmount(fstype: &str) -> bool {
+    !matches!(fstype, "ext2" | "ext3" | "ext4")
+}
+
+/// Validates a volume label against the limits of a filesystem.
+///
+/// - NTFS: up to 128 UTF-16 code units.
+/// - exFAT: up to 11 UTF-16 code units, no `"*/:<>?\|`.
+/// - FAT: up to 11 ASCII characters, no `"*+,./:;<=>?[\]|`.
+/// - ext2/3/4: up to 16 bytes.
+///
+/// Labels must not be empty, contain control characters or start with `-`
+/// (the tools would read it as an option).
+pub fn validate_label(fstype: &str, label: &str) -> Result<()> {
+    let invalid = |reason: String| {
+        Err(Error::InvalidLabel {
+            label: label.to_string(),
+            reason,
+        })
+    };
+
+    if label_tool(fstype).is_none() {
+        return Err(Error::InvalidFilesystem {
+            fs: fstype.to_string(),
+        });
+    }
+    if label.trim().is_empty() {
+        return invalid("label must not be empty".to_string());
+    }
+    if label.starts_with('-') {
+        return invalid("label must not start with '-'".to_string());
+    }
+    if label.chars().any(char::is_control) {
+        return invalid("label must not contain control characters".to_string());
+    }
+
+    let (max, length, forbidden) = match fstype {
+        "ntfs" => (128, label.encode_utf16().count(), ""),
+        "exfat" => (11, label.encode_utf16().count(), EXFAT_LABEL_FORBIDDEN),
+        "vfat" => {
+            if !label.is_ascii() {
+                return invalid("FAT labels must be ASCII".to_string());
+            }
+            (11, label.len(), FAT_LABEL_FORBIDDEN)
+        }
+        _ => (16, label.len(), ""),
+    };
+
+    if length > max {
+        return invalid(format!(
+            "{} labels are limited to {} characters",
+            fstype, max
+        ));
+    }
+    if let Some(c) = label.chars().find(|c| forbidden.contains(*c)) {
+        return invalid(format!("'{}' is not allowed in {} labels", c, fstype));
+    }
+    Ok(())
+}
+
+/// Changes the volume label of a filesystem.
+///
+/// Runs `ntfslabel`, `exfatlabel`, `fatlabel` or `e2label` through the
+/// privileged context, then waits for udev to pick up the new label. NTFS,
+/// exFAT and FAT volumes must be unmounted first (see [`label_requires_unmount`]).
+pub fn set_label_with_ctx(
+    device: &BlockDevice,
+    label: &str,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let fstype = device.fstype.as_deref().unwrap_or_default();
+    validate_label(fstype, label)?;
+    let tool = label_tool(fstype).unwrap_or_default();
+
+    let device_path = device.path.display().to_string();
+    if device.is_mounted() && label_requires_unmount(fstype) {
+        return Err(Error::Relabel {
+            device: device_path,
+            message: format!("{} volumes must be unmounted first", fstype),
+        });
+    }
+
+    let output = ctx.run_privileged(tool, &[&device_path, label])?;
+    match output.status.code() {
+        Some(0) => {}
+        Some(126) => return Err(Error::AuthenticationCancelled),
+        _ => {
+            return Err(Error::Relabel {
+                device: device_path,
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+    }
+
+    // Wait for udev to re-probe the device, so that scans see the new label
+    let _ = ctx.run_privileged("udevadm", &["settle"]);
+
+    Ok(())
+}
+
 /// Raw JSON structure from lsblk output.
 #[derive(Debug, Deserialize)]
 struct LsblkOutput {
@@ -160,6 +440,9 @@ struct LsblkDevice {
     fstype: Option<String>,
     #[serde(default)]
     mountpoint: Option<String>,
+    /// All mount points (lsblk >= 2.37). Unmounted devices report `[null]`.
+    #[serde(default)]
+    mountpoints: Option<Vec<Option<String>>>,
     #[serde(default)]
     size: Option<u64>,
     #[serde(rename = "type")]
@@ -171,22 +454,81 @@ struct LsblkDevice {
     #[serde(default)]
     tran: Option<String>,
     #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    serial: Option<String>,
+    #[serde(default)]
+    vendor: Option<String>,
+    #[serde(default)]
+    partlabel: Option<String>,
+    #[serde(default)]
+    parttype: Option<String>,
+    #[serde(default)]
+    pkname: Option<String>,
+    #[serde(default)]
+    wwn: Option<String>,
+    #[serde(default)]
+    hotplug: Option<bool>,
+    #[serde(default, rename = "log-sec")]
+    log_sec: Option<u32>,
+    #[serde(default, rename = "phy-sec")]
+    phy_sec: Option<u32>,
+    #[serde(default, rename = "disc-gran")]
+    disc_gran: Option<u64>,
+    #[serde(default)]
     children: Option<Vec<LsblkDevice>>,
 }
 
+impl LsblkDevice {
+    /// Returns the mount points from MOUNTPOINTS, or from the legacy
+    /// MOUNTPOINT column on older lsblk.
+    fn mount_points(&self) -> Vec<PathBuf> {
+        match &self.mountpoints {
+            Some(mountpoints) => mountpoints.iter().flatten().map(PathBuf::from).collect(),
+            None => self.mountpoint.iter().map(PathBuf::from).collect(),
+        }
+    }
+}
+
+/// Columns requested from lsblk.
+const LSBLK_COLUMNS: &str = "NAME,LABEL,UUID,PARTUUID,FSTYPE,MOUNTPOINTS,SIZE,TYPE,ROTA,RM,TRAN,\
+     MODEL,SERIAL,VENDOR,PARTLABEL,PARTTYPE,PKNAME,WWN,HOTPLUG,LOG-SEC,PHY-SEC,DISC-GRAN";
+
+/// Columns for lsblk older than util-linux 2.37, which lacks MOUNTPOINTS.
+const LSBLK_LEGACY_COLUMNS: &str = "NAME,LABEL,UUID,PARTUUID,FSTYPE,MOUNTPOINT,SIZE,TYPE,ROTA,RM,TRAN,\
+     MODEL,SERIAL,VENDOR,PARTLABEL,PARTTYPE,PKNAME,WWN,HOTPLUG,LOG-SEC,PHY-SEC,DISC-GRAN";
+
 /// Lists all block devices on the system.
 ///
-/// Calls `lsblk --json --bytes` and parses the output.
+/// Uses `lsblk` and falls back to the sysfs/udev scanner if `lsblk` is
+/// unavailable or rejects one of the requested columns (older util-linux
+/// versions lack `PARTUUID`, `TRAN` or `HOTPLUG`). If both fail, the lsblk error is returned.
 pub fn list_block_devices() -> Result<Vec<BlockDevice>> {
+    list_block_devices_with_lsblk().or_else(|lsblk_error| {
+        crate::sysfs::SysfsScanner::new()
+            .list_block_devices()
+            .map_err(|_| lsblk_error)
+    })
+}
+
+/// Lists all block devices using `lsblk` only.
+///
+/// Calls `lsblk --json --bytes` and parses the output. If lsblk rejects the
+/// MOUNTPOINTS column, retries with the legacy MOUNTPOINT column, which only
+/// reports one mount point per device.
+pub fn list_block_devices_with_lsblk() -> Result<Vec<BlockDevice>> {
+    run_lsblk(LSBLK_COLUMNS).or_else(|error| match error {
+        Error::CommandExit { .. } => run_lsblk(LSBLK_LEGACY_COLUMNS),
+        error => Err(error),
+    })
+}
+
+/// Runs lsblk with the given output columns and collects the devices.
+fn run_lsblk(columns: &str) -> Result<Vec<BlockDevice>> {
     use crate::error::IoResultExt;
 
     let output = Command::new("lsblk")
-        .args([
-            "--json",
-            "--bytes",
-            "--output",
-            "NAME,LABEL,UUID,PARTUUID,FSTYPE,MOUNTPOINT,SIZE,TYPE,ROTA,RM,TRAN",
-        ])
+        .args(["--json", "--bytes", "--output", columns])
         .output()
         .command_context("lsblk")?;
 
@@ -211,58 +553,150 @@ pub fn list_block_devices() -> Result<Vec<BlockDevice>> {
     Ok(devices)
 }
 
-/// Recursively collect devices from lsblk output, including children (partitions).
+/// Properties inherited from parent devices in the lsblk tree.
+#[derive(Debug, Default)]
+struct Inherited {
+    rota: Option<bool>,
+    rm: Option<bool>,
+    tran: Option<String>,
+    model: Option<String>,
+    serial: Option<String>,
+    vendor: Option<String>,
+    wwn: Option<String>,
+    hotplug: Option<bool>,
+}
+
+/// Recursively collect devices from lsblk output, including children
+/// (partitions and crypt/LVM/RAID devices stacked on them).
+///
+/// We propagate parent properties (ROTA, RM, TRAN and the drive identity) to
+/// children if they are missing in the child. lsblk only reports TRAN, MODEL
+/// and SERIAL for the disk itself, so a LUKS volume on a USB partition
+/// inherits "usb" from the disk two levels up.
 ///
-/// We propagate parent properties (ROTA, RM, TRAN) to children if they are missing
-/// in the child (lsblk usually sets them for children too, but good to be safe/consistent).
+/// md RAID arrays and LVM volumes spanning several devices are listed by
+/// lsblk below each of them; only the first occurrence is collected.
 fn collect_devices(
     lsblk_devices: &[LsblkDevice],
     devices: &mut Vec<BlockDevice>,
-    parent: Option<&LsblkDevice>,
+    parent: Option<&Inherited>,
 ) {
     for dev in lsblk_devices {
-        // Inherit properties from parent if not present (though lsblk usually provides them)
-        // or prioritize device's own properties if present.
-        let rota = dev
-            .rota
-            .or_else(|| parent.and_then(|p| p.rota))
-            .unwrap_or(false);
-        let removable = dev
-            .rm
-            .or_else(|| parent.and_then(|p| p.rm))
-            .unwrap_or(false);
-        let transport = dev
-            .tran
-            .clone()
-            .or_else(|| parent.and_then(|p| p.tran.clone()));
-
-        // Only include partitions (type = "part")
-        if dev.device_type.as_deref() == Some("part") {
+        let device_kind = dev
+            .device_type
+            .as_deref()
+            .and_then(DeviceKind::from_lsblk_type);
+        // A shared child, along with everything stacked on it, was collected
+        // below its first parent
+        if let Some(kind) = device_kind {
+            let path = kind.device_path(&dev.name);
+            if devices.iter().any(|d| d.path == path) {
+                continue;
+            }
+        }
+
+        // Prioritize the device's own properties, falling back to the parents'
+        let inherited = Inherited {
+            rota: dev.rota.or_else(|| parent.and_then(|p| p.rota)),
+            rm: dev.rm.or_else(|| parent.and_then(|p| p.rm)),
+            tran: inherit(&dev.tran, parent.and_then(|p| p.tran.as_ref())),
+            model: inherit(&dev.model, parent.and_then(|p| p.model.as_ref())),
+            serial: inherit(&dev.serial, parent.and_then(|p| p.serial.as_ref())),
+            vendor: inherit(&dev.vendor, parent.and_then(|p| p.vendor.as_ref())),
+            wwn: inherit(&dev.wwn, parent.and_then(|p| p.wwn.as_ref())),
+            hotplug: dev.hotplug.or_else(|| parent.and_then(|p| p.hotplug)),
+        };
+
+        // Include partitions and mapped devices, plus whole disks that carry
+        // a filesystem directly (no partition table)
+        let device_kind =
+            device_kind.filter(|kind| *kind != DeviceKind::Disk || dev.fstype.is_some());
+        if let Some(device_kind) = device_kind {
             devices.push(BlockDevice {
                 name: dev.name.clone(),
                 label: dev.label.clone(),
                 uuid: dev.uuid.clone(),
                 partuuid: dev.partuuid.clone(),
                 fstype: dev.fstype.clone(),
-                mountpoint: dev.mountpoint.clone(),
+                mountpoints: dev.mount_points(),
                 size: dev.size.unwrap_or(0),
-                path: PathBuf::from(format!("/dev/{}", dev.name)),
-                rota,
-                removable,
-                transport: transport.clone(),
+                path: device_kind.device_path(&dev.name),
+                rota: inherited.rota.unwrap_or(false),
+                removable: inherited.rm.unwrap_or(false),
+                transport: inherited.tran.clone(),
+                device_kind,
+                model: inherited.model.clone(),
+                serial: inherited.serial.clone(),
+                vendor: inherited.vendor.clone(),
+                partlabel: dev.partlabel.clone(),
+                parttype: dev.parttype.clone(),
+                pkname: dev.pkname.clone(),
+                wwn: inherited.wwn.clone(),
+                hotplug: inherited.hotplug.unwrap_or(false),
+                log_sec: dev.log_sec,
+                phy_sec: dev.phy_sec,
+                disc_gran: dev.disc_gran,
+                holders: dev
+                    .children
+                    .iter()
+                    .flatten()
+                    .filter(|child| {
+                        child
+                            .device_type
+                            .as_deref()
+                            .and_then(DeviceKind::from_lsblk_type)
+                            .is_some_and(|kind| kind.is_device_mapper())
+                    })
+                    .map(|child| child.name.clone())
+                    .collect(),
             });
         }
 
-        // Recurse into children (partitions of a disk)
+        // Recurse into children (partitions of a disk, mapped devices)
         if let Some(children) = &dev.children {
-            collect_devices(children, devices, Some(dev));
+            collect_devices(children, devices, Some(&inherited));
+        }
+    }
+}
+
+/// Returns the device's own string property (trimmed, lsblk pads VENDOR with
+/// spaces), falling back to the inherited one.
+fn inherit(own: &Option<String>, parent: Option<&String>) -> Option<String> {
+    own.as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .or_else(|| parent.cloned())
+}
+
+/// Returns the kernel name of the disk a device lives on.
+///
+/// Follows PKNAME through partitions and mapped devices (e.g. a LUKS mapping
+/// on `sda1` lives on `sda`). Names that are not in `devices` are returned
+/// as they are, as whole disks with a partition table are not scanned.
+pub fn disk_name<'a>(name: &'a str, devices: &'a [BlockDevice]) -> &'a str {
+    let mut current = name;
+    // Bounded walk, in case of a malformed PKNAME cycle
+    for _ in 0..devices.len() {
+        match devices
+            .iter()
+            .find(|d| d.name == current)
+            .and_then(|d| d.pkname.as_deref())
+        {
+            Some(parent) => current = parent,
+            None => break,
         }
     }
+    current
 }
 
-/// Filters block devices to only include NTFS and exFAT partitions.
+/// Filters block devices to only include NTFS and exFAT devices, plus
+/// locked encrypted containers that have to be unlocked first.
 pub fn filter_mountable_devices(devices: &[BlockDevice]) -> Vec<&BlockDevice> {
-    devices.iter().filter(|d| d.is_mountable()).collect()
+    devices
+        .iter()
+        .filter(|d| d.is_mountable() || d.is_locked())
+        .collect()
 }
 
 /// Represents an offline managed device from fstab that is not currently online.
@@ -368,6 +802,8 @@ pub fn vfs_type_to_fstype(vfs_type: &str) -> &str {
 }
 
 /// Represents a device that may be online (connected) or offline (in fstab but not connected).
+// Few of these exist at a time, so boxing the online variant is not worth it
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum ManagedDevice {
     /// Device is online and visible to the system.
@@ -548,6 +984,13 @@ mod tests {
                 "rota": true,
                 "rm": true,
                 "tran": "usb",
+                "model": "Portable SSD",
+                "serial": "XYZ123",
+                "vendor": "Samsung ",
+                "hotplug": true,
+                "log-sec": 512,
+                "phy-sec": 4096,
+                "disc-gran": 0,
                 "children": [
                     {
                         "name": "sda1",
@@ -557,7 +1000,14 @@ mod tests {
                         "fstype": "exfat",
                         "mountpoint": null,
                         "size": 128849018880,
-                        "type": "part"
+                        "type": "part",
+                        "model": null,
+                        "partlabel": "Basic data partition",
+                        "parttype": "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7",
+                        "pkname": "sda",
+                        "log-sec": 512,
+                        "phy-sec": 4096,
+                        "disc-gran": 0
                     }
                 ]
             }
@@ -589,6 +1039,24 @@ mod tests {
         assert!(exfat_device.rota); // from parent
         assert!(exfat_device.removable); // from parent
         assert_eq!(exfat_device.transport.as_deref(), Some("usb"));
+        // Drive identity from parent, partition fields from the partition
+        assert_eq!(exfat_device.model.as_deref(), Some("Portable SSD"));
+        assert_eq!(exfat_device.vendor.as_deref(), Some("Samsung"));
+        assert!(exfat_device.hotplug);
+        assert_eq!(exfat_device.pkname.as_deref(), Some("sda"));
+        assert_eq!(
+            exfat_device.partlabel.as_deref(),
+            Some("Basic data partition")
+        );
+        assert_eq!(exfat_device.phy_sec, Some(4096));
+        assert!(!exfat_device.supports_discard());
+        assert_eq!(
+            exfat_device.disk_id().as_deref(),
+            Some("Portable_SSD_XYZ123")
+        );
+
+        // Neither drive identity nor PKNAME reported
+        assert_eq!(ntfs_device.disk_id().as_deref(), None);
     }
 
     #[test]
@@ -601,6 +1069,171 @@ mod tests {
         assert_eq!(mountable.len(), 2);
     }
 
+    #[test]
+    fn test_collect_whole_disk_and_mapped_devices() {
+        let json = r#"{
+            "blockdevices": [
+                {
+                    "name": "mmcblk0", "label": "SDCARD", "uuid": "1111-2222",
+                    "fstype": "exfat", "size": 63864569856, "type": "disk",
+                    "rota": false, "rm": true, "tran": null
+                },
+                {
+                    "name": "sdb", "fstype": null, "size": 1000204886016, "type": "disk",
+                    "rota": true, "rm": false, "tran": "usb",
+                    "children": [
+                        {
+                            "name": "sdb1", "uuid": "luks-uuid", "fstype": "crypto_LUKS",
+                            "size": 1000203837440, "type": "part",
+                            "children": [
+                                {
+                                    "name": "luks-luks-uuid", "uuid": "ABCD", "fstype": "ntfs",
+                                    "mountpoint": "/run/media/deck/Games",
+                                    "size": 1000187060224, "type": "crypt"
+                                }
+                            ]
+                        },
+                        {
+                            "name": "sdb2", "uuid": "bitlk-uuid", "fstype": "BitLocker",
+                            "size": 1048576, "type": "part"
+                        }
+                    ]
+                },
+                {
+                    "name": "sr0", "fstype": "iso9660", "size": 1024, "type": "rom"
+                }
+            ]
+        }"#;
+        let lsblk_output: LsblkOutput = serde_json::from_str(json).unwrap();
+        let mut devices = Vec::new();
+        collect_devices(&lsblk_output.blockdevices, &mut devices, None);
+
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        // The partitioned disk and the optical drive are skipped
+        assert_eq!(names, vec!["mmcblk0", "sdb1", "luks-luks-uuid", "sdb2"]);
+
+        let sd = &devices[0];
+        assert_eq!(sd.device_kind, DeviceKind::Disk);
+        assert_eq!(sd.path, PathBuf::from("/dev/mmcblk0"));
+        assert!(sd.is_exfat());
+        assert_eq!(sd.fstab_spec().as_deref(), Some("UUID=1111-2222"));
+
+        let luks = &devices[1];
+        assert_eq!(luks.device_kind, DeviceKind::Partition);
+        assert!(!luks.is_mountable());
+        assert_eq!(luks.encryption(), Some(Encryption::Luks));
+        assert_eq!(luks.holders, vec!["luks-luks-uuid"]);
+        assert!(!luks.is_locked());
+
+        let bitlocker = &devices[3];
+        assert_eq!(bitlocker.encryption(), Some(Encryption::BitLocker));
+        assert_eq!(Encryption::BitLocker.cryptsetup_type(), "bitlk");
+        assert!(bitlocker.is_locked());
+
+        // Unlocked containers are hidden behind their mapping
+        let mountable: Vec<&str> = filter_mountable_devices(&devices)
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(mountable, vec!["mmcblk0", "luks-luks-uuid", "sdb2"]);
+
+        let crypt = &devices[2];
+        assert_eq!(crypt.device_kind, DeviceKind::Crypt);
+        assert_eq!(crypt.path, PathBuf::from("/dev/mapper/luks-luks-uuid"));
+        assert!(crypt.is_ntfs());
+        assert!(crypt.rota); // from the disk two levels up
+        assert_eq!(crypt.transport.as_deref(), Some("usb"));
+    }
+
+    #[test]
+    fn test_collect_shared_children_once() {
+        // An md RAID1 mirror over two USB disks with LVM on top: lsblk lists
+        // the array and its volumes below both members
+        let member = |disk: &str| {
+            format!(
+                r#"{{
+                    "name": "{disk}", "fstype": null, "size": 1000204886016, "type": "disk",
+                    "rota": false, "rm": true, "tran": "usb",
+                    "children": [
+                        {{
+                            "name": "{disk}1", "uuid": "raid-uuid", "fstype": "linux_raid_member",
+                            "size": 1000203837440, "type": "part",
+                            "children": [
+                                {{
+                                    "name": "md127", "uuid": "pv-uuid", "fstype": "LVM2_member",
+                                    "size": 1000069595136, "type": "raid1",
+                                    "children": [
+                                        {{
+                                            "name": "games-library", "uuid": "BEEF", "fstype": "ext4",
+                                            "size": 1000065400832, "type": "lvm"
+                                        }}
+                                    ]
+                                }}
+                            ]
+                        }}
+                    ]
+                }}"#
+            )
+        };
+        let json = format!(
+            r#"{{ "blockdevices": [ {}, {} ] }}"#,
+            member("sdb"),
+            member("sdc")
+        );
+        let lsblk_output: LsblkOutput = serde_json::from_str(&json).unwrap();
+        let mut devices = Vec::new();
+        collect_devices(&lsblk_output.blockdevices, &mut devices, None);
+
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["sdb1", "md127", "games-library", "sdc1"]);
+        assert_eq!(devices[1].device_kind, DeviceKind::Raid);
+        assert_eq!(devices[2].path, PathBuf::from("/dev/mapper/games-library"));
+        assert_eq!(devices[2].transport.as_deref(), Some("usb"));
+    }
+
+    #[test]
+    fn test_lsblk_mountpoints() {
+        let json = r#"{
+            "blockdevices": [
+                {"name": "sda1", "fstype": "ntfs", "type": "part",
+                 "mountpoints": ["/home/deck/Drives/Games", "/run/media/deck/Games"]},
+                {"name": "sda2", "fstype": "exfat", "type": "part", "mountpoints": [null]},
+                {"name": "sda3", "fstype": "exfat", "type": "part", "mountpoint": "/mnt/legacy"}
+            ]
+        }"#;
+        let lsblk_output: LsblkOutput = serde_json::from_str(json).unwrap();
+        let mut devices = Vec::new();
+        collect_devices(&lsblk_output.blockdevices, &mut devices, None);
+
+        assert_eq!(
+            devices[0].mountpoints,
+            vec![
+                PathBuf::from("/home/deck/Drives/Games"),
+                PathBuf::from("/run/media/deck/Games")
+            ]
+        );
+        assert_eq!(
+            devices[0].mountpoint(),
+            Some(Path::new("/home/deck/Drives/Games"))
+        );
+        assert!(!devices[1].is_mounted());
+        // Legacy MOUNTPOINT column
+        assert_eq!(devices[2].mountpoints, vec![PathBuf::from("/mnt/legacy")]);
+    }
+
+    #[test]
+    fn test_device_kind_from_lsblk_type() {
+        assert_eq!(
+            DeviceKind::from_lsblk_type("part"),
+            Some(DeviceKind::Partition)
+        );
+        assert_eq!(DeviceKind::from_lsblk_type("lvm"), Some(DeviceKind::Lvm));
+        assert_eq!(DeviceKind::from_lsblk_type("raid1"), Some(DeviceKind::Raid));
+        assert_eq!(DeviceKind::from_lsblk_type("md"), Some(DeviceKind::Raid));
+        assert_eq!(DeviceKind::from_lsblk_type("loop"), None);
+        assert_eq!(DeviceKind::from_lsblk_type("rom"), None);
+    }
+
     #[test]
     fn test_fstab_spec() {
         let device = BlockDevice {
@@ -609,12 +1242,13 @@ mod tests {
             uuid: Some("AABB-CCDD".to_string()),
             partuuid: Some("1122-3344".to_string()),
             fstype: Some("ntfs".to_string()),
-            mountpoint: None,
+            mountpoints: Vec::new(),
             size: 1024,
             path: PathBuf::from("/dev/sda1"),
             rota: false,
             removable: false,
             transport: None,
+            ..Default::default()
         };
 
         // UUID takes precedence, case-sensitive
@@ -630,12 +1264,13 @@ mod tests {
             uuid: Some("1234-5678".to_string()),
             partuuid: None,
             fstype: Some("ntfs".to_string()),
-            mountpoint: None,
+            mountpoints: Vec::new(),
             size: 1024,
             path: PathBuf::from("/dev/sda1"),
             rota: false,
             removable: false,
             transport: None,
+            ..Default::default()
         };
         assert_eq!(device_with_label.suggested_mount_name(), "My_Games");
 
@@ -646,12 +1281,13 @@ mod tests {
             uuid: Some("12345678-abcd-efgh".to_string()),
             partuuid: None,
             fstype: Some("ntfs".to_string()),
-            mountpoint: None,
+            mountpoints: Vec::new(),
             size: 1024,
             path: PathBuf::from("/dev/sda1"),
             rota: false,
             removable: false,
             transport: None,
+            ..Default::default()
         };
         assert_eq!(device_no_label.suggested_mount_name(), "12345678");
     }
@@ -663,6 +1299,32 @@ mod tests {
         assert_eq!(sanitize_mount_name("Game/Data"), "Game_Data");
     }
 
+    #[test]
+    fn test_validate_label() {
+        assert!(validate_label("ntfs", "Games Library").is_ok());
+        assert!(validate_label("ntfs", &"x".repeat(128)).is_ok());
+        assert!(validate_label("ntfs", &"x".repeat(129)).is_err());
+
+        // exFAT counts UTF-16 code units, FAT only takes ASCII
+        assert!(validate_label("exfat", "Spiele-Käse").is_ok());
+        assert!(validate_label("exfat", "SD Card 512G").is_err());
+        assert!(validate_label("exfat", "a:b").is_err());
+        assert!(validate_label("vfat", "STEAM_SD").is_ok());
+        assert!(validate_label("vfat", "Käse").is_err());
+        assert!(validate_label("vfat", "v1.0").is_err());
+
+        assert!(validate_label("ext4", &"x".repeat(16)).is_ok());
+        assert!(validate_label("ext4", &"ü".repeat(9)).is_err());
+
+        assert!(validate_label("ntfs", "").is_err());
+        assert!(validate_label("ntfs", "-f").is_err());
+        assert!(validate_label("ntfs", "a\nb").is_err());
+        assert!(matches!(
+            validate_label("btrfs", "Games"),
+            Err(Error::InvalidFilesystem { .. })
+        ));
+    }
+
     #[test]
     fn test_parse_fs_spec() {
         // UUID
@@ -721,12 +1383,13 @@ mod tests {
             uuid: Some("1234-5678".to_string()),
             partuuid: Some("abcd-efgh".to_string()),
             fstype: Some("ntfs".to_string()),
-            mountpoint: None,
+            mountpoints: Vec::new(),
             size: 1024,
             path: PathBuf::from("/dev/sda1"),
             rota: false,
             removable: false,
             transport: None,
+            ..Default::default()
         };
 
         let managed_online = ManagedDevice::Online(online_device);
@@ -759,12 +1422,13 @@ mod tests {
             uuid: Some("1234-5678".to_string()),
             partuuid: Some("abcd-efgh".to_string()),
             fstype: Some("ntfs".to_string()),
-            mountpoint: None,
+            mountpoints: Vec::new(),
             size: 1024,
             path: PathBuf::from("/dev/sda1"),
             rota: false,
             removable: false,
             transport: None,
+            ..Default::default()
         };
 
         // Match by UUID
@@ -820,12 +1484,13 @@ UUID=OFFLINE-DEVICE  /home/deck/Drives/Offline  exfat  rw  0  0
                 uuid: Some("AABBCCDD11223344".to_string()),
                 partuuid: Some("1122-3344".to_string()),
                 fstype: Some("ntfs".to_string()),
-                mountpoint: Some("/home/deck/Drives/Games".to_string()),
+                mountpoints: vec![PathBuf::from("/home/deck/Drives/Games")],
                 size: 499570991104,
                 path: PathBuf::from("/dev/nvme0n1p2"),
                 rota: false,
                 removable: false,
                 transport: Some("nvme".to_string()),
+                ..Default::default()
             },
             BlockDevice {
                 name: "sda1".to_string(),
@@ -833,12 +1498,13 @@ UUID=OFFLINE-DEVICE  /home/deck/Drives/Offline  exfat  rw  0  0
                 uuid: Some("NEW-DEVICE".to_string()),
                 partuuid: None,
                 fstype: Some("exfat".to_string()),
-                mountpoint: None,
+                mountpoints: Vec::new(),
                 size: 128849018880,
                 path: PathBuf::from("/dev/sda1"),
                 rota: false,
                 removable: true,
                 transport: Some("usb".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -32,6 +32,27 @@ pub enum Error {
     #[snafu(display("failed to parse lsblk output: {message}"))]
     LsblkParse { message: String },
 
+    /// Failed to read sysfs while scanning block devices.
+    #[snafu(display("failed to read sysfs at {}", path.display()))]
+    Sysfs {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Failed to query the space usage of a filesystem or directory.
+    #[snafu(display("failed to read space usage of {}", path.display()))]
+    Usage {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Failed to read or write the usage cache.
+    #[snafu(display("failed to access usage cache at {}", path.display()))]
+    UsageCache {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     /// Fstab file not found or cannot be read.
     #[snafu(display("failed to read fstab at {}", path.display()))]
     FstabRead {
@@ -111,14 +132,94 @@ pub enum Error {
     #[snafu(display("Steam process control error: {message}"))]
     SteamProcess { message: String },
 
+    /// A Steam library folder cannot be changed.
+    #[snafu(display("Steam library {}: {message}", path.display()))]
+    SteamLibrary { path: PathBuf, message: String },
+
+    /// Moving an installed app between Steam libraries failed.
+    #[snafu(display("failed to move {app}: {message}"))]
+    MoveApp { app: String, message: String },
+
+    /// Device monitoring failed (inotify, poll, mount table).
+    #[snafu(display("device monitor error during {operation}"))]
+    Monitor {
+        operation: String,
+        source: std::io::Error,
+    },
+
+    /// Session detection or switching failed.
+    #[snafu(display("session control error: {message}"))]
+    Session { message: String },
+
     /// Systemd operation failed.
     #[snafu(display("systemd operation failed: {message}"))]
     Systemd { message: String },
 
+    /// D-Bus call failed (bus unavailable, access denied, unknown object, ...).
+    #[snafu(display("D-Bus call '{method}' failed: {source}"))]
+    DBus {
+        method: String,
+        #[snafu(source(from(zbus::Error, Box::new)))]
+        source: Box<zbus::Error>,
+    },
+
+    /// Unlocking an encrypted volume failed.
+    #[snafu(display("failed to unlock {device}: {message}"))]
+    Unlock { device: String, message: String },
+
+    /// Changing the volume label failed.
+    #[snafu(display("failed to relabel {device}: {message}"))]
+    Relabel { device: String, message: String },
+
+    /// Formatting a device failed or was refused.
+    #[snafu(display("cannot format {device}: {message}"))]
+    Format { device: String, message: String },
+
+    /// Reading the SMART health of a disk failed.
+    #[snafu(display("failed to read health of {disk}: {message}"))]
+    Health { disk: String, message: String },
+
+    /// Failed to read the crypttab file.
+    #[snafu(display("failed to read crypttab at {}", path.display()))]
+    Crypttab {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Failed to read the managed udev rules file.
+    #[snafu(display("failed to read udev rules at {}", path.display()))]
+    UdevRules {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Failed to read a systemd mount unit file or directory.
+    #[snafu(display("failed to read mount unit at {}", path.display()))]
+    MountUnitRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Failed to parse a systemd mount unit file.
+    #[snafu(display("failed to parse mount unit: {message}"))]
+    MountUnitParse { message: String },
+
+    /// Path cannot be converted to a systemd unit name.
+    #[snafu(display("cannot derive unit name from path {}: {reason}", path.display()))]
+    InvalidMountPath { path: PathBuf, reason: String },
+
+    /// String is not a valid escaped systemd unit name.
+    #[snafu(display("invalid unit name '{name}': {reason}"))]
+    InvalidUnitName { name: String, reason: String },
+
     /// Invalid UUID format.
     #[snafu(display("invalid UUID format: {uuid}"))]
     InvalidUuid { uuid: String },
 
+    /// Volume label is not valid for the filesystem.
+    #[snafu(display("invalid label '{label}': {reason}"))]
+    InvalidLabel { label: String, reason: String },
+
     /// User cancelled authentication dialog.
     #[snafu(display("authentication cancelled by user"))]
     AuthenticationCancelled,
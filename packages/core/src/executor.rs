@@ -592,6 +592,30 @@ impl PrivilegedSession {
         })
     }
 
+    /// Executes a command in the privileged session, writing `input` to its stdin.
+    pub fn run_command_with_input(
+        &mut self,
+        program: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Result<Output> {
+        let id = self.next_id();
+        let cmd = DaemonCommand::ExecWithInput {
+            program: program.to_string(),
+            args: args.iter().map(|s| s.to_string()).collect(),
+            input: input.to_string(),
+        };
+        let request = self.create_signed_request(id, cmd);
+
+        let response = self.send_request(&request)?;
+
+        Ok(Output {
+            status: std::process::ExitStatus::from_raw(response.exit_code),
+            stdout: response.stdout.into_bytes(),
+            stderr: response.stderr.into_bytes(),
+        })
+    }
+
     /// Writes content to a file in the privileged session.
     pub fn write_file(&mut self, path: &str, content: &str) -> Result<()> {
         let id = self.next_id();
@@ -942,6 +966,37 @@ impl ExecutionContext {
         }
     }
 
+    /// Executes a command that requires root privileges, writing `input` to
+    /// its stdin.
+    ///
+    /// Use this for secrets such as passphrases: they are passed on stdin
+    /// (or over the authenticated session channel) and never on argv.
+    pub fn run_privileged_with_input(
+        &mut self,
+        cmd: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Result<Output> {
+        match self.escalation {
+            PrivilegeEscalation::None => run_command_with_input(None, cmd, args, input),
+            PrivilegeEscalation::Pkexec => run_command_with_input(Some("pkexec"), cmd, args, input),
+            PrivilegeEscalation::Sudo => run_command_with_input(Some("sudo"), cmd, args, input),
+            PrivilegeEscalation::PkexecSession | PrivilegeEscalation::SudoSession => {
+                self.ensure_session()?;
+                let session = self
+                    .session
+                    .as_ref()
+                    .ok_or_else(|| Error::SessionCommunication {
+                        message: "Session not available".to_string(),
+                    })?;
+                let mut guard = session.lock().map_err(|e| Error::SessionCommunication {
+                    message: format!("Failed to lock session: {}", e),
+                })?;
+                guard.run_command_with_input(cmd, args, input)
+            }
+        }
+    }
+
     /// Executes a command that requires root privileges, checking for success.
     ///
     /// Returns an error if the command fails or if authentication is cancelled.
@@ -1092,6 +1147,60 @@ fn run_command(cmd: &str, args: &[&str]) -> Result<Output> {
         })
 }
 
+/// Runs a command (optionally through a wrapper) with `input` written to its stdin.
+fn run_command_with_input(
+    wrapper: Option<&str>,
+    cmd: &str,
+    args: &[&str],
+    input: &str,
+) -> Result<Output> {
+    let command_name = match wrapper {
+        Some(wrapper) => format!("{} {}", wrapper, cmd),
+        None => cmd.to_string(),
+    };
+    let mut command = match wrapper {
+        Some(wrapper) => {
+            let mut command = Command::new(wrapper);
+            command.arg(cmd);
+            command
+        }
+        None => Command::new(cmd),
+    };
+
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match (wrapper, e.kind()) {
+            (Some(wrapper), std::io::ErrorKind::NotFound) => Error::EscalationToolNotFound {
+                tool: wrapper.to_string(),
+            },
+            _ => Error::CommandExecution {
+                command: command_name.clone(),
+                source: e,
+            },
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Dropping stdin afterwards signals EOF
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| Error::CommandExecution {
+                command: command_name.clone(),
+                source: e,
+            })?;
+    }
+
+    child
+        .wait_with_output()
+        .map_err(|e| Error::CommandExecution {
+            command: command_name,
+            source: e,
+        })
+}
+
 /// Runs a command with a privilege escalation wrapper (pkexec or sudo).
 fn run_with_wrapper(wrapper: &str, cmd: &str, args: &[&str]) -> Result<Output> {
     // Check if wrapper tool exists (only for known standard tools)
@@ -1171,6 +1280,16 @@ mod tests {
         assert_eq!(ctx.escalation(), PrivilegeEscalation::PkexecSession);
     }
 
+    #[test]
+    fn test_run_privileged_with_input() {
+        let mut ctx = ExecutionContext::default();
+        let output = ctx
+            .run_privileged_with_input("cat", &[], "secret passphrase")
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"secret passphrase");
+    }
+
     #[test]
     fn test_sudo_session_context() {
         let ctx = ExecutionContext::with_sudo_session();
@@ -0,0 +1,476 @@
+//! Formatting drives for use as Steam libraries.
+//!
+//! Formatting is a two-step process:
+//!
+//! 1. [`plan_format`] checks the target and returns a [`FormatPlan`] with a
+//!    confirmation token. The plan is refused for the system disk and for
+//!    anything that is mounted or in use (e.g. by an unlocked LUKS mapping).
+//! 2. [`format_with_ctx`] re-checks the target, verifies the token and runs
+//!    the privileged steps: `wipefs`, optionally `sfdisk` to create a GPT with
+//!    a single partition, and `mkfs.exfat` / `mkfs.ntfs -Q` / `mkfs.ext4`.
+//!
+//! The token is derived from the target's current partitions and
+//! filesystems, so it becomes invalid if a different card is inserted or the
+//! layout changes between confirmation and formatting. The formatted device
+//! is returned for the normal configure/mount flow.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::disk::{self, BlockDevice, DeviceKind};
+use crate::error::{Error, Result};
+use crate::executor::ExecutionContext;
+use crate::sysfs;
+
+/// Mount points whose disk is never formatted.
+const SYSTEM_MOUNT_POINTS: &[&str] = &[
+    "/",
+    "/boot",
+    "/boot/efi",
+    "/efi",
+    "/home",
+    "/usr",
+    "/var",
+    "[SWAP]",
+];
+
+/// GPT partition type for exFAT and NTFS ("Microsoft basic data").
+const GPT_TYPE_BASIC_DATA: &str = "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7";
+
+/// GPT partition type for ext4 ("Linux filesystem").
+const GPT_TYPE_LINUX: &str = "0FC63DAF-8483-4772-8E79-3D69D8477DE4";
+
+/// Filesystems a drive can be formatted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatFilesystem {
+    /// exFAT: readable everywhere, the SteamOS default for SD cards.
+    Exfat,
+    /// NTFS: for drives shared with Windows.
+    Ntfs,
+    /// ext4: native Linux filesystem, SteamOS only.
+    Ext4,
+}
+
+impl FormatFilesystem {
+    /// Returns the filesystem type as reported by lsblk.
+    pub fn fstype(&self) -> &'static str {
+        match self {
+            Self::Exfat => "exfat",
+            Self::Ntfs => "ntfs",
+            Self::Ext4 => "ext4",
+        }
+    }
+
+    /// Returns the GPT partition type GUID for a partition of this filesystem.
+    pub fn gpt_type(&self) -> &'static str {
+        match self {
+            Self::Exfat | Self::Ntfs => GPT_TYPE_BASIC_DATA,
+            Self::Ext4 => GPT_TYPE_LINUX,
+        }
+    }
+
+    /// Returns the mkfs program and its arguments.
+    ///
+    /// `whole_disk` adds the flags mkfs.ntfs needs to format a device that is
+    /// not a partition.
+    pub fn mkfs_command(
+        &self,
+        device: &Path,
+        label: &str,
+        whole_disk: bool,
+    ) -> (&'static str, Vec<String>) {
+        let device = device.display().to_string();
+        let label = label.to_string();
+        match self {
+            Self::Exfat => ("mkfs.exfat", vec!["-L".to_string(), label, device]),
+            Self::Ntfs => {
+                let mut args = vec!["-Q".to_string(), "-L".to_string(), label];
+                if whole_disk {
+                    args.push("-F".to_string());
+                }
+                args.push(device);
+                ("mkfs.ntfs", args)
+            }
+            Self::Ext4 => (
+                "mkfs.ext4",
+                vec!["-F".to_string(), "-L".to_string(), label, device],
+            ),
+        }
+    }
+}
+
+/// What to create on the target device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatOptions {
+    /// Filesystem to create.
+    pub filesystem: FormatFilesystem,
+    /// Volume label (validated with [`disk::validate_label`]).
+    pub label: String,
+    /// Whether to create a GPT with a single partition spanning the disk.
+    /// Only valid for whole disks.
+    #[serde(default)]
+    pub partition_table: bool,
+}
+
+impl FormatOptions {
+    /// Creates options that format the target device directly.
+    pub fn new(filesystem: FormatFilesystem, label: impl Into<String>) -> Self {
+        Self {
+            filesystem,
+            label: label.into(),
+            partition_table: false,
+        }
+    }
+
+    /// Creates a GPT with a single partition and formats that partition.
+    pub fn with_partition_table(mut self) -> Self {
+        self.partition_table = true;
+        self
+    }
+}
+
+/// A checked format operation, waiting for confirmation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatPlan {
+    /// Device that is wiped (a whole disk or a partition).
+    pub device: PathBuf,
+    /// Device the filesystem is created on: the new first partition when a
+    /// partition table is created, otherwise `device`.
+    pub target: PathBuf,
+    /// What to create.
+    pub options: FormatOptions,
+    /// Whether the filesystem is created on a whole disk without partition table.
+    pub whole_disk: bool,
+    /// Existing devices that will be destroyed (the device and its partitions).
+    pub affected: Vec<PathBuf>,
+    /// Token that has to be passed to [`format_with_ctx`].
+    pub token: String,
+}
+
+/// Checks a device for formatting and returns the plan to confirm.
+///
+/// # Errors
+/// Returns [`Error::Format`] if the device is (on) the system disk, is
+/// mounted or in use, or a partition table is requested for a partition,
+/// and [`Error::InvalidLabel`] if the label does not fit the filesystem.
+pub fn plan_format(device: &Path, options: &FormatOptions) -> Result<FormatPlan> {
+    // Resolve /dev/disk/by-* links to the kernel device
+    let device = device.canonicalize().map_err(|e| Error::Format {
+        device: device.display().to_string(),
+        message: e.to_string(),
+    })?;
+    let devices = disk::list_block_devices()?;
+
+    // Disks missing from the scan have to be real drives, not loop or dm devices
+    let name = device.file_name().unwrap_or_default();
+    let sys_device = Path::new(sysfs::SYS_ROOT)
+        .join("class/block")
+        .join(name)
+        .join("device");
+    if !devices.iter().any(|d| d.name.as_str() == name) && !sys_device.exists() {
+        return Err(Error::Format {
+            device: device.display().to_string(),
+            message: "not a disk or partition".to_string(),
+        });
+    }
+
+    plan_format_with_devices(&device, options, &devices)
+}
+
+/// Builds a [`FormatPlan`] against a list of scanned block devices.
+fn plan_format_with_devices(
+    device: &Path,
+    options: &FormatOptions,
+    devices: &[BlockDevice],
+) -> Result<FormatPlan> {
+    let refuse = |message: String| Error::Format {
+        device: device.display().to_string(),
+        message,
+    };
+    let name = device
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| refuse("not a block device path".to_string()))?;
+
+    disk::validate_label(options.filesystem.fstype(), &options.label)?;
+
+    // Whole disks with a partition table (or no filesystem at all) are not in
+    // the scan, only their partitions are
+    let kind = devices
+        .iter()
+        .find(|d| d.name == name)
+        .map_or(DeviceKind::Disk, |d| d.device_kind);
+    match kind {
+        DeviceKind::Disk => {}
+        DeviceKind::Partition if !options.partition_table => {}
+        DeviceKind::Partition => {
+            return Err(refuse(
+                "a partition table can only be created on a whole disk".to_string(),
+            ));
+        }
+        _ => return Err(refuse("mapped devices cannot be formatted".to_string())),
+    }
+
+    // The system disk is off limits, including its other partitions
+    let disk_name = disk::disk_name(name, devices);
+    if let Some(system) = devices.iter().find(|d| {
+        disk::disk_name(&d.name, devices) == disk_name
+            && d.mountpoints
+                .iter()
+                .any(|p| SYSTEM_MOUNT_POINTS.iter().any(|s| p == Path::new(s)))
+    }) {
+        return Err(refuse(format!(
+            "refusing to format the system disk ({} is a system partition)",
+            system.path.display()
+        )));
+    }
+
+    let affected: Vec<&BlockDevice> = devices
+        .iter()
+        .filter(|d| has_ancestor(&d.name, name, devices))
+        .collect();
+    if let Some(mounted) = affected.iter().find(|d| d.is_mounted()) {
+        return Err(refuse(format!(
+            "{} is mounted at {}",
+            mounted.path.display(),
+            mounted.mountpoints[0].display()
+        )));
+    }
+    if let Some(held) = affected.iter().find(|d| !d.holders.is_empty()) {
+        return Err(refuse(format!(
+            "{} is in use by {}",
+            held.path.display(),
+            held.holders.join(", ")
+        )));
+    }
+
+    let target = if options.partition_table {
+        partition_path(device, 1)
+    } else {
+        device.to_path_buf()
+    };
+
+    let mut hasher = DefaultHasher::new();
+    device.hash(&mut hasher);
+    options.hash(&mut hasher);
+    for d in &affected {
+        (&d.name, &d.uuid, &d.partuuid, &d.fstype, d.size).hash(&mut hasher);
+    }
+
+    Ok(FormatPlan {
+        device: device.to_path_buf(),
+        target,
+        options: options.clone(),
+        whole_disk: kind == DeviceKind::Disk && !options.partition_table,
+        affected: affected.iter().map(|d| d.path.clone()).collect(),
+        token: format!("{:016x}", hasher.finish()),
+    })
+}
+
+/// Formats a device according to a confirmed plan.
+///
+/// The target is checked again and `token` must match the one of a fresh
+/// plan, so nothing is wiped if the device changed since the plan was shown.
+///
+/// Returns the formatted device, ready to be configured and mounted.
+pub fn format_with_ctx(
+    plan: &FormatPlan,
+    token: &str,
+    ctx: &mut ExecutionContext,
+) -> Result<BlockDevice> {
+    let current = plan_format(&plan.device, &plan.options)?;
+    if current.token != token || current.token != plan.token {
+        return Err(Error::Format {
+            device: plan.device.display().to_string(),
+            message: "confirmation token does not match; the device changed since it was confirmed"
+                .to_string(),
+        });
+    }
+
+    // Partitions first, so their signatures do not resurface in a new layout
+    for affected in current.affected.iter().filter(|p| **p != current.device) {
+        run_format_step(
+            &current,
+            ctx,
+            "wipefs",
+            &["--all", &affected.display().to_string()],
+        )?;
+    }
+    let device = current.device.display().to_string();
+    run_format_step(&current, ctx, "wipefs", &["--all", &device])?;
+
+    if current.options.partition_table {
+        let script = format!(
+            "label: gpt\ntype={}\n",
+            current.options.filesystem.gpt_type()
+        );
+        let output =
+            ctx.run_privileged_with_input("sfdisk", &["--wipe", "always", &device], &script)?;
+        check_output(&current, &output)?;
+        let _ = ctx.run_privileged("udevadm", &["settle"]);
+    }
+
+    let (program, args) = current.options.filesystem.mkfs_command(
+        &current.target,
+        &current.options.label,
+        current.whole_disk,
+    );
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_format_step(&current, ctx, program, &args)?;
+
+    // Wait for udev to probe the new filesystem
+    let _ = ctx.run_privileged("udevadm", &["settle"]);
+
+    disk::list_block_devices()?
+        .into_iter()
+        .find(|d| d.path == current.target)
+        .ok_or_else(|| Error::Format {
+            device: current.target.display().to_string(),
+            message: "formatted device not found".to_string(),
+        })
+}
+
+/// Runs one privileged step of a format operation.
+fn run_format_step(
+    plan: &FormatPlan,
+    ctx: &mut ExecutionContext,
+    program: &str,
+    args: &[&str],
+) -> Result<()> {
+    let output = ctx.run_privileged(program, args)?;
+    check_output(plan, &output)
+}
+
+fn check_output(plan: &FormatPlan, output: &std::process::Output) -> Result<()> {
+    match output.status.code() {
+        Some(0) => Ok(()),
+        Some(126) => Err(Error::AuthenticationCancelled),
+        _ => Err(Error::Format {
+            device: plan.device.display().to_string(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }),
+    }
+}
+
+/// Returns the path of partition `number` of a disk.
+///
+/// Disks whose name ends in a digit use a `p` separator (`/dev/mmcblk0p1`,
+/// `/dev/nvme0n1p1`), others do not (`/dev/sda1`).
+pub fn partition_path(disk: &Path, number: u32) -> PathBuf {
+    let disk = disk.display().to_string();
+    if disk.ends_with(|c: char| c.is_ascii_digit()) {
+        PathBuf::from(format!("{}p{}", disk, number))
+    } else {
+        PathBuf::from(format!("{}{}", disk, number))
+    }
+}
+
+/// Returns true if `name` is `ancestor` or stacked on it.
+fn has_ancestor(name: &str, ancestor: &str, devices: &[BlockDevice]) -> bool {
+    let mut current = name;
+    for _ in 0..=devices.len() {
+        if current == ancestor {
+            return true;
+        }
+        match devices
+            .iter()
+            .find(|d| d.name == current)
+            .and_then(|d| d.pkname.as_deref())
+        {
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(name: &str, pkname: &str, mountpoint: Option<&str>) -> BlockDevice {
+        BlockDevice {
+            name: name.to_string(),
+            uuid: Some(format!("uuid-{}", name)),
+            fstype: Some("exfat".to_string()),
+            mountpoints: mountpoint.map(PathBuf::from).into_iter().collect(),
+            path: PathBuf::from(format!("/dev/{}", name)),
+            pkname: Some(pkname.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn devices() -> Vec<BlockDevice> {
+        vec![
+            part("nvme0n1p1", "nvme0n1", Some("/boot/efi")),
+            part("nvme0n1p8", "nvme0n1", Some("/home")),
+            part("nvme0n1p9", "nvme0n1", None),
+            part("mmcblk0p1", "mmcblk0", None),
+            part("sda1", "sda", Some("/run/media/deck/USB")),
+            part("sdb1", "sdb", None),
+            BlockDevice {
+                holders: vec!["luks-1".to_string()],
+                ..part("sdb2", "sdb", None)
+            },
+        ]
+    }
+
+    #[test]
+    fn test_plan_format_guards() {
+        let devices = devices();
+        let exfat = FormatOptions::new(FormatFilesystem::Exfat, "Games");
+        let plan = |path: &str, options: &FormatOptions| {
+            plan_format_with_devices(Path::new(path), options, &devices)
+        };
+
+        // Whole SD card with a new partition table
+        let gpt = exfat.clone().with_partition_table();
+        let sd = plan("/dev/mmcblk0", &gpt).unwrap();
+        assert_eq!(sd.target, PathBuf::from("/dev/mmcblk0p1"));
+        assert_eq!(sd.affected, vec![PathBuf::from("/dev/mmcblk0p1")]);
+        assert!(!sd.whole_disk);
+        assert!(plan("/dev/mmcblk0", &exfat).unwrap().whole_disk);
+        assert_eq!(sd.token, plan("/dev/mmcblk0", &gpt).unwrap().token);
+        assert_ne!(sd.token, plan("/dev/mmcblk0", &exfat).unwrap().token);
+
+        // System disk, also its unmounted partitions
+        assert!(plan("/dev/nvme0n1", &gpt).is_err());
+        assert!(plan("/dev/nvme0n1p9", &exfat).is_err());
+
+        // Mounted, in use, partition table on a partition
+        assert!(plan("/dev/sda", &gpt).is_err());
+        assert!(plan("/dev/sdb", &gpt).is_err());
+        assert!(plan("/dev/sdb1", &exfat).is_ok());
+        assert!(plan("/dev/sdb1", &gpt).is_err());
+
+        // Labels are validated per filesystem
+        let long = FormatOptions::new(FormatFilesystem::Exfat, "Steam Library");
+        assert!(matches!(
+            plan("/dev/sdb1", &long),
+            Err(Error::InvalidLabel { .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_commands() {
+        assert_eq!(
+            partition_path(Path::new("/dev/mmcblk0"), 1),
+            PathBuf::from("/dev/mmcblk0p1")
+        );
+        assert_eq!(
+            partition_path(Path::new("/dev/sda"), 1),
+            PathBuf::from("/dev/sda1")
+        );
+
+        let (program, args) =
+            FormatFilesystem::Ntfs.mkfs_command(Path::new("/dev/sda"), "Games", true);
+        assert_eq!(program, "mkfs.ntfs");
+        assert_eq!(args, vec!["-Q", "-L", "Games", "-F", "/dev/sda"]);
+    }
+}
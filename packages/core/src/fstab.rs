@@ -8,6 +8,8 @@ use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
+use serde::Serialize;
+
 use crate::Error;
 use crate::error::{IoResultExt, Result};
 
@@ -55,7 +57,8 @@ impl IntoMountOptions for Vec<String> {
 }
 
 /// Represents a single fstab entry.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FstabEntry {
     /// The device identifier (e.g., "UUID=xxx" or "PARTUUID=xxx").
     pub fs_spec: String,
@@ -357,16 +360,23 @@ where
 /// with new entries. Useful for scenarios where file I/O is handled separately
 /// (e.g., with privilege escalation via pkexec).
 pub fn update_managed_entries_content(content: &str, entries: &[FstabEntry]) -> Result<String> {
+    let lines: Vec<String> = entries.iter().map(FstabEntry::to_fstab_line).collect();
+    Ok(replace_managed_block(content, &lines))
+}
+
+/// Replaces the managed block of a file's content with `lines`.
+///
+/// The block is removed if `lines` is empty. Shared by the fstab and
+/// crypttab writers, which use the same markers.
+pub(crate) fn replace_managed_block(content: &str, lines: &[String]) -> String {
     let mut header_lines = Vec::new();
     let mut footer_lines = Vec::new();
     let mut in_managed_block = false;
-    let mut has_managed_block = false;
     let mut past_managed_block = false;
 
     for line in content.lines() {
         if line.trim() == MANAGED_BLOCK_BEGIN {
             in_managed_block = true;
-            has_managed_block = true;
             continue;
         }
 
@@ -386,9 +396,6 @@ pub fn update_managed_entries_content(content: &str, entries: &[FstabEntry]) ->
         }
     }
 
-    // Ensure we don't have a stale marker if the block wasn't properly closed
-    let _ = has_managed_block;
-
     let mut output = String::new();
 
     // Write header lines
@@ -398,14 +405,14 @@ pub fn update_managed_entries_content(content: &str, entries: &[FstabEntry]) ->
     }
 
     // Write managed block if there are entries
-    if !entries.is_empty() {
+    if !lines.is_empty() {
         output.push_str(MANAGED_BLOCK_BEGIN);
         output.push('\n');
         output.push_str(MANAGED_BLOCK_COMMENT);
         output.push('\n');
 
-        for entry in entries {
-            output.push_str(&entry.to_fstab_line());
+        for line in lines {
+            output.push_str(line);
             output.push('\n');
         }
 
@@ -419,7 +426,7 @@ pub fn update_managed_entries_content(content: &str, entries: &[FstabEntry]) ->
         output.push('\n');
     }
 
-    Ok(output)
+    output
 }
 
 fn prune_backups_with_ctx(
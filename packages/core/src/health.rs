@@ -0,0 +1,325 @@
+//! SMART and NVMe health reporting per physical disk.
+//!
+//! Health data is read with `smartctl --json --all` through the privileged
+//! context, as SMART queries need root. Each physical disk is queried once,
+//! no matter how many partitions it has. The JSON report is parsed into a
+//! [`DiskHealth`] with the values that matter for game drives: temperature,
+//! wear (NVMe "percentage used"), media errors and reallocated sectors.
+//!
+//! [`DiskHealth::warnings`] compares these values with [`HealthThresholds`].
+//! Disks behind USB bridges often do not pass SMART through; they simply
+//! have no health data.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::disk::{self, BlockDevice};
+use crate::error::{Error, Result};
+use crate::executor::ExecutionContext;
+
+/// ATA attribute ID of "Reallocated_Sector_Ct".
+const ATA_REALLOCATED_SECTORS: u64 = 5;
+
+/// ATA attribute ID of "Current_Pending_Sector".
+const ATA_PENDING_SECTORS: u64 = 197;
+
+/// smartctl exit status bits meaning the report could not be produced
+/// (command line error, device could not be opened).
+const SMARTCTL_FATAL_BITS: i32 = 0b11;
+
+/// Health report of a physical disk.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskHealth {
+    /// Disk device path (e.g., "/dev/nvme0n1").
+    pub disk: PathBuf,
+    /// Drive model as reported by smartctl.
+    pub model: Option<String>,
+    /// Overall SMART self-assessment. None if not reported.
+    pub passed: Option<bool>,
+    /// Current temperature in degrees Celsius.
+    pub temperature_celsius: Option<u32>,
+    /// NVMe estimate of the used endurance in percent (may exceed 100).
+    pub percentage_used: Option<u32>,
+    /// NVMe media and data integrity errors.
+    pub media_errors: Option<u64>,
+    /// ATA reallocated sectors.
+    pub reallocated_sectors: Option<u64>,
+    /// ATA sectors waiting to be reallocated.
+    pub pending_sectors: Option<u64>,
+    /// Power-on time in hours.
+    pub power_on_hours: Option<u64>,
+}
+
+/// A health value that crossed its threshold.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum HealthWarning {
+    /// The drive's SMART self-assessment failed.
+    SmartFailed,
+    /// The drive is running hot.
+    HighTemperature { celsius: u32 },
+    /// The drive's rated endurance is (nearly) used up.
+    Worn { percentage_used: u32 },
+    /// The drive reported media errors.
+    MediaErrors { count: u64 },
+    /// Sectors were reallocated or are pending reallocation.
+    BadSectors { reallocated: u64, pending: u64 },
+}
+
+impl std::fmt::Display for HealthWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SmartFailed => write!(f, "SMART self-assessment failed"),
+            Self::HighTemperature { celsius } => write!(f, "temperature is {}°C", celsius),
+            Self::Worn { percentage_used } => {
+                write!(f, "{}% of rated endurance used", percentage_used)
+            }
+            Self::MediaErrors { count } => write!(f, "{} media errors", count),
+            Self::BadSectors {
+                reallocated,
+                pending,
+            } => write!(
+                f,
+                "{} reallocated and {} pending sectors",
+                reallocated, pending
+            ),
+        }
+    }
+}
+
+/// Limits above which a [`HealthWarning`] is raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthThresholds {
+    /// Warn at or above this temperature (°C).
+    pub temperature_celsius: u32,
+    /// Warn at or above this wear level (percent).
+    pub percentage_used: u32,
+    /// Warn above this many media errors.
+    pub media_errors: u64,
+    /// Warn above this many reallocated plus pending sectors.
+    pub bad_sectors: u64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            temperature_celsius: 70,
+            percentage_used: 90,
+            media_errors: 0,
+            bad_sectors: 0,
+        }
+    }
+}
+
+impl DiskHealth {
+    /// Returns the warnings for values crossing the given thresholds.
+    pub fn warnings(&self, thresholds: &HealthThresholds) -> Vec<HealthWarning> {
+        let mut warnings = Vec::new();
+        if self.passed == Some(false) {
+            warnings.push(HealthWarning::SmartFailed);
+        }
+        if let Some(celsius) = self
+            .temperature_celsius
+            .filter(|t| *t >= thresholds.temperature_celsius)
+        {
+            warnings.push(HealthWarning::HighTemperature { celsius });
+        }
+        if let Some(percentage_used) = self
+            .percentage_used
+            .filter(|p| *p >= thresholds.percentage_used)
+        {
+            warnings.push(HealthWarning::Worn { percentage_used });
+        }
+        if let Some(count) = self.media_errors.filter(|c| *c > thresholds.media_errors) {
+            warnings.push(HealthWarning::MediaErrors { count });
+        }
+        let reallocated = self.reallocated_sectors.unwrap_or(0);
+        let pending = self.pending_sectors.unwrap_or(0);
+        if reallocated + pending > thresholds.bad_sectors {
+            warnings.push(HealthWarning::BadSectors {
+                reallocated,
+                pending,
+            });
+        }
+        warnings
+    }
+}
+
+/// Parses the output of `smartctl --json --all`.
+pub fn parse_smartctl_json(disk: &Path, json: &str) -> Result<DiskHealth> {
+    let report: serde_json::Value = serde_json::from_str(json).map_err(|e| Error::Health {
+        disk: disk.display().to_string(),
+        message: format!("invalid smartctl output: {}", e),
+    })?;
+
+    let nvme = &report["nvme_smart_health_information_log"];
+    let ata_attribute = |id: u64| {
+        report["ata_smart_attributes"]["table"]
+            .as_array()?
+            .iter()
+            .find(|attr| attr["id"].as_u64() == Some(id))?["raw"]["value"]
+            .as_u64()
+    };
+
+    Ok(DiskHealth {
+        disk: disk.to_path_buf(),
+        model: report["model_name"].as_str().map(str::to_string),
+        passed: report["smart_status"]["passed"].as_bool(),
+        temperature_celsius: report["temperature"]["current"]
+            .as_u64()
+            .or_else(|| nvme["temperature"].as_u64())
+            .and_then(|t| u32::try_from(t).ok()),
+        percentage_used: nvme["percentage_used"]
+            .as_u64()
+            .and_then(|p| u32::try_from(p).ok()),
+        media_errors: nvme["media_errors"].as_u64(),
+        reallocated_sectors: ata_attribute(ATA_REALLOCATED_SECTORS),
+        pending_sectors: ata_attribute(ATA_PENDING_SECTORS),
+        power_on_hours: report["power_on_time"]["hours"]
+            .as_u64()
+            .or_else(|| nvme["power_on_hours"].as_u64()),
+    })
+}
+
+/// Reads the health of a physical disk with `smartctl`.
+///
+/// smartctl sets status bits for failing drives too; only a report that
+/// could not be produced at all is an error.
+pub fn read_disk_health_with_ctx(disk: &Path, ctx: &mut ExecutionContext) -> Result<DiskHealth> {
+    let disk_str = disk.display().to_string();
+    let output = ctx.run_privileged("smartctl", &["--json", "--all", &disk_str])?;
+
+    match output.status.code() {
+        Some(126) => return Err(Error::AuthenticationCancelled),
+        Some(code) if code & SMARTCTL_FATAL_BITS == 0 => {}
+        _ => {
+            return Err(Error::Health {
+                disk: disk_str,
+                message: smartctl_error(&output.stdout)
+                    .unwrap_or_else(|| String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            });
+        }
+    }
+
+    parse_smartctl_json(disk, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extracts the error messages smartctl reports in its JSON output.
+fn smartctl_error(stdout: &[u8]) -> Option<String> {
+    let report: serde_json::Value = serde_json::from_slice(stdout).ok()?;
+    let messages: Vec<&str> = report["smartctl"]["messages"]
+        .as_array()?
+        .iter()
+        .filter_map(|m| m["string"].as_str())
+        .collect();
+    (!messages.is_empty()).then(|| messages.join("; "))
+}
+
+/// Returns the path of the physical disk a block device lives on.
+pub fn disk_path(device: &BlockDevice, devices: &[BlockDevice]) -> PathBuf {
+    PathBuf::from(format!("/dev/{}", disk::disk_name(&device.name, devices)))
+}
+
+/// Reads the health of several disks, querying each disk once.
+///
+/// Returns the reports keyed by disk path. Disks whose health cannot be read
+/// (e.g. USB bridges without SMART pass-through) are left out; a cancelled
+/// authentication is returned as an error.
+pub fn read_health_with_ctx(
+    disks: &[PathBuf],
+    ctx: &mut ExecutionContext,
+) -> Result<HashMap<PathBuf, DiskHealth>> {
+    let mut reports = HashMap::new();
+    for disk in disks {
+        if reports.contains_key(disk) {
+            continue;
+        }
+        match read_disk_health_with_ctx(disk, ctx) {
+            Ok(health) => {
+                reports.insert(disk.clone(), health);
+            }
+            Err(Error::AuthenticationCancelled) => return Err(Error::AuthenticationCancelled),
+            Err(_) => {}
+        }
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nvme_report() {
+        let json = r#"{
+            "smartctl": {"exit_status": 0},
+            "model_name": "Samsung SSD 980 1TB",
+            "smart_status": {"passed": true},
+            "temperature": {"current": 74},
+            "power_on_time": {"hours": 1234},
+            "nvme_smart_health_information_log": {
+                "temperature": 74,
+                "percentage_used": 93,
+                "media_errors": 2,
+                "power_on_hours": 1234
+            }
+        }"#;
+        let health = parse_smartctl_json(Path::new("/dev/nvme0n1"), json).unwrap();
+        assert_eq!(health.model.as_deref(), Some("Samsung SSD 980 1TB"));
+        assert_eq!(health.temperature_celsius, Some(74));
+        assert_eq!(health.percentage_used, Some(93));
+        assert_eq!(health.media_errors, Some(2));
+        assert_eq!(health.reallocated_sectors, None);
+
+        assert_eq!(
+            health.warnings(&HealthThresholds::default()),
+            vec![
+                HealthWarning::HighTemperature { celsius: 74 },
+                HealthWarning::Worn {
+                    percentage_used: 93
+                },
+                HealthWarning::MediaErrors { count: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ata_report() {
+        let json = r#"{
+            "smart_status": {"passed": false},
+            "temperature": {"current": 38},
+            "ata_smart_attributes": {"table": [
+                {"id": 5, "name": "Reallocated_Sector_Ct", "raw": {"value": 8}},
+                {"id": 9, "name": "Power_On_Hours", "raw": {"value": 20000}},
+                {"id": 197, "name": "Current_Pending_Sector", "raw": {"value": 0}}
+            ]}
+        }"#;
+        let health = parse_smartctl_json(Path::new("/dev/sda"), json).unwrap();
+        assert_eq!(health.reallocated_sectors, Some(8));
+        assert_eq!(health.pending_sectors, Some(0));
+        assert_eq!(
+            health.warnings(&HealthThresholds::default()),
+            vec![
+                HealthWarning::SmartFailed,
+                HealthWarning::BadSectors {
+                    reallocated: 8,
+                    pending: 0
+                },
+            ]
+        );
+
+        assert!(parse_smartctl_json(Path::new("/dev/sda"), "not json").is_err());
+        assert_eq!(
+            smartctl_error(br#"{"smartctl": {"messages": [{"string": "Unknown USB bridge"}]}}"#)
+                .as_deref(),
+            Some("Unknown USB bridge")
+        );
+    }
+}
@@ -7,11 +7,21 @@
 //!
 //! - [`device`]: Unified device abstraction (primary API)
 //! - [`disk`]: Disk scanning using `lsblk`
+//! - [`sysfs`]: Disk scanning via sysfs and the udev database (lsblk fallback)
 //! - [`preset`]: Mount preset definitions (SSD, Portable)
 //! - [`fstab`]: Fstab parsing and writing
+//! - [`crypt`]: LUKS/BitLocker unlocking and managed crypttab entries
+//! - [`format`]: Formatting drives as exFAT/NTFS/ext4 (with safety guards)
+//! - [`health`]: SMART/NVMe health of physical disks
+//! - [`mount_unit`]: Native systemd `.mount`/`.automount` units (alternative to fstab)
 //! - [`mount`]: Mount/unmount operations and dirty volume handling
+//! - [`monitor`]: Hotplug and mount change events
+//! - [`udisks`]: Coexistence with the udisks2/SteamOS automounter
 //! - [`steam`]: Steam VDF parsing and library injection
+//! - [`usage`]: Free/used space of devices and Steam libraries
 //! - [`syscall`]: Systemd control (daemon-reload, session switching)
+//! - [`session`]: Current session detection and scheduled return to Game Mode
+//! - [`systemd_dbus`]: systemd D-Bus backend used by [`syscall`]
 //! - [`executor`]: Command execution with privilege escalation
 //! - [`protocol`]: Daemon communication protocol (HMAC-SHA256)
 //! - [`error`]: Error types
@@ -52,25 +62,35 @@
 //! }
 //! ```
 
+pub mod crypt;
 pub mod device;
 pub mod disk;
 pub mod error;
 pub mod executor;
+pub mod format;
 pub mod fstab;
+pub mod health;
+pub mod monitor;
 pub mod mount;
+pub mod mount_unit;
 pub mod preset;
 pub mod protocol;
+pub mod session;
 pub mod steam;
 pub mod syscall;
+pub mod sysfs;
+pub mod systemd_dbus;
+pub mod udisks;
+pub mod usage;
 
 // Re-export commonly used types
 pub use device::{
-    Device, DeviceConnectionState, ListDevicesConfig, find_online_block_device_by_uuid,
-    list_devices,
+    BlockMetadata, Device, DeviceConnectionState, ListDevicesConfig, MountConflict,
+    PersistenceBackend, RelabelOptions, find_online_block_device_by_uuid, list_devices,
 };
 pub use disk::{
-    BlockDevice, ManagedDevice, ManagedDevicesResult, OfflineDevice, normalize_fstype,
-    vfs_type_to_fstype,
+    BlockDevice, DeviceKind, Encryption, ManagedDevice, ManagedDevicesResult, OfflineDevice,
+    normalize_fstype, vfs_type_to_fstype,
 };
 pub use error::{Error, Result};
 pub use executor::{
@@ -79,4 +99,8 @@ pub use executor::{
 };
 pub use fstab::FstabEntry;
 pub use preset::{MountPreset, PresetConfig};
-pub use steam::LibraryFolder;
+pub use steam::compatdata::CompatdataStatus;
+pub use steam::{
+    InstalledApp, LibraryFolder, MovedApp, RestartStrategy, SteamFlavour, SteamInstall,
+    SteamProcess, SteamSession, SteamShutdown,
+};
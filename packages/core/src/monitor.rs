@@ -0,0 +1,531 @@
+//! Hotplug and mount monitoring.
+//!
+//! [`device::list_devices`] is a one-shot snapshot. [`DeviceMonitor`] keeps a snapshot
+//! up to date by watching `/dev/disk/by-uuid` and `/dev/disk/by-partuuid` with
+//! inotify (udev creates and removes these links on hotplug) and
+//! `/proc/self/mounts` with poll(2) (the kernel flags it on every mount table
+//! change). On each change the device list is rebuilt and compared with the
+//! previous one, producing typed [`DeviceEvent`]s.
+//!
+//! Dirty NTFS volumes are found in the kernel log. Only messages newer than
+//! the monitor's start and the device's last observed mount are considered,
+//! so a volume that was repaired and mounted cleanly is no longer reported.
+//!
+//! Events can be consumed by calling [`DeviceMonitor::next_events`], by
+//! iterating over the monitor, or from a background thread via
+//! [`DeviceMonitor::spawn`].
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::fd::AsFd;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::time::{ClockId, clock_gettime};
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::device::{self, Device, ListDevicesConfig};
+use crate::error::{MonitorSnafu, Result};
+use crate::mount;
+
+/// Directories whose links udev creates and removes on hotplug.
+const WATCHED_DIRS: &[&str] = &["/dev/disk/by-uuid", "/dev/disk/by-partuuid"];
+
+/// Parent directory watched so that the link directories appearing later are noticed.
+const DISK_DIR: &str = "/dev/disk";
+
+/// Kernel mount table, flagged with POLLPRI on every change.
+const MOUNTS_PATH: &str = "/proc/self/mounts";
+
+/// Delay before rescanning after a change, so that udev has finished probing.
+const SETTLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Interval of unconditional rescans (catches changes without inotify events).
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A change to the set of known devices.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "device", rename_all = "camelCase")]
+pub enum DeviceEvent {
+    /// A device was connected (or a configured offline device came online).
+    DeviceAdded(Device),
+    /// A device was disconnected.
+    DeviceRemoved(Device),
+    /// A device was mounted (including at an additional mount point).
+    Mounted(Device),
+    /// A device was unmounted (from at least one mount point).
+    Unmounted(Device),
+    /// A dirty NTFS volume was detected on a device.
+    BecameDirty(Device),
+}
+
+impl DeviceEvent {
+    /// Returns the device the event refers to (in its updated state).
+    pub fn device(&self) -> &Device {
+        match self {
+            Self::DeviceAdded(d)
+            | Self::DeviceRemoved(d)
+            | Self::Mounted(d)
+            | Self::Unmounted(d)
+            | Self::BecameDirty(d) => d,
+        }
+    }
+}
+
+/// Computes the events that turn `old` into `new`.
+///
+/// Devices are matched by UUID, then PARTUUID, fs_spec and device path.
+/// Configuration-only changes (offline entries appearing or disappearing)
+/// produce no events.
+pub fn diff_devices(old: &[Device], new: &[Device]) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+
+    for new_device in new {
+        let key = device_key(new_device);
+        let Some(old_device) = old.iter().find(|d| device_key(d) == key) else {
+            if !new_device.is_offline() {
+                events.push(DeviceEvent::DeviceAdded(new_device.clone()));
+            }
+            continue;
+        };
+
+        match (old_device.is_offline(), new_device.is_offline()) {
+            (true, false) => events.push(DeviceEvent::DeviceAdded(new_device.clone())),
+            (false, true) => events.push(DeviceEvent::DeviceRemoved(new_device.clone())),
+            (true, true) => {}
+            (false, false) => {
+                // A udisks automount next to the fstab mount also counts
+                let gained = (!old_device.is_mounted && new_device.is_mounted)
+                    || new_device
+                        .mountpoints
+                        .iter()
+                        .any(|p| !old_device.mountpoints.contains(p));
+                let lost = (old_device.is_mounted && !new_device.is_mounted)
+                    || old_device
+                        .mountpoints
+                        .iter()
+                        .any(|p| !new_device.mountpoints.contains(p));
+                if gained {
+                    events.push(DeviceEvent::Mounted(new_device.clone()));
+                } else if lost {
+                    events.push(DeviceEvent::Unmounted(new_device.clone()));
+                }
+            }
+        }
+
+        if !old_device.is_dirty && new_device.is_dirty {
+            events.push(DeviceEvent::BecameDirty(new_device.clone()));
+        }
+    }
+
+    for old_device in old {
+        let key = device_key(old_device);
+        if !old_device.is_offline() && !new.iter().any(|d| device_key(d) == key) {
+            events.push(DeviceEvent::DeviceRemoved(old_device.clone()));
+        }
+    }
+
+    events
+}
+
+/// Identity of a device across snapshots.
+fn device_key(device: &Device) -> String {
+    device
+        .uuid
+        .clone()
+        .or_else(|| device.partuuid.clone())
+        .or_else(|| device.fs_spec.clone())
+        .or_else(|| device.path.as_ref().map(|p| p.display().to_string()))
+        .unwrap_or_else(|| device.name.clone())
+}
+
+/// Watches for hotplug and mount changes and reports them as [`DeviceEvent`]s.
+pub struct DeviceMonitor {
+    config: ListDevicesConfig,
+    devices: Vec<Device>,
+    inotify: Inotify,
+    mounts: File,
+    pending: VecDeque<DeviceEvent>,
+    /// Kernel log time at which the monitor was created.
+    started_at: Duration,
+    /// Kernel log time at which each device (by kernel name) was last seen mounted.
+    mounted_at: HashMap<String, Duration>,
+}
+
+impl std::fmt::Debug for DeviceMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeviceMonitor")
+            .field("config", &self.config)
+            .field("devices", &self.devices)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeviceMonitor {
+    /// Creates a monitor and takes the initial device snapshot.
+    pub fn new(config: ListDevicesConfig) -> Result<Self> {
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+            .map_err(std::io::Error::from)
+            .context(MonitorSnafu {
+                operation: "inotify_init",
+            })?;
+        add_watches(&inotify);
+
+        let mounts = File::open(MOUNTS_PATH).context(MonitorSnafu {
+            operation: format!("open {}", MOUNTS_PATH),
+        })?;
+
+        let mut monitor = Self {
+            config,
+            devices: Vec::new(),
+            inotify,
+            mounts,
+            pending: VecDeque::new(),
+            started_at: kernel_log_time(),
+            mounted_at: HashMap::new(),
+        };
+        monitor.devices = monitor.snapshot()?;
+        monitor.consume_mounts();
+        Ok(monitor)
+    }
+
+    /// Returns the current device snapshot.
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
+    }
+
+    /// Rescans devices and returns the changes since the last snapshot.
+    pub fn refresh(&mut self) -> Result<Vec<DeviceEvent>> {
+        let devices = self.snapshot()?;
+        let events = diff_devices(&self.devices, &devices);
+        self.devices = devices;
+        Ok(events)
+    }
+
+    /// Blocks until devices change and returns the resulting events.
+    ///
+    /// Never returns an empty list; spurious wakeups are absorbed.
+    pub fn next_events(&mut self) -> Result<Vec<DeviceEvent>> {
+        loop {
+            self.wait_for_change()?;
+            std::thread::sleep(SETTLE_DELAY);
+
+            // Drain everything that arrived while settling
+            self.drain_inotify();
+            self.consume_mounts();
+
+            let events = self.refresh()?;
+            if !events.is_empty() {
+                return Ok(events);
+            }
+        }
+    }
+
+    /// Moves the monitor to a background thread and returns a channel of events.
+    ///
+    /// The thread stops when the receiver is dropped or monitoring fails; in
+    /// the latter case the channel is closed.
+    pub fn spawn(mut self) -> mpsc::Receiver<DeviceEvent> {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            while let Ok(events) = self.next_events() {
+                for event in events {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Lists devices and marks dirty NTFS volumes reported by the kernel.
+    fn snapshot(&mut self) -> Result<Vec<Device>> {
+        let mut devices = device::list_devices(&self.config)?;
+
+        let now = kernel_log_time();
+        for device in devices.iter().filter(|d| d.is_mounted) {
+            if let Some(name) = kernel_name(device) {
+                self.mounted_at.insert(name.to_string(), now);
+            }
+        }
+
+        mark_dirty_devices(&mut devices, |name| {
+            self.mounted_at
+                .get(name)
+                .map_or(self.started_at, |&mounted| mounted.max(self.started_at))
+        });
+        Ok(devices)
+    }
+
+    /// Waits until inotify or the mount table signals a change, or the rescan interval elapses.
+    fn wait_for_change(&mut self) -> Result<()> {
+        let timeout = PollTimeout::try_from(RESCAN_INTERVAL).unwrap_or(PollTimeout::MAX);
+        let mut fds = [
+            PollFd::new(self.inotify.as_fd(), PollFlags::POLLIN),
+            PollFd::new(self.mounts.as_fd(), PollFlags::POLLPRI),
+        ];
+
+        match poll(&mut fds, timeout) {
+            Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+            Err(e) => {
+                return Err(std::io::Error::from(e)).context(MonitorSnafu { operation: "poll" });
+            }
+        }
+
+        let disk_dir_changed = self.drain_inotify();
+        if disk_dir_changed {
+            // by-uuid / by-partuuid may have been created since the last attempt
+            add_watches(&self.inotify);
+        }
+        Ok(())
+    }
+
+    /// Reads all pending inotify events. Returns true if any were read.
+    fn drain_inotify(&self) -> bool {
+        let mut any = false;
+        while let Ok(events) = self.inotify.read_events() {
+            if events.is_empty() {
+                break;
+            }
+            any = true;
+        }
+        any
+    }
+
+    /// Re-reads the mount table, which clears its POLLPRI state.
+    fn consume_mounts(&mut self) {
+        let mut buf = String::new();
+        let _ = self.mounts.seek(SeekFrom::Start(0));
+        let _ = self.mounts.read_to_string(&mut buf);
+    }
+}
+
+impl Iterator for DeviceMonitor {
+    type Item = Result<DeviceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            match self.next_events() {
+                Ok(events) => self.pending.extend(events),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Adds inotify watches on the link directories, ignoring missing ones.
+fn add_watches(inotify: &Inotify) {
+    let flags = AddWatchFlags::IN_CREATE | AddWatchFlags::IN_DELETE | AddWatchFlags::IN_MOVED_TO;
+    let _ = inotify.add_watch(DISK_DIR, flags);
+    for dir in WATCHED_DIRS {
+        let _ = inotify.add_watch(*dir, flags);
+    }
+}
+
+/// Sets `is_dirty` on online NTFS devices the kernel log reports as dirty.
+///
+/// Only messages logged after `since(kernel name)` count. Reading the kernel
+/// log may be restricted (`kernel.dmesg_restrict=1`); in that case devices
+/// are left unchanged.
+fn mark_dirty_devices(devices: &mut [Device], since: impl Fn(&str) -> Duration) {
+    let candidates: Vec<usize> = devices
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| !d.is_offline() && !d.is_mounted && d.fstype == "ntfs")
+        .map(|(i, _)| i)
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+
+    let Ok(output) = Command::new("dmesg").output() else {
+        return;
+    };
+    if !output.status.success() {
+        return;
+    }
+    let log = String::from_utf8_lossy(&output.stdout);
+
+    for i in candidates {
+        let Some(name) = kernel_name(&devices[i]) else {
+            continue;
+        };
+        let recent = kernel_log_since(&log, since(name));
+        devices[i].is_dirty = mount::is_dirty_in_kernel_log(&recent, name);
+    }
+}
+
+/// Kernel name of a device (e.g., "sda1"), as used in kernel messages.
+fn kernel_name(device: &Device) -> Option<&str> {
+    device
+        .path
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+}
+
+/// Current time on the clock that stamps kernel log messages.
+///
+/// The kernel log uses a monotonic clock that stops during suspend, unlike
+/// `/proc/uptime`.
+fn kernel_log_time() -> Duration {
+    clock_gettime(ClockId::CLOCK_MONOTONIC)
+        .map(Duration::from)
+        .unwrap_or_default()
+}
+
+/// Returns the lines of `dmesg` output logged after `since`.
+///
+/// Lines without a `[seconds.micros]` timestamp cannot be dated and are dropped.
+fn kernel_log_since(log: &str, since: Duration) -> String {
+    log.lines()
+        .filter(|line| log_timestamp(line).is_some_and(|time| time > since))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses the `[    5.123456]` prefix of a `dmesg` line.
+fn log_timestamp(line: &str) -> Option<Duration> {
+    let (stamp, _) = line.trim_start().strip_prefix('[')?.split_once(']')?;
+    let seconds: f64 = stamp.trim().parse().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::DeviceConnectionState;
+    use std::path::PathBuf;
+
+    fn device(uuid: &str, online: bool, mounted: bool) -> Device {
+        let mut device = Device::new_for_test(uuid);
+        device.connection_state = if online {
+            DeviceConnectionState::Online
+        } else {
+            DeviceConnectionState::Offline
+        };
+        device.path = online.then(|| PathBuf::from("/dev/sda1"));
+        device.is_mounted = mounted;
+        if mounted {
+            device.mountpoints = vec![PathBuf::from("/home/deck/Drives").join(uuid)];
+        }
+        device
+    }
+
+    fn kinds(events: &[DeviceEvent]) -> Vec<&'static str> {
+        events
+            .iter()
+            .map(|e| match e {
+                DeviceEvent::DeviceAdded(_) => "added",
+                DeviceEvent::DeviceRemoved(_) => "removed",
+                DeviceEvent::Mounted(_) => "mounted",
+                DeviceEvent::Unmounted(_) => "unmounted",
+                DeviceEvent::BecameDirty(_) => "dirty",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_added_and_removed() {
+        let old = vec![device("A", true, false)];
+        let new = vec![device("B", true, false)];
+        let events = diff_devices(&old, &new);
+        assert_eq!(kinds(&events), vec!["added", "removed"]);
+        assert_eq!(events[0].device().uuid.as_deref(), Some("B"));
+        assert_eq!(events[1].device().uuid.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn test_diff_offline_transitions() {
+        // Configured device plugged in
+        let events = diff_devices(&[device("A", false, false)], &[device("A", true, false)]);
+        assert_eq!(kinds(&events), vec!["added"]);
+
+        // Configured device unplugged
+        let events = diff_devices(&[device("A", true, true)], &[device("A", false, false)]);
+        assert_eq!(kinds(&events), vec!["removed"]);
+
+        // New offline configuration is not a hotplug event
+        let events = diff_devices(&[], &[device("A", false, false)]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_diff_mount_state() {
+        let events = diff_devices(&[device("A", true, false)], &[device("A", true, true)]);
+        assert_eq!(kinds(&events), vec!["mounted"]);
+
+        let events = diff_devices(&[device("A", true, true)], &[device("A", true, false)]);
+        assert_eq!(kinds(&events), vec!["unmounted"]);
+
+        let events = diff_devices(&[device("A", true, true)], &[device("A", true, true)]);
+        assert!(events.is_empty());
+
+        // Additional udisks automount while already mounted
+        let mut automounted = device("A", true, true);
+        automounted
+            .mountpoints
+            .push(PathBuf::from("/run/media/deck/A"));
+        let events = diff_devices(
+            &[device("A", true, true)],
+            std::slice::from_ref(&automounted),
+        );
+        assert_eq!(kinds(&events), vec!["mounted"]);
+        let events = diff_devices(
+            std::slice::from_ref(&automounted),
+            &[device("A", true, true)],
+        );
+        assert_eq!(kinds(&events), vec!["unmounted"]);
+    }
+
+    #[test]
+    fn test_diff_became_dirty() {
+        let mut dirty = device("A", true, false);
+        dirty.is_dirty = true;
+        let events = diff_devices(&[device("A", true, false)], std::slice::from_ref(&dirty));
+        assert_eq!(kinds(&events), vec!["dirty"]);
+
+        // Staying dirty does not repeat the event
+        let events = diff_devices(std::slice::from_ref(&dirty), std::slice::from_ref(&dirty));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = DeviceEvent::Mounted(device("A", true, true));
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "mounted");
+        assert_eq!(json["device"]["uuid"], "A");
+        assert_eq!(json["device"]["isMounted"], true);
+    }
+
+    #[test]
+    fn test_kernel_log_since() {
+        let log = "[    5.000000] ntfs3: sda1: volume is dirty and \"force\" flag is not set!\n\
+                   [   60.500000] usb 1-1: new high-speed USB device\n\
+                   undated continuation line\n\
+                   [  120.250000] ntfs3: sdb1: volume is dirty and \"force\" flag is not set!\n";
+
+        assert_eq!(
+            log_timestamp("[   60.500000] usb 1-1"),
+            Some(Duration::from_millis(60_500))
+        );
+        assert_eq!(log_timestamp("no timestamp"), None);
+
+        // An old message no longer marks the device dirty
+        let recent = kernel_log_since(log, Duration::from_secs(10));
+        assert!(!mount::is_dirty_in_kernel_log(&recent, "sda1"));
+        assert!(mount::is_dirty_in_kernel_log(&recent, "sdb1"));
+        assert!(!recent.contains("undated"));
+
+        let recent = kernel_log_since(log, Duration::from_secs(200));
+        assert!(recent.is_empty());
+    }
+}
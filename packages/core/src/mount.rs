@@ -9,6 +9,15 @@ use std::path::Path;
 use crate::disk::BlockDevice;
 use crate::error::{Error, IoResultExt, Result};
 use crate::executor::ExecutionContext;
+use crate::sysfs;
+
+/// Directory udisks2 automounts removable media under (`/run/media/<user>/<label>`).
+pub const UDISKS_MOUNT_ROOT: &str = "/run/media";
+
+/// Returns true if `path` is a udisks2 automount point.
+pub fn is_udisks_mount_point(path: &Path) -> bool {
+    path.starts_with(UDISKS_MOUNT_ROOT) && path != Path::new(UDISKS_MOUNT_ROOT)
+}
 
 /// Creates a mount point directory if it doesn't exist.
 ///
@@ -123,7 +132,18 @@ pub fn unmount_device(mount_point: &Path) -> Result<()> {
 }
 
 /// Unmounts a device with privilege escalation support.
+///
+/// Mounts below the mount point, such as the bind mount of a redirected
+/// Proton prefix directory, would keep it busy and are unmounted first.
 pub fn unmount_device_with_ctx(mount_point: &Path, ctx: &mut ExecutionContext) -> Result<()> {
+    for nested in sysfs::nested_mount_points(mount_point) {
+        unmount_with_ctx(&nested, ctx)?;
+    }
+    unmount_with_ctx(mount_point, ctx)
+}
+
+/// Runs `umount` on a single mount point.
+fn unmount_with_ctx(mount_point: &Path, ctx: &mut ExecutionContext) -> Result<()> {
     let mount_point_str = mount_point.display().to_string();
     let output = ctx.run_privileged("umount", &[&mount_point_str])?;
 
@@ -187,14 +207,18 @@ pub fn detect_dirty_volume_with_ctx(
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let device_name = &device.name;
 
-    // Look for dirty volume messages related to this device
-    let is_dirty = stdout
-        .lines()
-        .any(|line| line.contains(device_name) && is_dirty_volume_error(line));
+    Ok(is_dirty_in_kernel_log(&stdout, &device.name))
+}
 
-    Ok(is_dirty)
+/// Checks kernel log output for dirty volume messages about a device.
+///
+/// # Arguments
+/// * `log` - Output of `dmesg`
+/// * `device_name` - Kernel device name (e.g., "sda1")
+pub fn is_dirty_in_kernel_log(log: &str, device_name: &str) -> bool {
+    log.lines()
+        .any(|line| line.contains(device_name) && is_dirty_volume_error(line))
 }
 
 /// Attempts to repair a dirty NTFS volume using ntfsfix.
@@ -247,15 +271,16 @@ pub fn reload_systemd_daemon_with_ctx(ctx: &mut ExecutionContext) -> Result<()>
 
 /// Starts a systemd mount unit for a mount point.
 ///
-/// The unit name is derived from the mount point path.
-pub fn start_mount_unit(mount_point: &Path) -> Result<()> {
-    let unit_name = crate::syscall::mount_point_to_unit_name(mount_point);
+/// The unit name is derived from the mount point path. Returns the unit state
+/// once the start job has completed.
+pub fn start_mount_unit(mount_point: &Path) -> Result<crate::syscall::UnitStatus> {
+    let unit_name = crate::syscall::mount_point_to_unit_name(mount_point)?;
     crate::syscall::start_unit(&unit_name)
 }
 
 /// Stops a systemd mount unit for a mount point.
-pub fn stop_mount_unit(mount_point: &Path) -> Result<()> {
-    let unit_name = crate::syscall::mount_point_to_unit_name(mount_point);
+pub fn stop_mount_unit(mount_point: &Path) -> Result<crate::syscall::UnitStatus> {
+    let unit_name = crate::syscall::mount_point_to_unit_name(mount_point)?;
     crate::syscall::stop_unit(&unit_name)
 }
 
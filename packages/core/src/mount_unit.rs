@@ -0,0 +1,678 @@
+//! Native systemd mount unit persistence.
+//!
+//! This module is an alternative to [`crate::fstab`] for systems where
+//! `/etc/fstab` is immutable or overwritten on update. Instead of a managed
+//! fstab block, it writes `<escaped>.mount` (and optionally `<escaped>.automount`)
+//! units to `/etc/systemd/system`, generated from the same [`PresetConfig`].
+//!
+//! Managed unit files carry a marker comment so that they can be listed and
+//! removed without touching units created by other tools.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use snafu::ResultExt;
+
+use crate::error::{Error, MountUnitReadSnafu, Result};
+use crate::executor::ExecutionContext;
+use crate::fstab::FstabEntry;
+use crate::preset::PresetConfig;
+use crate::syscall;
+
+/// Default directory for administrator-installed unit files.
+pub const SYSTEMD_UNIT_DIR: &str = "/etc/systemd/system";
+
+/// Marker comment identifying unit files managed by this tool.
+pub const MANAGED_UNIT_MARKER: &str =
+    "# Created by SteamOS Mount Tool. DO NOT EDIT THIS FILE MANUALLY.";
+
+/// Target that managed units are installed into.
+const INSTALL_TARGET: &str = "local-fs.target";
+
+/// A mount described as a pair of systemd `.mount` / `.automount` units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountUnit {
+    /// Device node to mount (e.g., "/dev/disk/by-uuid/1234-5678").
+    pub what: String,
+    /// Mount point path.
+    pub mount_point: PathBuf,
+    /// Filesystem type (e.g., "ntfs3", "exfat").
+    pub vfs_type: String,
+    /// Mount options passed to mount(8), without `x-systemd.*` and `noauto`/`nofail`.
+    pub options: Vec<String>,
+    /// Whether an `.automount` unit is generated (mount on access).
+    pub automount: bool,
+    /// `TimeoutIdleSec=` of the automount unit.
+    pub idle_timeout_secs: Option<u32>,
+    /// How long the mount job waits for the device before failing.
+    pub device_timeout_secs: Option<u32>,
+}
+
+impl MountUnit {
+    /// Creates a mount unit from a preset configuration.
+    ///
+    /// # Arguments
+    /// * `config` - The preset configuration (same as used for fstab)
+    /// * `fs_spec` - The device identifier (e.g., "UUID=xxx", "PARTUUID=xxx")
+    /// * `mount_point` - The mount point path
+    /// * `uid` - User ID for ownership
+    /// * `gid` - Group ID for ownership
+    pub fn from_preset_config(
+        config: &PresetConfig,
+        fs_spec: &str,
+        mount_point: &Path,
+        uid: u32,
+        gid: u32,
+    ) -> Self {
+        let entry = FstabEntry::new(
+            fs_spec,
+            mount_point,
+            config.filesystem.driver_name(),
+            config.generate_options(uid, gid),
+            0,
+            0,
+        );
+        Self::from_fstab_entry(&entry)
+    }
+
+    /// Converts an fstab entry into the equivalent mount unit.
+    ///
+    /// `x-systemd.*` options are translated into unit settings the same way
+    /// `systemd-fstab-generator` would.
+    pub fn from_fstab_entry(entry: &FstabEntry) -> Self {
+        let mut options = Vec::new();
+        let mut automount = false;
+        let mut idle_timeout_secs = None;
+        let mut device_timeout_secs = None;
+
+        for option in &entry.mount_options {
+            if option == "x-systemd.automount" {
+                automount = true;
+            } else if let Some(value) = option.strip_prefix("x-systemd.idle-timeout=") {
+                idle_timeout_secs = parse_seconds(value);
+            } else if let Some(value) = option.strip_prefix("x-systemd.device-timeout=") {
+                device_timeout_secs = parse_seconds(value);
+            } else if option == "noauto" || option == "nofail" || option.starts_with("x-systemd.") {
+                // Expressed through [Install] and unit dependencies instead
+            } else if !option.is_empty() {
+                options.push(option.clone());
+            }
+        }
+
+        Self {
+            what: fs_spec_to_device_path(&entry.fs_spec),
+            mount_point: entry.mount_point.clone(),
+            vfs_type: entry.vfs_type.clone(),
+            options,
+            automount,
+            idle_timeout_secs,
+            device_timeout_secs,
+        }
+    }
+
+    /// Converts this unit back into the equivalent fstab entry.
+    ///
+    /// This is how units are presented alongside fstab entries in [`crate::device`].
+    pub fn to_fstab_entry(&self) -> FstabEntry {
+        let mut options = self.options.clone();
+        options.push("nofail".to_string());
+        if self.automount {
+            options.push("noauto".to_string());
+            options.push("x-systemd.automount".to_string());
+            if let Some(timeout) = self.idle_timeout_secs {
+                options.push(format!("x-systemd.idle-timeout={}s", timeout));
+            }
+        }
+        if let Some(timeout) = self.device_timeout_secs {
+            options.push(format!("x-systemd.device-timeout={}s", timeout));
+        }
+
+        FstabEntry::new(
+            device_path_to_fs_spec(&self.what),
+            &self.mount_point,
+            &self.vfs_type,
+            options,
+            0,
+            0,
+        )
+    }
+
+    /// Returns the `.mount` unit name derived from the mount point.
+    pub fn mount_unit_name(&self) -> Result<String> {
+        syscall::mount_point_to_unit_name(&self.mount_point)
+    }
+
+    /// Returns the `.automount` unit name derived from the mount point.
+    pub fn automount_unit_name(&self) -> Result<String> {
+        syscall::mount_point_to_automount_unit_name(&self.mount_point)
+    }
+
+    /// Returns the unit that is enabled/started to activate this mount.
+    ///
+    /// This is the `.automount` unit for automounts and the `.mount` unit otherwise.
+    pub fn primary_unit_name(&self) -> Result<String> {
+        if self.automount {
+            self.automount_unit_name()
+        } else {
+            self.mount_unit_name()
+        }
+    }
+
+    /// Renders the `.mount` unit file content.
+    pub fn render_mount_unit(&self) -> String {
+        let mut out = String::new();
+        out.push_str(MANAGED_UNIT_MARKER);
+        out.push('\n');
+        out.push_str("[Unit]\n");
+        out.push_str(&format!(
+            "Description=SteamOS Mount: {}\n",
+            escape_specifiers(&self.mount_point.to_string_lossy())
+        ));
+        if let Some(timeout) = self.device_timeout_secs {
+            out.push_str(&format!("JobTimeoutSec={}s\n", timeout));
+        }
+        out.push('\n');
+        out.push_str("[Mount]\n");
+        out.push_str(&format!("What={}\n", escape_specifiers(&self.what)));
+        out.push_str(&format!(
+            "Where={}\n",
+            escape_specifiers(&self.mount_point.to_string_lossy())
+        ));
+        out.push_str(&format!("Type={}\n", self.vfs_type));
+        if !self.options.is_empty() {
+            out.push_str(&format!(
+                "Options={}\n",
+                escape_specifiers(&self.options.join(","))
+            ));
+        }
+
+        // Automounted mounts are pulled in by their .automount unit only
+        if !self.automount {
+            out.push('\n');
+            out.push_str("[Install]\n");
+            out.push_str(&format!("WantedBy={}\n", INSTALL_TARGET));
+        }
+
+        out
+    }
+
+    /// Renders the `.automount` unit file content, if this is an automount.
+    pub fn render_automount_unit(&self) -> Option<String> {
+        if !self.automount {
+            return None;
+        }
+
+        let mut out = String::new();
+        out.push_str(MANAGED_UNIT_MARKER);
+        out.push('\n');
+        out.push_str("[Unit]\n");
+        out.push_str(&format!(
+            "Description=SteamOS Automount: {}\n",
+            escape_specifiers(&self.mount_point.to_string_lossy())
+        ));
+        out.push('\n');
+        out.push_str("[Automount]\n");
+        out.push_str(&format!(
+            "Where={}\n",
+            escape_specifiers(&self.mount_point.to_string_lossy())
+        ));
+        if let Some(timeout) = self.idle_timeout_secs {
+            out.push_str(&format!("TimeoutIdleSec={}s\n", timeout));
+        }
+        out.push('\n');
+        out.push_str("[Install]\n");
+        out.push_str(&format!("WantedBy={}\n", INSTALL_TARGET));
+
+        Some(out)
+    }
+
+    /// Parses a managed `.mount` unit file.
+    ///
+    /// Returns None if the file is not managed by this tool.
+    pub fn parse_mount_unit(content: &str) -> Result<Option<Self>> {
+        if !content.lines().any(|l| l.trim() == MANAGED_UNIT_MARKER) {
+            return Ok(None);
+        }
+
+        let values = parse_unit_file(content);
+        let get = |section: &str, key: &str| {
+            values
+                .get(&(section.to_string(), key.to_string()))
+                .map(|v| unescape_specifiers(v))
+        };
+
+        let what = get("Mount", "What").ok_or_else(|| Error::MountUnitParse {
+            message: "missing What= in [Mount] section".to_string(),
+        })?;
+        let mount_point = get("Mount", "Where").ok_or_else(|| Error::MountUnitParse {
+            message: "missing Where= in [Mount] section".to_string(),
+        })?;
+        let vfs_type = get("Mount", "Type").unwrap_or_else(|| "auto".to_string());
+        let options = get("Mount", "Options")
+            .map(|o| o.split(',').map(|s| s.to_string()).collect())
+            .unwrap_or_default();
+        let device_timeout_secs = get("Unit", "JobTimeoutSec").and_then(|v| parse_seconds(&v));
+
+        Ok(Some(Self {
+            what,
+            mount_point: PathBuf::from(mount_point),
+            vfs_type,
+            options,
+            automount: false,
+            idle_timeout_secs: None,
+            device_timeout_secs,
+        }))
+    }
+}
+
+/// A managed mount unit installed on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledMountUnit {
+    /// The parsed unit definition.
+    pub unit: MountUnit,
+    /// Path of the `.mount` unit file.
+    pub mount_unit_path: PathBuf,
+    /// Path of the `.automount` unit file, if present.
+    pub automount_unit_path: Option<PathBuf>,
+    /// Whether the primary unit is enabled (linked into `local-fs.target.wants`).
+    pub enabled: bool,
+}
+
+/// Lists all managed mount units in a unit directory.
+///
+/// Units without the managed marker are ignored. A missing directory yields
+/// an empty list.
+pub fn list_managed_units(unit_dir: &Path) -> Result<Vec<InstalledMountUnit>> {
+    let entries = match fs::read_dir(unit_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).context(MountUnitReadSnafu { path: unit_dir });
+        }
+    };
+
+    let mut units = Vec::new();
+    for entry in entries {
+        let entry = entry.context(MountUnitReadSnafu { path: unit_dir })?;
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("mount") || !path.is_file() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path).context(MountUnitReadSnafu { path: &path })?;
+        let Some(mut unit) = MountUnit::parse_mount_unit(&content)? else {
+            continue;
+        };
+
+        // systemd refuses to load mount units whose name does not match Where=
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if syscall::unit_name_to_mount_point(name).ok().as_deref() != Some(&unit.mount_point) {
+            continue;
+        }
+
+        let automount_path = path.with_extension("automount");
+        let automount_unit_path = if automount_path.is_file() {
+            let content = fs::read_to_string(&automount_path).context(MountUnitReadSnafu {
+                path: &automount_path,
+            })?;
+            let values = parse_unit_file(&content);
+            unit.automount = true;
+            unit.idle_timeout_secs = values
+                .get(&("Automount".to_string(), "TimeoutIdleSec".to_string()))
+                .and_then(|v| parse_seconds(v));
+            Some(automount_path)
+        } else {
+            None
+        };
+
+        let enabled = unit_dir
+            .join(format!("{}.wants", INSTALL_TARGET))
+            .join(unit.primary_unit_name()?)
+            .exists();
+
+        units.push(InstalledMountUnit {
+            unit,
+            mount_unit_path: path,
+            automount_unit_path,
+            enabled,
+        });
+    }
+
+    units.sort_by(|a, b| a.unit.mount_point.cmp(&b.unit.mount_point));
+    Ok(units)
+}
+
+/// Writes the unit files for a mount and reloads systemd.
+///
+/// The units are not enabled; use [`enable_with_ctx`] to activate them.
+pub fn install_units_with_ctx(
+    unit: &MountUnit,
+    unit_dir: &Path,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let mount_unit_path = unit_dir.join(unit.mount_unit_name()?);
+    ctx.write_file_privileged(
+        &mount_unit_path.display().to_string(),
+        &unit.render_mount_unit(),
+    )?;
+
+    let automount_unit_path = unit_dir.join(unit.automount_unit_name()?);
+    match unit.render_automount_unit() {
+        Some(content) => {
+            ctx.write_file_privileged(&automount_unit_path.display().to_string(), &content)?;
+        }
+        None if automount_unit_path.exists() => {
+            ctx.run_privileged_checked("rm", &[&automount_unit_path.display().to_string()])?;
+        }
+        None => {}
+    }
+
+    syscall::daemon_reload_with_ctx(ctx)
+}
+
+/// Enables and starts a mount's primary unit.
+pub fn enable_with_ctx(unit: &MountUnit, ctx: &mut ExecutionContext) -> Result<()> {
+    let primary = unit.primary_unit_name()?;
+    syscall::enable_unit_with_ctx(&primary, ctx)?;
+    syscall::start_unit_with_ctx(&primary, ctx)?;
+    Ok(())
+}
+
+/// Disables and stops a mount's units.
+pub fn disable_with_ctx(unit: &MountUnit, ctx: &mut ExecutionContext) -> Result<()> {
+    syscall::disable_unit_with_ctx(&unit.primary_unit_name()?, ctx)?;
+    if unit.automount {
+        syscall::stop_unit_with_ctx(&unit.automount_unit_name()?, ctx)?;
+    }
+    syscall::stop_unit_with_ctx(&unit.mount_unit_name()?, ctx)?;
+    Ok(())
+}
+
+/// Disables, stops and deletes a mount's unit files, then reloads systemd.
+pub fn remove_units_with_ctx(
+    unit: &MountUnit,
+    unit_dir: &Path,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    disable_with_ctx(unit, ctx)?;
+
+    for name in [unit.mount_unit_name()?, unit.automount_unit_name()?] {
+        let path = unit_dir.join(name);
+        if path.exists() {
+            ctx.run_privileged_checked("rm", &[&path.display().to_string()])?;
+        }
+    }
+
+    syscall::daemon_reload_with_ctx(ctx)
+}
+
+/// Parses `[Section]` / `Key=Value` pairs of a unit file.
+///
+/// Later assignments of the same key override earlier ones.
+fn parse_unit_file(content: &str) -> HashMap<(String, String), String> {
+    let mut values = HashMap::new();
+    let mut section = String::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            values.insert(
+                (section.clone(), key.trim().to_string()),
+                value.trim().to_string(),
+            );
+        }
+    }
+
+    values
+}
+
+/// Converts an fstab fs_spec into a device node path usable in `What=`.
+fn fs_spec_to_device_path(fs_spec: &str) -> String {
+    if let Some(uuid) = fs_spec.strip_prefix("UUID=") {
+        format!("/dev/disk/by-uuid/{}", uuid)
+    } else if let Some(partuuid) = fs_spec.strip_prefix("PARTUUID=") {
+        format!("/dev/disk/by-partuuid/{}", partuuid)
+    } else if let Some(label) = fs_spec.strip_prefix("LABEL=") {
+        format!("/dev/disk/by-label/{}", escape_udev_label(label))
+    } else {
+        fs_spec.to_string()
+    }
+}
+
+/// Converts a `What=` device node path back into an fstab fs_spec.
+fn device_path_to_fs_spec(what: &str) -> String {
+    if let Some(uuid) = what.strip_prefix("/dev/disk/by-uuid/") {
+        format!("UUID={}", uuid)
+    } else if let Some(partuuid) = what.strip_prefix("/dev/disk/by-partuuid/") {
+        format!("PARTUUID={}", partuuid)
+    } else if let Some(label) = what.strip_prefix("/dev/disk/by-label/") {
+        format!("LABEL={}", unescape_udev_label(label))
+    } else {
+        what.to_string()
+    }
+}
+
+/// Escapes a label the way udev names `/dev/disk/by-label` links.
+fn escape_udev_label(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '/' => out.push_str("\\x2f"),
+            ' ' => out.push_str("\\x20"),
+            '\\' => out.push_str("\\x5c"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reverses [`escape_udev_label`].
+fn unescape_udev_label(label: &str) -> String {
+    label
+        .replace("\\x2f", "/")
+        .replace("\\x20", " ")
+        .replace("\\x5c", "\\")
+}
+
+/// Escapes `%` so systemd does not interpret it as a specifier.
+pub(crate) fn escape_specifiers(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Reverses [`escape_specifiers`].
+fn unescape_specifiers(value: &str) -> String {
+    value.replace("%%", "%")
+}
+
+/// Parses a systemd time span given in seconds (e.g., "60s" or "60").
+fn parse_seconds(value: &str) -> Option<u32> {
+    value.trim().trim_end_matches('s').parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::{DeviceType, SupportedFilesystem};
+
+    #[test]
+    fn test_from_preset_fixed() {
+        let config = PresetConfig::new(SupportedFilesystem::Ntfs);
+        let unit = MountUnit::from_preset_config(
+            &config,
+            "UUID=1234-5678",
+            Path::new("/home/deck/Drives/Games"),
+            1000,
+            1000,
+        );
+
+        assert_eq!(unit.what, "/dev/disk/by-uuid/1234-5678");
+        assert_eq!(unit.vfs_type, "ntfs3");
+        assert!(!unit.automount);
+        assert_eq!(unit.device_timeout_secs, Some(3));
+        assert!(unit.options.contains(&"prealloc".to_string()));
+        assert!(!unit.options.iter().any(|o| o.starts_with("x-systemd.")));
+        assert!(!unit.options.contains(&"nofail".to_string()));
+        assert_eq!(
+            unit.mount_unit_name().unwrap(),
+            "home-deck-Drives-Games.mount"
+        );
+        assert_eq!(
+            unit.primary_unit_name().unwrap(),
+            "home-deck-Drives-Games.mount"
+        );
+
+        let rendered = unit.render_mount_unit();
+        assert!(rendered.starts_with(MANAGED_UNIT_MARKER));
+        assert!(rendered.contains("What=/dev/disk/by-uuid/1234-5678\n"));
+        assert!(rendered.contains("Where=/home/deck/Drives/Games\n"));
+        assert!(rendered.contains("JobTimeoutSec=3s\n"));
+        assert!(rendered.contains("WantedBy=local-fs.target\n"));
+        assert!(unit.render_automount_unit().is_none());
+    }
+
+    #[test]
+    fn test_from_preset_removable() {
+        let mut config = PresetConfig::new(SupportedFilesystem::Exfat);
+        config.device_type = DeviceType::Removable;
+        let unit = MountUnit::from_preset_config(
+            &config,
+            "UUID=DEAD-BEEF",
+            Path::new("/home/deck/Drives/SD"),
+            1000,
+            1000,
+        );
+
+        assert!(unit.automount);
+        assert_eq!(unit.idle_timeout_secs, Some(60));
+        assert!(!unit.options.contains(&"noauto".to_string()));
+        assert_eq!(
+            unit.primary_unit_name().unwrap(),
+            "home-deck-Drives-SD.automount"
+        );
+
+        // The .mount unit is only pulled in by the automount
+        assert!(!unit.render_mount_unit().contains("[Install]"));
+        let automount = unit.render_automount_unit().unwrap();
+        assert!(automount.contains("[Automount]\nWhere=/home/deck/Drives/SD\n"));
+        assert!(automount.contains("TimeoutIdleSec=60s\n"));
+        assert!(automount.contains("WantedBy=local-fs.target\n"));
+    }
+
+    #[test]
+    fn test_fstab_entry_round_trip() {
+        let entry = FstabEntry::new(
+            "LABEL=My Games",
+            "/home/deck/Drives/My Games",
+            "ntfs3",
+            "uid=1000,gid=1000,rw,nofail,noauto,x-systemd.automount,x-systemd.idle-timeout=60s",
+            0,
+            0,
+        );
+        let unit = MountUnit::from_fstab_entry(&entry);
+        assert_eq!(unit.what, "/dev/disk/by-label/My\\x20Games");
+
+        let back = unit.to_fstab_entry();
+        assert_eq!(back.fs_spec, "LABEL=My Games");
+        assert_eq!(back.mount_point, entry.mount_point);
+        assert!(
+            back.mount_options
+                .contains(&"x-systemd.automount".to_string())
+        );
+        assert!(
+            back.mount_options
+                .contains(&"x-systemd.idle-timeout=60s".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_unmanaged_unit() {
+        let content = "[Mount]\nWhat=/dev/sda1\nWhere=/mnt/other\n";
+        assert!(MountUnit::parse_mount_unit(content).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_list_managed_units() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut config = PresetConfig::new(SupportedFilesystem::Exfat);
+        config.device_type = DeviceType::Removable;
+        let removable = MountUnit::from_preset_config(
+            &config,
+            "UUID=DEAD-BEEF",
+            Path::new("/home/deck/Drives/100% SD"),
+            1000,
+            1000,
+        );
+        let fixed = MountUnit::from_preset_config(
+            &PresetConfig::new(SupportedFilesystem::Ntfs),
+            "PARTUUID=1122-3344",
+            Path::new("/home/deck/Drives/Games"),
+            1000,
+            1000,
+        );
+
+        for unit in [&removable, &fixed] {
+            fs::write(
+                dir.path().join(unit.mount_unit_name().unwrap()),
+                unit.render_mount_unit(),
+            )
+            .unwrap();
+            if let Some(content) = unit.render_automount_unit() {
+                fs::write(
+                    dir.path().join(unit.automount_unit_name().unwrap()),
+                    content,
+                )
+                .unwrap();
+            }
+        }
+        // Unmanaged unit is ignored
+        fs::write(
+            dir.path().join("mnt-other.mount"),
+            "[Mount]\nWhat=/dev/sda1\nWhere=/mnt/other\n",
+        )
+        .unwrap();
+        // Enable the fixed mount
+        let wants = dir.path().join("local-fs.target.wants");
+        fs::create_dir(&wants).unwrap();
+        fs::write(wants.join(fixed.mount_unit_name().unwrap()), "").unwrap();
+
+        let units = list_managed_units(dir.path()).unwrap();
+        assert_eq!(units.len(), 2);
+
+        let installed_removable = units
+            .iter()
+            .find(|u| u.unit.mount_point == removable.mount_point)
+            .unwrap();
+        assert_eq!(installed_removable.unit, removable);
+        assert!(installed_removable.automount_unit_path.is_some());
+        assert!(!installed_removable.enabled);
+
+        let installed_fixed = units
+            .iter()
+            .find(|u| u.unit.mount_point == fixed.mount_point)
+            .unwrap();
+        assert_eq!(installed_fixed.unit, fixed);
+        assert!(installed_fixed.enabled);
+        assert_eq!(
+            installed_fixed.unit.to_fstab_entry().fs_spec,
+            "PARTUUID=1122-3344"
+        );
+    }
+
+    #[test]
+    fn test_list_managed_units_missing_dir() {
+        let units = list_managed_units(Path::new("/nonexistent/systemd/system")).unwrap();
+        assert!(units.is_empty());
+    }
+}
@@ -26,6 +26,51 @@ pub fn current_gid() -> u32 {
     nix::unistd::getgid().as_raw()
 }
 
+/// Returns the UID of the user who invoked the program.
+///
+/// A root process started through `sudo` or `pkexec` acts for the user in
+/// `SUDO_UID` or `PKEXEC_UID`; otherwise this is the current UID.
+pub fn invoking_uid() -> u32 {
+    invoking_uid_from(current_uid(), |name| std::env::var(name).ok())
+}
+
+fn invoking_uid_from(current: u32, var: impl Fn(&str) -> Option<String>) -> u32 {
+    if current != 0 {
+        return current;
+    }
+    ["SUDO_UID", "PKEXEC_UID"]
+        .iter()
+        .find_map(|name| var(name)?.parse().ok())
+        .unwrap_or(current)
+}
+
+/// Returns the primary GID of the [invoking user](invoking_uid).
+pub fn invoking_gid() -> u32 {
+    match invoking_user() {
+        Some(user) => user.gid.as_raw(),
+        None => current_gid(),
+    }
+}
+
+/// Returns the home directory of the [invoking user](invoking_uid).
+pub fn invoking_home_dir() -> Option<std::path::PathBuf> {
+    match invoking_user() {
+        Some(user) => Some(user.dir),
+        None => dirs::home_dir(),
+    }
+}
+
+/// Looks up the invoking user if it is not the current one.
+fn invoking_user() -> Option<nix::unistd::User> {
+    let uid = invoking_uid();
+    if uid == current_uid() {
+        return None;
+    }
+    nix::unistd::User::from_uid(nix::unistd::Uid::from_raw(uid))
+        .ok()
+        .flatten()
+}
+
 /// Default options applied to all mounts.
 pub const BASE_OPTIONS: &str = "umask=000,nofail,rw,noatime";
 
@@ -345,6 +390,16 @@ pub fn suggest_preset_config(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_invoking_uid() {
+        let env = |name: &str| (name == "PKEXEC_UID").then(|| "1000".to_string());
+        assert_eq!(invoking_uid_from(0, env), 1000);
+        // Only root acts for another user
+        assert_eq!(invoking_uid_from(1001, env), 1001);
+        assert_eq!(invoking_uid_from(0, |_| None), 0);
+        assert_eq!(invoking_uid_from(0, |_| Some("deck".to_string())), 0);
+    }
+
     #[test]
     fn test_ssd_preset_ntfs() {
         let preset = PresetConfig::new(SupportedFilesystem::Ntfs); // Default is Flash/Fixed
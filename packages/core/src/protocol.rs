@@ -86,6 +86,18 @@ pub enum DaemonCommand {
         /// Arguments to pass.
         args: Vec<String>,
     },
+    /// Execute a command, writing `input` to its stdin.
+    ///
+    /// Used for secrets (e.g. passphrases) that must never appear on argv,
+    /// where other users could read them from `/proc`.
+    ExecWithInput {
+        /// Program to execute.
+        program: String,
+        /// Arguments to pass.
+        args: Vec<String>,
+        /// Data written to the program's stdin.
+        input: String,
+    },
     /// Write content to a file.
     WriteFile {
         /// File path.
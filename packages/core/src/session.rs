@@ -0,0 +1,449 @@
+//! Session detection and Game Mode / Desktop Mode round trips.
+//!
+//! [`crate::syscall::session_select`] switches sessions but does not know
+//! which session is currently running. This module detects the current
+//! session (gamescope vs plasma) and schedules a return to Game Mode, so
+//! that "switch to Desktop, add the library, switch back" can be automated.
+//!
+//! All functions act on the calling user's session and user systemd manager.
+//! Callers running as root (e.g. the Decky backend) must run them as the
+//! session user.
+
+use std::process::{Command, Output};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, IoResultExt, Result};
+use crate::steam;
+use crate::syscall;
+
+/// Name of the transient user unit that returns to Game Mode.
+pub const RETURN_TO_GAME_MODE_UNIT: &str = "steamos-mount-return-to-game-mode";
+
+/// Name of the transient user unit that opens Steam's storage settings.
+pub const OPEN_STORAGE_SETTINGS_UNIT: &str = "steamos-mount-open-storage-settings";
+
+/// Delay after switching to Desktop Mode before opening Steam's storage settings.
+///
+/// Leaves time for the Plasma session and the desktop Steam client to start.
+pub const OPEN_STORAGE_SETTINGS_DELAY: Duration = Duration::from_secs(20);
+
+/// Kind of graphical session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionKind {
+    /// Game Mode (gamescope session).
+    GameMode,
+    /// Desktop Mode (Plasma session).
+    Desktop,
+    /// No graphical session, or an unrecognised one.
+    Unknown,
+}
+
+impl SessionKind {
+    /// Classifies a desktop name (`XDG_CURRENT_DESKTOP` or logind's `Desktop`).
+    pub fn from_desktop(desktop: &str) -> Self {
+        let desktop = desktop.to_ascii_lowercase();
+        if desktop.contains("gamescope") {
+            Self::GameMode
+        } else if desktop
+            .split(':')
+            .any(|d| d == "kde" || d.starts_with("plasma"))
+        {
+            Self::Desktop
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Returns the `steamos-session-select` argument for this session.
+    pub fn session_select_name(&self) -> Option<&'static str> {
+        match self {
+            Self::GameMode => Some("gamescope"),
+            Self::Desktop => Some("plasma"),
+            Self::Unknown => None,
+        }
+    }
+}
+
+/// Information about the current graphical session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    /// Detected session kind.
+    pub kind: SessionKind,
+    /// logind session ID, if known.
+    pub session_id: Option<String>,
+    /// Session type (e.g., "wayland", "x11").
+    pub session_type: Option<String>,
+    /// Desktop name the kind was derived from (e.g., "gamescope", "KDE").
+    pub desktop: Option<String>,
+}
+
+impl SessionInfo {
+    /// Detects the session from environment variables.
+    ///
+    /// Returns None if the environment does not identify a graphical session.
+    pub fn from_environment(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let session_id = var("XDG_SESSION_ID").filter(|v| !v.is_empty());
+        let session_type = var("XDG_SESSION_TYPE").filter(|v| !v.is_empty());
+
+        // gamescope exports its own display variables to clients
+        if var("GAMESCOPE_WAYLAND_DISPLAY").is_some_and(|v| !v.is_empty()) {
+            return Some(Self {
+                kind: SessionKind::GameMode,
+                session_id,
+                session_type,
+                desktop: Some("gamescope".to_string()),
+            });
+        }
+
+        let desktop = var("XDG_CURRENT_DESKTOP")
+            .or_else(|| var("XDG_SESSION_DESKTOP"))
+            .filter(|v| !v.is_empty())?;
+        let kind = SessionKind::from_desktop(&desktop);
+        if kind == SessionKind::Unknown {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            session_id,
+            session_type,
+            desktop: Some(desktop),
+        })
+    }
+
+    /// Parses the output of `loginctl show-session --property=Id,Type,Desktop`.
+    pub fn from_show_session_output(output: &str) -> Self {
+        let mut info = Self {
+            kind: SessionKind::Unknown,
+            session_id: None,
+            session_type: None,
+            desktop: None,
+        };
+
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match key.trim() {
+                "Id" => info.session_id = Some(value.to_string()),
+                "Type" => info.session_type = Some(value.to_string()),
+                "Desktop" => {
+                    info.kind = SessionKind::from_desktop(value);
+                    info.desktop = Some(value.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        info
+    }
+}
+
+/// Detects the current graphical session of the calling user.
+///
+/// The environment is checked first; if it does not identify a session (for
+/// example when running from a service), logind is asked for the user's
+/// graphical session via `loginctl`.
+pub fn detect_session() -> Result<SessionInfo> {
+    if let Some(info) = SessionInfo::from_environment(|name| std::env::var(name).ok()) {
+        return Ok(info);
+    }
+
+    let uid = crate::preset::current_uid().to_string();
+    let output = run_command(
+        "loginctl",
+        &["show-user", &uid, "--property=Display", "--value"],
+    )?;
+    let session_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if session_id.is_empty() {
+        return Ok(SessionInfo {
+            kind: SessionKind::Unknown,
+            session_id: None,
+            session_type: None,
+            desktop: None,
+        });
+    }
+
+    let output = run_command(
+        "loginctl",
+        &["show-session", &session_id, "--property=Id,Type,Desktop"],
+    )?;
+    Ok(SessionInfo::from_show_session_output(
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// Schedules a switch back to Game Mode after the given delay.
+///
+/// Uses a transient timer in the user's systemd manager, replacing any
+/// previously scheduled return. The timer only survives the switch to Desktop
+/// Mode if the user manager keeps running (as with SteamOS autologin).
+pub fn schedule_return_to_game_mode(after: Duration) -> Result<()> {
+    schedule_return_with(after, &mut run_command)
+}
+
+/// Schedules the return to Game Mode, running commands through `run`.
+///
+/// Only an earlier return timer is replaced; a pending opening of the
+/// storage settings is kept.
+fn schedule_return_with(
+    after: Duration,
+    run: &mut impl FnMut(&str, &[&str]) -> Result<Output>,
+) -> Result<()> {
+    // Replace an existing schedule instead of failing on the unit name
+    clear_transient_unit_with(RETURN_TO_GAME_MODE_UNIT, run);
+
+    run(
+        "systemd-run",
+        &[
+            "--user",
+            &format!("--unit={}", RETURN_TO_GAME_MODE_UNIT),
+            &format!("--on-active={}s", after.as_secs().max(1)),
+            "--timer-property=AccuracySec=1s",
+            "--collect",
+            "steamos-session-select",
+            "gamescope",
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Cancels a scheduled return to Game Mode, and a pending opening of
+/// Steam's storage settings.
+///
+/// Returns true if a return was scheduled.
+pub fn cancel_return_to_game_mode() -> Result<bool> {
+    clear_transient_unit(OPEN_STORAGE_SETTINGS_UNIT);
+
+    let was_scheduled = is_return_to_game_mode_scheduled()?;
+    if was_scheduled {
+        run_command(
+            "systemctl",
+            &[
+                "--user",
+                "stop",
+                &format!("{}.timer", RETURN_TO_GAME_MODE_UNIT),
+            ],
+        )?;
+    }
+    Ok(was_scheduled)
+}
+
+/// Returns true if a return to Game Mode is currently scheduled.
+pub fn is_return_to_game_mode_scheduled() -> Result<bool> {
+    let output = Command::new("systemctl")
+        .args([
+            "--user",
+            "is-active",
+            "--quiet",
+            &format!("{}.timer", RETURN_TO_GAME_MODE_UNIT),
+        ])
+        .output()
+        .command_context("systemctl --user is-active")?;
+    Ok(output.status.success())
+}
+
+/// Switches to Desktop Mode and schedules the return to Game Mode.
+///
+/// This automates the "switch to Desktop, add the library in Steam, switch
+/// back" flow. If `open_storage_settings` is set, Steam's storage settings are
+/// opened once the desktop session had time to start.
+///
+/// If Desktop Mode is already running, only the timers are scheduled.
+pub fn switch_to_desktop_and_return(
+    return_after: Duration,
+    open_storage_settings: bool,
+) -> Result<()> {
+    if return_after <= OPEN_STORAGE_SETTINGS_DELAY && open_storage_settings {
+        return Err(Error::Session {
+            message: format!(
+                "return delay must be longer than {}s to open storage settings",
+                OPEN_STORAGE_SETTINGS_DELAY.as_secs()
+            ),
+        });
+    }
+
+    arm_timers_with(return_after, open_storage_settings, &mut run_command)?;
+
+    if detect_session()?.kind == SessionKind::Desktop {
+        return Ok(());
+    }
+
+    syscall::switch_to_desktop()
+}
+
+/// Starts the timers of [`switch_to_desktop_and_return`], running commands
+/// through `run`.
+fn arm_timers_with(
+    return_after: Duration,
+    open_storage_settings: bool,
+    run: &mut impl FnMut(&str, &[&str]) -> Result<Output>,
+) -> Result<()> {
+    if open_storage_settings {
+        // A unit left over from an earlier call would make systemd-run fail
+        clear_transient_unit_with(OPEN_STORAGE_SETTINGS_UNIT, run);
+        run(
+            "systemd-run",
+            &[
+                "--user",
+                &format!("--unit={}", OPEN_STORAGE_SETTINGS_UNIT),
+                &format!("--on-active={}s", OPEN_STORAGE_SETTINGS_DELAY.as_secs()),
+                "--collect",
+                "steam",
+                steam::client::STORAGE_SETTINGS_URL,
+            ],
+        )?;
+    }
+
+    // Armed last, so a failure above leaves no return pending
+    if let Err(e) = schedule_return_with(return_after, run) {
+        clear_transient_unit_with(OPEN_STORAGE_SETTINGS_UNIT, run);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Stops a transient user unit (timer and service) and forgets its failed
+/// state, so its name can be reused. Missing units are ignored.
+fn clear_transient_unit(unit: &str) {
+    clear_transient_unit_with(unit, &mut run_command);
+}
+
+/// Clears a transient user unit, running commands through `run`.
+fn clear_transient_unit_with(unit: &str, run: &mut impl FnMut(&str, &[&str]) -> Result<Output>) {
+    let timer = format!("{}.timer", unit);
+    let service = format!("{}.service", unit);
+    for action in ["stop", "reset-failed"] {
+        let _ = run("systemctl", &["--user", action, &timer, &service]);
+    }
+}
+
+/// Runs a command as the current user, failing on non-zero exit.
+fn run_command(program: &str, args: &[&str]) -> Result<Output> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .command_context(format!("{} {}", program, args.join(" ")))?;
+
+    if !output.status.success() {
+        return Err(Error::Session {
+            message: format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_session_kind_from_desktop() {
+        assert_eq!(
+            SessionKind::from_desktop("gamescope"),
+            SessionKind::GameMode
+        );
+        assert_eq!(SessionKind::from_desktop("KDE"), SessionKind::Desktop);
+        assert_eq!(SessionKind::from_desktop("plasma"), SessionKind::Desktop);
+        assert_eq!(SessionKind::from_desktop("GNOME"), SessionKind::Unknown);
+        assert_eq!(SessionKind::from_desktop(""), SessionKind::Unknown);
+    }
+
+    #[test]
+    fn test_session_from_environment() {
+        let info = SessionInfo::from_environment(env(&[
+            ("XDG_CURRENT_DESKTOP", "KDE"),
+            ("XDG_SESSION_TYPE", "wayland"),
+            ("XDG_SESSION_ID", "2"),
+        ]))
+        .unwrap();
+        assert_eq!(info.kind, SessionKind::Desktop);
+        assert_eq!(info.session_type.as_deref(), Some("wayland"));
+        assert_eq!(info.session_id.as_deref(), Some("2"));
+
+        let info = SessionInfo::from_environment(env(&[
+            ("GAMESCOPE_WAYLAND_DISPLAY", "gamescope-0"),
+            ("XDG_CURRENT_DESKTOP", "KDE"),
+        ]))
+        .unwrap();
+        assert_eq!(info.kind, SessionKind::GameMode);
+
+        assert!(SessionInfo::from_environment(env(&[])).is_none());
+        assert!(SessionInfo::from_environment(env(&[("XDG_CURRENT_DESKTOP", "GNOME")])).is_none());
+    }
+
+    #[test]
+    fn test_session_from_show_session_output() {
+        let info = SessionInfo::from_show_session_output("Id=3\nType=x11\nDesktop=gamescope\n");
+        assert_eq!(info.kind, SessionKind::GameMode);
+        assert_eq!(info.session_id.as_deref(), Some("3"));
+        assert_eq!(info.session_type.as_deref(), Some("x11"));
+        assert_eq!(info.kind.session_select_name(), Some("gamescope"));
+
+        let info = SessionInfo::from_show_session_output("Id=c1\nType=tty\nDesktop=\n");
+        assert_eq!(info.kind, SessionKind::Unknown);
+        assert_eq!(info.desktop, None);
+    }
+
+    #[test]
+    fn test_schedule_keeps_storage_settings_timer() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut calls: Vec<Vec<String>> = Vec::new();
+        let mut run = |program: &str, args: &[&str]| {
+            calls.push(
+                std::iter::once(program)
+                    .chain(args.iter().copied())
+                    .map(str::to_string)
+                    .collect(),
+            );
+            Ok(Output {
+                status: std::process::ExitStatus::from_raw(0),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            })
+        };
+        arm_timers_with(Duration::from_secs(600), true, &mut run).unwrap();
+
+        let storage_unit = format!("--unit={}", OPEN_STORAGE_SETTINGS_UNIT);
+        let started = calls
+            .iter()
+            .position(|call| call[0] == "systemd-run" && call.contains(&storage_unit))
+            .expect("storage settings timer not started");
+        let storage_timer = format!("{}.timer", OPEN_STORAGE_SETTINGS_UNIT);
+        assert!(
+            !calls[started..]
+                .iter()
+                .any(|call| call[0] == "systemctl" && call.contains(&storage_timer))
+        );
+        let return_unit = format!("--unit={}", RETURN_TO_GAME_MODE_UNIT);
+        assert!(
+            calls[started..]
+                .iter()
+                .any(|call| call.contains(&return_unit))
+        );
+    }
+}
@@ -1,18 +1,42 @@
 //! Steam VDF parsing and library folder injection.
 //!
-//! This module handles parsing Steam's `libraryfolders.vdf` configuration file
-//! and injecting new library folder entries.
+//! This module discovers Steam installations (native and Flatpak), parses
+//! their `libraryfolders.vdf` configuration file and injects new library
+//! folder entries.
 //!
-//! Uses the `keyvalues-serde` crate for robust VDF parsing with serde support.
+//! VDF files are handled through [`KvBlock`], a small KeyValues document
+//! model that keeps key order and unknown keys (e.g. `contentstatsid`), so
+//! edits only touch what they change. Modified files are replaced atomically
+//! after a backup of the original is written.
+//!
+//! Proton prefix redirection lives in [`compatdata`], detection of running
+//! Steam clients in [`process`] and restarting them after an edit in
+//! [`restart`]. [`client`] adds libraries through the running client
+//! instead, without editing any file.
 
 use std::collections::HashMap;
 use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use nix::unistd::{AccessFlags, access};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, IoResultExt, Result};
+use crate::executor::ExecutionContext;
+use crate::preset;
+use crate::usage;
+
+pub mod client;
+pub mod compatdata;
+pub mod process;
+pub mod restart;
+
+pub use process::{SteamProcess, SteamSession, SteamShutdown};
+pub use restart::{RestartStrategy, edit_while_stopped_with_ctx, ensure_stopped};
 
 /// Represents a Steam library folder entry.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -33,84 +57,652 @@ pub struct LibraryFolder {
     pub apps: HashMap<String, String>,
 }
 
-/// Root structure for libraryfolders.vdf.
-#[derive(Debug, Deserialize)]
-struct LibraryFoldersRoot {
-    #[serde(flatten)]
-    folders: HashMap<String, LibraryFolder>,
+/// Top-level key of libraryfolders.vdf.
+const LIBRARY_FOLDERS_KEY: &str = "libraryfolders";
+
+/// Marker file Steam keeps in the root of every library folder.
+const LIBRARY_MARKER_FILE: &str = "libraryfolder.vdf";
+
+/// Top-level key of the library marker file.
+const LIBRARY_MARKER_KEY: &str = "libraryfolder";
+
+/// Directory created for new libraries on a drive.
+pub const DEFAULT_LIBRARY_DIR: &str = "SteamLibrary";
+
+/// `StateFlags` bit set once an app is fully installed.
+const APP_STATE_FULLY_INSTALLED: u32 = 4;
+
+/// Buffer size used when copying app files across filesystems.
+const COPY_BUFFER_SIZE: usize = 1 << 20;
+
+/// Path of the Steam client settings block inside config.vdf.
+const CONFIG_STEAM_BLOCK: [&str; 4] = ["InstallConfigStore", "Software", "Valve", "Steam"];
+
+/// Key prefix of the library folders listed in config.vdf.
+const BASE_INSTALL_FOLDER_PREFIX: &str = "BaseInstallFolder_";
+
+/// Suffix of the backup written next to a VDF file before it is replaced.
+const VDF_BACKUP_SUFFIX: &str = "backup.steamos-mount";
+
+impl LibraryFolder {
+    /// Reads a library folder entry from its VDF block.
+    ///
+    /// Returns None if the block has no path.
+    fn from_block(block: &KvBlock) -> Option<Self> {
+        let string = |key| block.get_str(key).unwrap_or_default().to_string();
+        Some(Self {
+            path: PathBuf::from(block.get_str("path")?),
+            label: string("label"),
+            contentid: string("contentid"),
+            totalsize: string("totalsize"),
+            apps: block
+                .get_block("apps")
+                .map(|apps| {
+                    apps.iter()
+                        .filter_map(|(id, size)| Some((id.to_string(), size.as_str()?.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
 }
 
-/// Returns the path to Steam's libraryfolders.vdf file.
-pub fn steam_library_vdf_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().ok_or(Error::HomeDirNotFound)?;
-    let vdf_path = home
-        .join(".local")
-        .join("share")
-        .join("Steam")
-        .join("steamapps")
-        .join("libraryfolders.vdf");
-
-    if !vdf_path.exists() {
+/// A KeyValues value: a string or a nested block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvValue {
+    /// A quoted string value.
+    String(String),
+    /// A `{ ... }` block of entries.
+    Block(KvBlock),
+}
+
+impl KvValue {
+    /// Returns the value as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            Self::Block(_) => None,
+        }
+    }
+
+    /// Returns the value as a block, if it is one.
+    pub fn as_block(&self) -> Option<&KvBlock> {
+        match self {
+            Self::String(_) => None,
+            Self::Block(block) => Some(block),
+        }
+    }
+
+    /// Returns the value as a mutable block, if it is one.
+    pub fn as_block_mut(&mut self) -> Option<&mut KvBlock> {
+        match self {
+            Self::String(_) => None,
+            Self::Block(block) => Some(block),
+        }
+    }
+}
+
+/// An ordered block of KeyValues entries.
+///
+/// A whole VDF file is a block too. Entries keep their order and duplicate
+/// keys, so parsing and emitting a file round-trips everything except
+/// comments and whitespace. Key lookups are case-insensitive, like Steam's.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KvBlock {
+    entries: Vec<(String, KvValue)>,
+}
+
+impl KvBlock {
+    /// Creates an empty block.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a KeyValues document.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut tokens = KvTokenizer { rest: content };
+        let block = Self::parse_entries(&mut tokens, false)?;
+        Ok(block)
+    }
+
+    /// Parses entries up to the closing brace (nested) or the end of input.
+    fn parse_entries(tokens: &mut KvTokenizer<'_>, nested: bool) -> Result<Self> {
+        let mut block = Self::new();
+        loop {
+            let key = match tokens.next_token()? {
+                Some(KvToken::String(key)) => key,
+                Some(KvToken::Close) if nested => return Ok(block),
+                None if !nested => return Ok(block),
+                Some(KvToken::Close) => return Err(vdf_parse_error("unexpected '}'")),
+                Some(KvToken::Open) => return Err(vdf_parse_error("expected a key, found '{'")),
+                None => return Err(vdf_parse_error("unexpected end of file, missing '}'")),
+            };
+            let value = match tokens.next_token()? {
+                Some(KvToken::String(value)) => KvValue::String(value),
+                Some(KvToken::Open) => KvValue::Block(Self::parse_entries(tokens, true)?),
+                _ => {
+                    return Err(vdf_parse_error(&format!(
+                        "missing value for key \"{}\"",
+                        key
+                    )));
+                }
+            };
+            block.entries.push((key, value));
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the block has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the entries in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, &KvValue)> + ExactSizeIterator {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Returns the first value with the given key.
+    pub fn get(&self, key: &str) -> Option<&KvValue> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the first value with the given key, mutably.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut KvValue> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the first string value with the given key.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key)?.as_str()
+    }
+
+    /// Returns the first block with the given key.
+    pub fn get_block(&self, key: &str) -> Option<&KvBlock> {
+        self.get(key)?.as_block()
+    }
+
+    /// Returns the first block with the given key, mutably.
+    pub fn get_block_mut(&mut self, key: &str) -> Option<&mut KvBlock> {
+        self.get_mut(key)?.as_block_mut()
+    }
+
+    /// Sets the first entry with the given key, or appends a new entry.
+    pub fn set(&mut self, key: &str, value: KvValue) {
+        match self.get_mut(key) {
+            Some(existing) => *existing = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    /// Sets a string value; see [`KvBlock::set`].
+    pub fn set_str(&mut self, key: &str, value: impl Into<String>) {
+        self.set(key, KvValue::String(value.into()));
+    }
+
+    /// Inserts an entry at the given position, keeping existing entries.
+    pub fn insert(&mut self, index: usize, key: impl Into<String>, value: KvValue) {
+        self.entries.insert(index, (key.into(), value));
+    }
+
+    /// Returns the block with the given key, inserting an empty one if there
+    /// is none (a string value with that key is replaced).
+    pub fn get_or_insert_block(&mut self, key: &str) -> &mut KvBlock {
+        if self.get_block(key).is_none() {
+            self.set(key, KvValue::Block(KvBlock::new()));
+        }
+        self.get_block_mut(key).expect("block was just inserted")
+    }
+
+    /// Keeps only the entries for which the predicate returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &KvValue) -> bool) {
+        self.entries.retain(|(key, value)| f(key, value));
+    }
+
+    /// Removes the first entry with the given key and returns its value.
+    pub fn remove(&mut self, key: &str) -> Option<KvValue> {
+        let index = self
+            .entries
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Emits the block in Steam's own layout (tab indentation, two tabs
+    /// between key and value).
+    pub fn to_vdf_string(&self) -> String {
+        let mut output = String::new();
+        self.write_entries(&mut output, 0);
+        output
+    }
+
+    fn write_entries(&self, output: &mut String, depth: usize) {
+        let indent = "\t".repeat(depth);
+        for (key, value) in &self.entries {
+            match value {
+                KvValue::String(value) => output.push_str(&format!(
+                    "{}\"{}\"\t\t\"{}\"\n",
+                    indent,
+                    escape_kv(key),
+                    escape_kv(value)
+                )),
+                KvValue::Block(block) => {
+                    output.push_str(&format!("{}\"{}\"\n{}{{\n", indent, escape_kv(key), indent));
+                    block.write_entries(output, depth + 1);
+                    output.push_str(&format!("{}}}\n", indent));
+                }
+            }
+        }
+    }
+}
+
+/// A KeyValues token.
+enum KvToken {
+    String(String),
+    Open,
+    Close,
+}
+
+/// Splits KeyValues text into tokens, skipping whitespace and `//` comments.
+struct KvTokenizer<'a> {
+    rest: &'a str,
+}
+
+impl KvTokenizer<'_> {
+    fn next_token(&mut self) -> Result<Option<KvToken>> {
+        loop {
+            self.rest = self.rest.trim_start();
+            match self.rest.strip_prefix("//") {
+                Some(comment) => self.rest = comment.find('\n').map_or("", |i| &comment[i..]),
+                None => break,
+            }
+        }
+
+        if let Some(rest) = self.rest.strip_prefix('{') {
+            self.rest = rest;
+            return Ok(Some(KvToken::Open));
+        }
+        if let Some(rest) = self.rest.strip_prefix('}') {
+            self.rest = rest;
+            return Ok(Some(KvToken::Close));
+        }
+        if let Some(quoted) = self.rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.rest = &quoted[i + 1..];
+                        return Ok(Some(KvToken::String(value)));
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, escaped @ ('\\' | '"'))) => value.push(escaped),
+                        // Steam keeps the backslash of unknown escapes
+                        // (e.g. Windows paths)
+                        Some((_, other)) => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                        None => break,
+                    },
+                    c => value.push(c),
+                }
+            }
+            return Err(vdf_parse_error("unterminated string"));
+        }
+        if self.rest.is_empty() {
+            return Ok(None);
+        }
+
+        // Unquoted token
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | '"'))
+            .unwrap_or(self.rest.len());
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Ok(Some(KvToken::String(token.to_string())))
+    }
+}
+
+/// Escapes a KeyValues string for emitting.
+fn escape_kv(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn vdf_parse_error(message: &str) -> Error {
+    Error::VdfParse {
+        message: format!("Failed to parse VDF: {}", message),
+    }
+}
+
+/// Reads and parses a VDF file.
+pub fn load_vdf(path: &Path) -> Result<KvBlock> {
+    let content = fs::read_to_string(path).vdf_write_context(path)?;
+    KvBlock::parse(&content)
+}
+
+/// Replaces a VDF file atomically, keeping a backup of the original.
+///
+/// The document is written to a temporary file next to the original and
+/// renamed over it, so Steam never reads a partially written file. The
+/// original is copied to `<path>.backup.steamos-mount` first.
+///
+/// Nothing is written while a client of the Steam installation the file
+/// belongs to is running (see [`ensure_stopped`]).
+pub fn save_vdf(path: &Path, document: &KvBlock) -> Result<()> {
+    if let Some(install) = SteamInstall::from_vdf_path(path) {
+        ensure_stopped(&install.processes())?;
+    }
+
+    let backup_path = PathBuf::from(format!("{}.{}", path.display(), VDF_BACKUP_SUFFIX));
+    let permissions = fs::metadata(path).ok().map(|m| m.permissions());
+    if permissions.is_some() {
+        fs::copy(path, &backup_path).vdf_write_context(&backup_path)?;
+    }
+
+    let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+    let write_temp = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(document.to_vdf_string().as_bytes())?;
+        file.sync_all()?;
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&temp_path, permissions)?;
+        }
+        Ok(())
+    };
+    if let Err(e) = write_temp() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e).vdf_write_context(&temp_path);
+    }
+
+    fs::rename(&temp_path, path).vdf_write_context(path)
+}
+
+/// Returns the `libraryfolders` block of a parsed libraryfolders.vdf.
+fn library_folders_block(document: &mut KvBlock) -> Result<&mut KvBlock> {
+    document
+        .get_block_mut(LIBRARY_FOLDERS_KEY)
+        .ok_or_else(|| vdf_parse_error("missing \"libraryfolders\" block"))
+}
+
+/// Returns true for the numeric keys of library folder entries.
+fn is_library_id(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Flatpak application ID of Steam.
+pub const FLATPAK_APP_ID: &str = "com.valvesoftware.Steam";
+
+/// How a Steam installation is packaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SteamFlavour {
+    /// Distribution or SteamOS package, launched with `steam`.
+    Native,
+    /// Flathub package, launched with `flatpak run com.valvesoftware.Steam`.
+    Flatpak,
+}
+
+/// A Steam installation found on the system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SteamInstall {
+    /// Steam root directory, with symlinks resolved.
+    pub root: PathBuf,
+    /// Path to the installation's libraryfolders.vdf.
+    pub vdf_path: PathBuf,
+    /// How the installation is packaged.
+    pub flavour: SteamFlavour,
+    /// Whether the installation's client was running when it was discovered.
+    pub is_running: bool,
+}
+
+impl SteamInstall {
+    /// Creates an install from a Steam root directory.
+    ///
+    /// Returns None if the directory has no libraryfolders.vdf.
+    pub fn from_root(root: &Path) -> Option<Self> {
+        let root = fs::canonicalize(root).ok()?;
+        let vdf_path = root.join("steamapps").join("libraryfolders.vdf");
+        if !vdf_path.is_file() {
+            return None;
+        }
+
+        let flavour = if root.components().any(|c| c.as_os_str() == FLATPAK_APP_ID) {
+            SteamFlavour::Flatpak
+        } else {
+            SteamFlavour::Native
+        };
+        let is_running = is_install_running(&root, flavour);
+
+        Some(Self {
+            root,
+            vdf_path,
+            flavour,
+            is_running,
+        })
+    }
+
+    /// Returns the install a libraryfolders.vdf belongs to.
+    pub fn from_vdf_path(vdf_path: &Path) -> Option<Self> {
+        Self::from_root(vdf_path.parent()?.parent()?)
+    }
+
+    /// Returns a command that launches this installation's client.
+    ///
+    /// Arguments (e.g. `steam://` URLs) are forwarded to the running client.
+    /// Run as root through `sudo` or `pkexec`, the command runs as the
+    /// invoking user, who owns the client.
+    pub fn command(&self) -> Command {
+        let mut command = match self.flavour {
+            SteamFlavour::Native => Command::new("steam"),
+            SteamFlavour::Flatpak => {
+                let mut command = Command::new("flatpak");
+                command.args(["run", FLATPAK_APP_ID]);
+                command
+            }
+        };
+
+        let uid = preset::invoking_uid();
+        if uid != preset::current_uid() {
+            use std::os::unix::process::CommandExt;
+
+            command
+                .uid(uid)
+                .gid(preset::invoking_gid())
+                .env("XDG_RUNTIME_DIR", format!("/run/user/{}", uid));
+            if let Some(home) = preset::invoking_home_dir() {
+                command.env("HOME", home);
+            }
+        }
+        command
+    }
+
+    /// Checks whether this installation's client is running now.
+    pub fn check_running(&self) -> bool {
+        is_install_running(&self.root, self.flavour)
+    }
+
+    /// Returns the running client processes of this installation.
+    pub fn processes(&self) -> Vec<SteamProcess> {
+        process::user_steam_processes()
+            .into_iter()
+            .filter(|p| p.belongs_to(&self.root, self.flavour))
+            .collect()
+    }
+
+    /// Returns the path to this installation's config.vdf.
+    pub fn config_vdf_path(&self) -> PathBuf {
+        self.root.join("config").join("config.vdf")
+    }
+}
+
+/// Returns the Steam root directories to probe below a home directory.
+///
+/// `~/.steam/steam` comes first: it points at the root the client last ran
+/// from, which may be anywhere.
+fn steam_root_candidates(home: &Path) -> Vec<PathBuf> {
+    let flatpak_home = home.join(".var").join("app").join(FLATPAK_APP_ID);
+    vec![
+        home.join(".steam").join("steam"),
+        home.join(".steam").join("root"),
+        home.join(".local").join("share").join("Steam"),
+        flatpak_home.join(".local").join("share").join("Steam"),
+        flatpak_home.join(".steam").join("steam"),
+        flatpak_home.join("data").join("Steam"),
+    ]
+}
+
+/// Discovers the Steam installations below a home directory.
+///
+/// Symlinked roots are resolved and reported once.
+pub fn discover_steam_installs_in(home: &Path) -> Vec<SteamInstall> {
+    let mut installs: Vec<SteamInstall> = Vec::new();
+    for candidate in steam_root_candidates(home) {
+        if let Some(install) = SteamInstall::from_root(&candidate)
+            && !installs.iter().any(|i| i.root == install.root)
+        {
+            installs.push(install);
+        }
+    }
+    installs
+}
+
+/// Discovers the Steam installations of the invoking user.
+///
+/// Run through `sudo` or `pkexec`, these are below the home directory of the
+/// user who ran it, not root's.
+pub fn discover_steam_installs() -> Result<Vec<SteamInstall>> {
+    let home = preset::invoking_home_dir().ok_or(Error::HomeDirNotFound)?;
+    Ok(discover_steam_installs_in(&home))
+}
+
+/// Returns the Steam installation to use when none was chosen.
+///
+/// A running installation of the invoking user is preferred, then the
+/// discovery order.
+pub fn default_steam_install() -> Result<SteamInstall> {
+    let home = preset::invoking_home_dir().ok_or(Error::HomeDirNotFound)?;
+    let mut installs = discover_steam_installs_in(&home);
+    let index = installs.iter().position(|i| i.is_running).unwrap_or(0);
+
+    if installs.is_empty() {
         return Err(Error::SteamVdfNotFound {
-            path: vdf_path.clone(),
+            path: home
+                .join(".local")
+                .join("share")
+                .join("Steam")
+                .join("steamapps")
+                .join("libraryfolders.vdf"),
         });
     }
 
-    Ok(vdf_path)
+    Ok(installs.swap_remove(index))
+}
+
+/// Returns the path to Steam's libraryfolders.vdf file.
+///
+/// Uses the [`default_steam_install`].
+pub fn steam_library_vdf_path() -> Result<PathBuf> {
+    default_steam_install().map(|install| install.vdf_path)
+}
+
+/// Checks if the current user runs a Steam client from the given root.
+fn is_install_running(root: &Path, flavour: SteamFlavour) -> bool {
+    process::user_steam_processes()
+        .iter()
+        .any(|p| p.belongs_to(root, flavour))
 }
 
 /// Checks if Steam is currently running.
 pub fn is_steam_running() -> bool {
-    Command::new("pgrep")
-        .args(["-x", "steam"])
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+    !process::user_steam_processes().is_empty()
 }
 
 /// Shuts down Steam gracefully.
 ///
-/// Sends a shutdown signal and waits for the process to terminate.
-pub fn shutdown_steam() -> Result<()> {
-    if !is_steam_running() {
-        return Ok(());
-    }
+/// Asks the client to exit and waits for its processes to terminate.
+pub fn shutdown_steam() -> Result<SteamShutdown> {
+    let processes = process::user_steam_processes();
+    let mut command = Command::new("steam");
+    command.arg("-shutdown");
+    wait_for_shutdown(command, &processes, process::DEFAULT_SHUTDOWN_TIMEOUT)
+}
 
-    // Use Steam's built-in shutdown command
-    let output = Command::new("steam")
-        .arg("--shutdown")
-        .output()
-        .command_context("steam --shutdown")?;
+/// Shuts down the client of a specific Steam installation.
+pub fn shutdown_steam_install(install: &SteamInstall) -> Result<SteamShutdown> {
+    shutdown_steam_install_within(install, process::DEFAULT_SHUTDOWN_TIMEOUT)
+}
 
-    // Give Steam time to shut down
-    std::thread::sleep(std::time::Duration::from_secs(3));
+/// Shuts down the client of a Steam installation, waiting at most `timeout`
+/// for its processes to exit.
+///
+/// In Game Mode the session starts a new client afterwards, which
+/// [`SteamShutdown::auto_restart`] reports.
+pub fn shutdown_steam_install_within(
+    install: &SteamInstall,
+    timeout: std::time::Duration,
+) -> Result<SteamShutdown> {
+    let processes = install.processes();
+    let mut command = install.command();
+    command.arg("-shutdown");
+    wait_for_shutdown(command, &processes, timeout)
+}
 
-    // Verify Steam has stopped
-    for _ in 0..10 {
-        if !is_steam_running() {
-            return Ok(());
-        }
-        std::thread::sleep(std::time::Duration::from_millis(500));
+/// Runs a shutdown command and waits until `processes` have exited.
+fn wait_for_shutdown(
+    mut command: Command,
+    processes: &[SteamProcess],
+    timeout: std::time::Duration,
+) -> Result<SteamShutdown> {
+    if processes.is_empty() {
+        return Ok(SteamShutdown::default());
     }
 
-    if is_steam_running() {
-        return Err(Error::SteamProcess {
-            message: "Steam did not shut down within timeout".to_string(),
-        });
-    }
+    // Use Steam's built-in shutdown command
+    let output = command.output().command_context("steam -shutdown")?;
 
-    // Check if the command reported an error
-    if !output.status.success() {
+    if !process::wait_for_exit(processes, timeout) {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        // Steam --shutdown often returns non-zero even when successful
-        if !stderr.is_empty() && !stderr.contains("Steam is not running") {
-            return Err(Error::SteamProcess {
-                message: stderr.to_string(),
-            });
-        }
+        // Steam -shutdown often returns non-zero even when successful, so
+        // its output only matters when the client is still up
+        let message = if output.status.success() || stderr.trim().is_empty() {
+            format!("Steam did not shut down within {}s", timeout.as_secs())
+        } else {
+            stderr.trim().to_string()
+        };
+        return Err(Error::SteamProcess { message });
     }
 
-    Ok(())
+    Ok(SteamShutdown {
+        pids: processes.iter().map(|p| p.pid).collect(),
+        auto_restart: processes.iter().any(SteamProcess::auto_restarts),
+    })
 }
 
 /// Parses the libraryfolders.vdf file.
@@ -122,16 +714,19 @@ pub fn parse_library_folders(path: &Path) -> Result<Vec<(String, LibraryFolder)>
 
 /// Parses libraryfolders.vdf content.
 fn parse_library_folders_content(content: &str) -> Result<Vec<(String, LibraryFolder)>> {
-    let root: LibraryFoldersRoot =
-        keyvalues_serde::from_str(content).map_err(|e| Error::VdfParse {
-            message: format!("Failed to parse VDF: {}", e),
-        })?;
+    let mut document = KvBlock::parse(content)?;
+    let root = library_folders_block(&mut document)?;
 
-    // Filter to only numeric IDs (actual library folders) and sort by ID
+    // Only numeric IDs are library folders (skips e.g. "contentstatsid")
     let mut folders: Vec<(String, LibraryFolder)> = root
-        .folders
-        .into_iter()
-        .filter(|(id, _)| id.chars().all(|c| c.is_ascii_digit()))
+        .iter()
+        .filter(|(id, _)| is_library_id(id))
+        .filter_map(|(id, value)| {
+            Some((
+                id.to_string(),
+                LibraryFolder::from_block(value.as_block()?)?,
+            ))
+        })
         .collect();
 
     folders.sort_by(|(a, _), (b, _)| {
@@ -145,59 +740,893 @@ fn parse_library_folders_content(content: &str) -> Result<Vec<(String, LibraryFo
 
 /// Injects a new library folder into libraryfolders.vdf.
 ///
+/// The entry is added after the last library, keeping trailing keys such as
+/// `contentstatsid` in place.
+///
 /// Note: Steam must be shut down before calling this function.
 pub fn inject_library_folder(vdf_path: &Path, mount_path: &Path, label: &str) -> Result<()> {
-    let folders = parse_library_folders(vdf_path)?;
+    let mut document = load_vdf(vdf_path)?;
+    if inject_library_folder_into(&mut document, mount_path, label, "0")? {
+        save_vdf(vdf_path, &document)?;
+    }
+    Ok(())
+}
+
+/// Registers a library prepared by [`initialize_library_with_ctx`] in
+/// libraryfolders.vdf, with the content ID and label of its marker file.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn register_library(vdf_path: &Path, library: &InitializedLibrary) -> Result<()> {
+    let mut document = load_vdf(vdf_path)?;
+    if inject_library_folder_into(
+        &mut document,
+        &library.path,
+        &library.label,
+        &library.contentid,
+    )? {
+        save_vdf(vdf_path, &document)?;
+    }
+    Ok(())
+}
+
+/// Adds a library folder entry to a parsed libraryfolders.vdf.
+///
+/// Returns false if the path is already registered.
+fn inject_library_folder_into(
+    document: &mut KvBlock,
+    mount_path: &Path,
+    label: &str,
+    contentid: &str,
+) -> Result<bool> {
+    let root = library_folders_block(document)?;
+    let mount_path_str = mount_path.display().to_string();
 
     // Check if the path already exists
-    if folders.iter().any(|(_, f)| f.path == mount_path) {
-        // Already registered, nothing to do
-        return Ok(());
+    let already_registered = root
+        .iter()
+        .filter_map(|(_, value)| value.as_block())
+        .any(|folder| folder.get_str("path") == Some(mount_path_str.as_str()));
+    if already_registered {
+        return Ok(false);
     }
 
     // Calculate next ID
-    let next_id = folders
+    let next_id = root
         .iter()
         .filter_map(|(id, _)| id.parse::<u32>().ok())
         .max()
         .map(|n| n + 1)
         .unwrap_or(1);
 
-    // Read original content
-    let content = fs::read_to_string(vdf_path).vdf_write_context(vdf_path)?;
+    let mut entry = KvBlock::new();
+    entry.set_str("path", mount_path_str);
+    entry.set_str("label", label);
+    entry.set_str("contentid", contentid);
+    entry.set_str("totalsize", "0");
+    entry.set("apps", KvValue::Block(KvBlock::new()));
 
-    // Build new folder entry
-    let new_entry = format!(
-        r#"	"{}"
-	{{
-		"path"		"{}"
-		"label"		"{}"
-		"contentid"		"0"
-		"totalsize"		"0"
-		"apps"
-		{{
-		}}
-	}}"#,
-        next_id,
-        mount_path.display(),
-        label
-    );
-
-    // Insert before the final closing brace
-    let output = if let Some(last_brace) = content.rfind('}') {
-        let (before, after) = content.split_at(last_brace);
-        format!("{}\n{}\n{}", before.trim_end(), new_entry, after)
+    let index = root
+        .iter()
+        .rposition(|(id, _)| is_library_id(id))
+        .map_or(0, |i| i + 1);
+    root.insert(index, next_id.to_string(), KvValue::Block(entry));
+
+    Ok(true)
+}
+
+/// Removes a library folder from libraryfolders.vdf.
+///
+/// The remaining libraries are renumbered so the IDs stay contiguous.
+/// Returns false if the path was not registered. The library inside the
+/// Steam installation itself (ID "0") cannot be removed.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn remove_library_folder(vdf_path: &Path, library_path: &Path) -> Result<bool> {
+    let mut document = load_vdf(vdf_path)?;
+    let removed = remove_library_folder_from(&mut document, library_path)?;
+
+    if removed {
+        save_vdf(vdf_path, &document)?;
+    }
+
+    Ok(removed)
+}
+
+/// Removes a library folder entry from a parsed libraryfolders.vdf.
+fn remove_library_folder_from(document: &mut KvBlock, library_path: &Path) -> Result<bool> {
+    let root = library_folders_block(document)?;
+    let Some(id) = find_library_id(root, library_path) else {
+        return Ok(false);
+    };
+    if id == "0" {
+        return Err(Error::SteamLibrary {
+            path: library_path.to_path_buf(),
+            message: "the library of the Steam installation cannot be removed".to_string(),
+        });
+    }
+
+    root.remove(&id);
+
+    let library_ids = root
+        .entries
+        .iter_mut()
+        .filter(|(key, _)| is_library_id(key));
+    for (next_id, (key, _)) in library_ids.enumerate() {
+        *key = next_id.to_string();
+    }
+
+    Ok(true)
+}
+
+/// Sets the label Steam shows for a library folder.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn set_library_label(vdf_path: &Path, library_path: &Path, label: &str) -> Result<()> {
+    let mut document = load_vdf(vdf_path)?;
+    let root = library_folders_block(&mut document)?;
+    let folder = find_library_id(root, library_path)
+        .and_then(|id| root.get_block_mut(&id))
+        .ok_or_else(|| Error::SteamLibrary {
+            path: library_path.to_path_buf(),
+            message: "not registered in libraryfolders.vdf".to_string(),
+        })?;
+
+    folder.set_str("label", label);
+    save_vdf(vdf_path, &document)
+}
+
+/// Makes a library folder the default install location.
+///
+/// Steam keeps its library list in the `BaseInstallFolder_<n>` keys of
+/// config.vdf and installs to the first one by default. The library becomes
+/// `BaseInstallFolder_1`; the other entries follow in their previous order.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn set_default_library(config_vdf_path: &Path, library_path: &Path) -> Result<()> {
+    let mut document = load_vdf(config_vdf_path)?;
+    set_default_library_in(&mut document, library_path);
+    save_vdf(config_vdf_path, &document)
+}
+
+/// Reorders the `BaseInstallFolder_<n>` keys of a parsed config.vdf.
+fn set_default_library_in(document: &mut KvBlock, library_path: &Path) {
+    let steam = CONFIG_STEAM_BLOCK
+        .iter()
+        .fold(document, |block, key| block.get_or_insert_block(key));
+    let default_path = library_path.display().to_string();
+
+    let mut folders: Vec<(u32, String)> = steam
+        .iter()
+        .filter_map(|(key, value)| {
+            let index = key
+                .get(..BASE_INSTALL_FOLDER_PREFIX.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(BASE_INSTALL_FOLDER_PREFIX))
+                .and_then(|_| key[BASE_INSTALL_FOLDER_PREFIX.len()..].parse().ok())?;
+            Some((index, value.as_str()?.to_string()))
+        })
+        .filter(|(_, path)| *path != default_path)
+        .collect();
+    folders.sort_by_key(|(index, _)| *index);
+
+    steam.retain(|key, _| {
+        !key.get(..BASE_INSTALL_FOLDER_PREFIX.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(BASE_INSTALL_FOLDER_PREFIX))
+    });
+    let paths = std::iter::once(default_path).chain(folders.into_iter().map(|(_, path)| path));
+    for (index, path) in paths.enumerate() {
+        steam.set_str(
+            &format!("{}{}", BASE_INSTALL_FOLDER_PREFIX, index + 1),
+            path,
+        );
+    }
+}
+
+/// Returns the ID of the library folder with the given path.
+fn find_library_id(root: &KvBlock, library_path: &Path) -> Option<String> {
+    root.iter()
+        .filter(|(id, _)| is_library_id(id))
+        .find(|(_, value)| {
+            value
+                .as_block()
+                .and_then(|folder| folder.get_str("path"))
+                .is_some_and(|path| Path::new(path) == library_path)
+        })
+        .map(|(id, _)| id.to_string())
+}
+
+/// A library folder prepared on a drive by [`initialize_library_with_ctx`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializedLibrary {
+    /// Library root (the directory holding `steamapps`).
+    pub path: PathBuf,
+    /// Content ID from the library's marker file.
+    pub contentid: String,
+    /// Label from the library's marker file.
+    pub label: String,
+    /// Whether an existing library was found and reused.
+    pub adopted: bool,
+}
+
+/// Returns true if a directory is a Steam library root.
+fn is_library_root(dir: &Path) -> bool {
+    dir.join(LIBRARY_MARKER_FILE).is_file() || dir.join("steamapps").is_dir()
+}
+
+/// Finds an existing Steam library on a mounted drive.
+///
+/// Looks at the drive root (libraries created by a Deck on an SD card), its
+/// direct subdirectories (`SteamLibrary`, also from Windows) and the default
+/// Windows Steam install directories.
+pub fn find_existing_library(mount_point: &Path) -> Option<PathBuf> {
+    if is_library_root(mount_point) {
+        return Some(mount_point.to_path_buf());
+    }
+
+    let mut children: Vec<PathBuf> = fs::read_dir(mount_point)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    children.sort();
+
+    let windows_installs = ["Program Files (x86)", "Program Files"]
+        .iter()
+        .map(|dir| mount_point.join(dir).join("Steam"));
+
+    children
+        .into_iter()
+        .chain(windows_installs)
+        .find(|dir| is_library_root(dir))
+}
+
+/// Creates or adopts a Steam library folder on a mounted drive.
+///
+/// With `library_dir` (relative to the mount point), that directory is used.
+/// Otherwise an existing library found by [`find_existing_library`] is
+/// adopted, or a new one is created in [`DEFAULT_LIBRARY_DIR`].
+///
+/// The `steamapps` tree is created and the library's `libraryfolder.vdf`
+/// marker written with a new content ID unless one exists. If the user cannot
+/// write to the library (e.g. an ext4 drive root owned by root, or a library
+/// created by another user), it is created through the privileged context and
+/// handed to the current user.
+pub fn initialize_library_with_ctx(
+    mount_point: &Path,
+    library_dir: Option<&Path>,
+    ctx: &mut ExecutionContext,
+) -> Result<InitializedLibrary> {
+    let path = match library_dir {
+        Some(dir) => mount_point.join(dir),
+        None => find_existing_library(mount_point)
+            .unwrap_or_else(|| mount_point.join(DEFAULT_LIBRARY_DIR)),
+    };
+    let adopted = is_library_root(&path);
+    let steamapps = path.join("steamapps");
+
+    // As root for another user, go through chown so the user owns the library
+    let writable_dir = if path.exists() { &path } else { mount_point };
+    if preset::invoking_uid() == preset::current_uid()
+        && access(writable_dir, AccessFlags::W_OK).is_ok()
+    {
+        fs::create_dir_all(&steamapps).map_err(|e| Error::SteamLibrary {
+            path: path.clone(),
+            message: format!("cannot create steamapps: {}", e),
+        })?;
+    } else {
+        let owner = format!("{}:{}", preset::invoking_uid(), preset::invoking_gid());
+        ctx.mkdir_privileged(&steamapps.display().to_string())?;
+        ctx.run_privileged_checked("chown", &["-R", &owner, &path.display().to_string()])?;
+    }
+
+    let marker_path = path.join(LIBRARY_MARKER_FILE);
+    // A new library has no marker yet; a broken one is not silently replaced
+    let mut marker = if marker_path.exists() {
+        load_vdf(&marker_path)?
     } else {
-        return Err(Error::VdfParse {
-            message: "Could not find closing brace in VDF file".to_string(),
+        KvBlock::default()
+    };
+    let fields = marker.get_or_insert_block(LIBRARY_MARKER_KEY);
+    let contentid = fields
+        .get_str("contentid")
+        .filter(|id| !id.is_empty() && *id != "0")
+        .map(str::to_string);
+    let label = fields.get_str("label").unwrap_or_default().to_string();
+
+    let contentid = match contentid {
+        Some(contentid) => contentid,
+        None => {
+            let contentid = rand::rng().random::<u64>().to_string();
+            fields.set_str("contentid", contentid.as_str());
+            fields.set_str("label", label.as_str());
+            save_vdf(&marker_path, &marker)?;
+            contentid
+        }
+    };
+
+    Ok(InitializedLibrary {
+        path,
+        contentid,
+        label,
+        adopted,
+    })
+}
+
+/// A game or tool installed in a Steam library, read from its
+/// `steamapps/appmanifest_<appid>.acf`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledApp {
+    /// Steam app ID.
+    pub appid: u32,
+    /// Display name.
+    pub name: String,
+    /// Directory below `steamapps/common`.
+    pub installdir: String,
+    /// Size of the installed files in bytes.
+    pub size_on_disk: u64,
+    /// Steam's install state bit flags (4 = fully installed).
+    pub state_flags: u32,
+    /// Installed build.
+    pub buildid: u64,
+    /// Last update (seconds since the Unix epoch).
+    pub last_updated: u64,
+    /// Root of the library the app is installed in.
+    pub library: PathBuf,
+}
+
+impl InstalledApp {
+    /// Returns true if the app is fully installed (not queued or partially
+    /// downloaded).
+    pub fn is_fully_installed(&self) -> bool {
+        self.state_flags & APP_STATE_FULLY_INSTALLED != 0
+    }
+
+    /// Returns the directory holding the app's files.
+    pub fn install_path(&self) -> PathBuf {
+        self.library
+            .join("steamapps")
+            .join("common")
+            .join(&self.installdir)
+    }
+
+    /// Parses an app manifest. Returns None if required keys are missing.
+    fn from_manifest(library: &Path, content: &str) -> Option<Self> {
+        let document = KvBlock::parse(content).ok()?;
+        let state = document.get_block("AppState")?;
+        let number = |key| state.get_str(key)?.parse::<u64>().ok();
+
+        Some(Self {
+            appid: number("appid")?.try_into().ok()?,
+            name: state.get_str("name")?.to_string(),
+            installdir: state.get_str("installdir")?.to_string(),
+            size_on_disk: number("SizeOnDisk").unwrap_or(0),
+            state_flags: number("StateFlags")
+                .and_then(|f| f.try_into().ok())
+                .unwrap_or(0),
+            buildid: number("buildid").unwrap_or(0),
+            last_updated: number("LastUpdated").unwrap_or(0),
+            library: library.to_path_buf(),
+        })
+    }
+}
+
+/// Lists the apps installed in a Steam library, sorted by name.
+///
+/// Manifests that cannot be read or parsed are skipped.
+pub fn list_installed_apps(library: &Path) -> Result<Vec<InstalledApp>> {
+    let steamapps = library.join("steamapps");
+    let entries = fs::read_dir(&steamapps).map_err(|e| Error::SteamLibrary {
+        path: library.to_path_buf(),
+        message: format!("cannot read steamapps: {}", e),
+    })?;
+
+    let mut apps: Vec<InstalledApp> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with("appmanifest_") && name.ends_with(".acf"))
+        })
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| InstalledApp::from_manifest(library, &content))
+        .collect();
+    apps.sort_by_key(|app| app.name.to_lowercase());
+
+    Ok(apps)
+}
+
+/// Progress of a [`move_app`] operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveProgress {
+    /// Bytes moved so far.
+    pub bytes_done: u64,
+    /// Bytes to move in total.
+    pub bytes_total: u64,
+}
+
+/// Returns the paths belonging to an app, relative to `steamapps`.
+fn app_paths(app: &InstalledApp) -> [PathBuf; 4] {
+    [
+        Path::new("common").join(&app.installdir),
+        PathBuf::from(format!("appmanifest_{}.acf", app.appid)),
+        Path::new("shadercache").join(app.appid.to_string()),
+        Path::new("compatdata").join(app.appid.to_string()),
+    ]
+}
+
+/// An app whose files are ready to be moved to another library, returned
+/// by [`prepare_move`] and completed by [`finish_move`].
+#[derive(Debug)]
+pub struct PreparedMove {
+    app: InstalledApp,
+    target_library: PathBuf,
+    items: Vec<(PathBuf, PathBuf)>,
+    same_filesystem: bool,
+    finished: bool,
+}
+
+impl PreparedMove {
+    /// Removes the copies made by [`prepare_move`], unless the move was
+    /// finished.
+    pub fn abort(&self) {
+        if !self.finished && !self.same_filesystem {
+            remove_copies(&self.items);
+        }
+    }
+}
+
+/// An app moved by [`finish_move`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedApp {
+    /// The app as installed in the target library.
+    pub app: InstalledApp,
+    /// Original files that could not be deleted after the move.
+    pub leftovers: Vec<PathBuf>,
+}
+
+/// Moves an installed app to another Steam library.
+///
+/// Runs [`prepare_move`] and [`finish_move`] in one go.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn move_app(
+    vdf_path: &Path,
+    app: &InstalledApp,
+    target_library: &Path,
+    on_progress: impl FnMut(MoveProgress),
+) -> Result<MovedApp> {
+    if SteamInstall::from_vdf_path(vdf_path).is_some_and(|install| install.check_running()) {
+        return Err(Error::MoveApp {
+            app: app.name.clone(),
+            message: "Steam must be shut down first".to_string(),
         });
+    }
+
+    let mut prepared = prepare_move(vdf_path, app, target_library, on_progress)?;
+    finish_move(vdf_path, &mut prepared)
+}
+
+/// Prepares moving an installed app to another Steam library.
+///
+/// The app's game files (`steamapps/common/<installdir>`), app manifest and
+/// `shadercache` and `compatdata` directories are moved. Within one
+/// filesystem they are renamed later by [`finish_move`]. Across filesystems
+/// the free space is checked, then the files are copied here, which may take
+/// minutes and does not need Steam to be stopped. Symlinks, which Proton
+/// prefixes and some native games contain, are refused up front on NTFS,
+/// exFAT and FAT targets unless the target's compatdata is redirected.
+/// `on_progress` is called as data is copied.
+///
+/// Copies are removed if copying fails, or later by [`PreparedMove::abort`].
+pub fn prepare_move(
+    vdf_path: &Path,
+    app: &InstalledApp,
+    target_library: &Path,
+    mut on_progress: impl FnMut(MoveProgress),
+) -> Result<PreparedMove> {
+    let move_error = |message: String| Error::MoveApp {
+        app: app.name.clone(),
+        message,
+    };
+
+    if target_library == app.library {
+        return Err(move_error("the app is already in this library".to_string()));
+    }
+
+    // Both libraries must be registered before anything is moved
+    let mut document = load_vdf(vdf_path)?;
+    library_ids(&mut document, app, target_library)?;
+
+    let source_apps = app.library.join("steamapps");
+    let target_apps = target_library.join("steamapps");
+    let items: Vec<(PathBuf, PathBuf)> = app_paths(app)
+        .into_iter()
+        .filter(|path| source_apps.join(path).symlink_metadata().is_ok())
+        .map(|path| (source_apps.join(&path), target_apps.join(&path)))
+        .collect();
+    if let Some((_, existing)) = items.iter().find(|(_, dst)| dst.symlink_metadata().is_ok()) {
+        return Err(move_error(format!("{} already exists", existing.display())));
+    }
+
+    let bytes_total = items.iter().map(|(src, _)| tree_size(src)).sum();
+    let mut progress = MoveProgress {
+        bytes_done: 0,
+        bytes_total,
     };
+    on_progress(progress);
 
-    fs::write(vdf_path, output).vdf_write_context(vdf_path)?;
+    let device_of = |path: &Path| fs::metadata(path).map(|m| m.dev()).ok();
+    let same_filesystem =
+        device_of(&source_apps).is_some() && device_of(&source_apps) == device_of(target_library);
+    if !same_filesystem {
+        check_copy_target(&items, crate::sysfs::filesystem_type).map_err(move_error)?;
+        let available = usage::filesystem_usage(target_library)?.available;
+        if available < bytes_total {
+            return Err(move_error(format!(
+                "{} bytes needed but only {} available on {}",
+                bytes_total,
+                available,
+                target_library.display()
+            )));
+        }
+        copy_all(&items, &mut progress, &mut on_progress).map_err(|e| move_error(e.to_string()))?;
+    }
+
+    progress.bytes_done = bytes_total;
+    on_progress(progress);
 
+    Ok(PreparedMove {
+        app: app.clone(),
+        target_library: target_library.to_path_buf(),
+        items,
+        same_filesystem,
+        finished: false,
+    })
+}
+
+/// Completes a move prepared by [`prepare_move`].
+///
+/// Renames the files within one filesystem, then moves the app between the
+/// two libraries' `apps` maps in libraryfolders.vdf. If saving fails, the
+/// copies are removed and renamed directories are moved back. The originals
+/// of copied files are deleted only once the file was saved; the move
+/// counts as done even if some of them could not be deleted, and those are
+/// returned as leftovers.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn finish_move(vdf_path: &Path, prepared: &mut PreparedMove) -> Result<MovedApp> {
+    let app = &prepared.app;
+    let items = &prepared.items;
+    let move_error = |message: String| Error::MoveApp {
+        app: app.name.clone(),
+        message,
+    };
+
+    // Loaded again, as the client may have rewritten it during the copy
+    let mut document = load_vdf(vdf_path)?;
+    let (source_id, target_id) = library_ids(&mut document, app, &prepared.target_library)?;
+    if prepared.same_filesystem {
+        rename_items(items).map_err(|e| move_error(e.to_string()))?;
+    }
+
+    let root = library_folders_block(&mut document)?;
+    if let Some(apps) = root
+        .get_block_mut(&source_id)
+        .and_then(|folder| folder.get_block_mut("apps"))
+    {
+        apps.remove(&app.appid.to_string());
+    }
+    if let Some(folder) = root.get_block_mut(&target_id) {
+        folder
+            .get_or_insert_block("apps")
+            .set_str(&app.appid.to_string(), app.size_on_disk.to_string());
+    }
+    if let Err(e) = save_vdf(vdf_path, &document) {
+        if prepared.same_filesystem {
+            rename_back(items);
+        } else {
+            remove_copies(items);
+        }
+        return Err(e);
+    }
+    prepared.finished = true;
+
+    let leftovers = if prepared.same_filesystem {
+        Vec::new()
+    } else {
+        items
+            .iter()
+            .filter(|(src, _)| remove_tree(src).is_err())
+            .map(|(src, _)| src.clone())
+            .collect()
+    };
+
+    Ok(MovedApp {
+        app: InstalledApp {
+            library: prepared.target_library.clone(),
+            ..app.clone()
+        },
+        leftovers,
+    })
+}
+
+/// Returns the IDs of the source and target library of a move in
+/// libraryfolders.vdf.
+fn library_ids(
+    document: &mut KvBlock,
+    app: &InstalledApp,
+    target_library: &Path,
+) -> Result<(String, String)> {
+    let root = library_folders_block(document)?;
+    let [source_id, target_id] = [app.library.as_path(), target_library].map(|library| {
+        find_library_id(root, library).ok_or_else(|| Error::MoveApp {
+            app: app.name.clone(),
+            message: format!("{} is not a Steam library", library.display()),
+        })
+    });
+    Ok((source_id?, target_id?))
+}
+
+/// Checks that the items can be copied to filesystems without symlinks.
+///
+/// NTFS, exFAT and FAT mounts (see [`compatdata::is_posix_filesystem`])
+/// cannot hold symlinks, so a tree containing one could only be copied in
+/// part. Proton prefixes always contain some and are only accepted if the
+/// target library's compatdata is redirected to a POSIX filesystem.
+/// `fstype_of` returns the filesystem type holding a path.
+fn check_copy_target(
+    items: &[(PathBuf, PathBuf)],
+    fstype_of: impl Fn(&Path) -> Option<String>,
+) -> std::result::Result<(), String> {
+    for (src, dst) in items {
+        let Some(fstype) = dst
+            .ancestors()
+            .find(|path| path.exists())
+            .and_then(&fstype_of)
+            .filter(|fstype| !compatdata::is_posix_filesystem(fstype))
+        else {
+            continue;
+        };
+
+        let is_prefix = src
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|name| name == "compatdata");
+        if is_prefix {
+            return Err(format!(
+                "Proton prefixes cannot be kept on {}; redirect the target library's compatdata first",
+                fstype
+            ));
+        }
+        if let Some(link) = find_symlink(src) {
+            return Err(format!(
+                "{} is a symlink, which {} cannot hold",
+                link.display(),
+                fstype
+            ));
+        }
+    }
     Ok(())
 }
 
+/// Returns the first symlink in a tree, without following symlinks.
+fn find_symlink(path: &Path) -> Option<PathBuf> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.is_symlink() {
+        return Some(path.to_path_buf());
+    }
+    if !metadata.is_dir() {
+        return None;
+    }
+    fs::read_dir(path)
+        .ok()?
+        .flatten()
+        .find_map(|entry| find_symlink(&entry.path()))
+}
+
+/// Returns the total size of the files in a tree, without following symlinks.
+fn tree_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if metadata.is_file() {
+        return metadata.len();
+    }
+    if !metadata.is_dir() {
+        return 0;
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| tree_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Renames each item, moving already renamed items back if one fails.
+fn rename_items(items: &[(PathBuf, PathBuf)]) -> std::io::Result<()> {
+    for (index, (src, dst)) in items.iter().enumerate() {
+        let renamed = dst
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::rename(src, dst));
+        if let Err(e) = renamed {
+            rename_back(&items[..index]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Moves renamed items back to their original location.
+fn rename_back(items: &[(PathBuf, PathBuf)]) {
+    for (src, dst) in items {
+        let _ = fs::rename(dst, src);
+    }
+}
+
+/// Copies each item, then removes the originals.
+///
+/// If any copy fails, the copies made so far are removed and the originals
+/// are kept.
+fn copy_items(
+    items: &[(PathBuf, PathBuf)],
+    progress: &mut MoveProgress,
+    on_progress: &mut impl FnMut(MoveProgress),
+) -> std::io::Result<()> {
+    copy_all(items, progress, on_progress)?;
+    for (src, _) in items {
+        remove_tree(src)?;
+    }
+    Ok(())
+}
+
+/// Copies each item, keeping the originals.
+///
+/// If any copy fails, the copies made so far are removed.
+fn copy_all(
+    items: &[(PathBuf, PathBuf)],
+    progress: &mut MoveProgress,
+    on_progress: &mut impl FnMut(MoveProgress),
+) -> std::io::Result<()> {
+    for (index, (src, dst)) in items.iter().enumerate() {
+        let copied = dst
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| copy_tree(src, dst, progress, on_progress));
+        if let Err(e) = copied {
+            remove_copies(&items[..=index]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Removes the copies made by [`copy_all`].
+fn remove_copies(items: &[(PathBuf, PathBuf)]) {
+    for (_, dst) in items {
+        let _ = remove_tree(dst);
+    }
+}
+
+/// Copies a file or directory tree, recreating symlinks and keeping
+/// permissions where the target filesystem supports them.
+fn copy_tree(
+    src: &Path,
+    dst: &Path,
+    progress: &mut MoveProgress,
+    on_progress: &mut impl FnMut(MoveProgress),
+) -> std::io::Result<()> {
+    let metadata = fs::symlink_metadata(src)?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(src)?, dst);
+    }
+
+    if file_type.is_dir() {
+        fs::create_dir(dst)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_tree(
+                &entry.path(),
+                &dst.join(entry.file_name()),
+                progress,
+                on_progress,
+            )?;
+        }
+    } else {
+        let mut reader = fs::File::open(src)?;
+        let mut writer = fs::File::create(dst)?;
+        let mut buffer = vec![0; COPY_BUFFER_SIZE];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            progress.bytes_done += read as u64;
+            on_progress(*progress);
+        }
+        // The original is deleted afterwards, so the copy must be on disk
+        writer.sync_all()?;
+    }
+
+    // exFAT and NTFS mounts have fixed permissions; that is fine
+    let _ = fs::set_permissions(dst, metadata.permissions());
+    Ok(())
+}
+
+/// Removes a file, symlink or directory tree.
+fn remove_tree(path: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Rewrites library folder paths after a mount point was renamed.
+///
+/// Every library whose path is `old_prefix` or lies below it is moved to the
+/// same location under `new_prefix`. Returns the number of updated libraries;
+/// the file is only written if at least one changed.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn relocate_library_folders(
+    vdf_path: &Path,
+    old_prefix: &Path,
+    new_prefix: &Path,
+) -> Result<usize> {
+    let mut document = load_vdf(vdf_path)?;
+    let relocated = relocate_library_folders_in(&mut document, old_prefix, new_prefix)?;
+
+    if relocated > 0 {
+        save_vdf(vdf_path, &document)?;
+    }
+
+    Ok(relocated)
+}
+
+/// Rewrites the library paths of a parsed libraryfolders.vdf.
+fn relocate_library_folders_in(
+    document: &mut KvBlock,
+    old_prefix: &Path,
+    new_prefix: &Path,
+) -> Result<usize> {
+    let root = library_folders_block(document)?;
+    let mut relocated = 0;
+
+    for (_, value) in root.entries.iter_mut() {
+        let Some(folder) = value.as_block_mut() else {
+            continue;
+        };
+        let Some(suffix) = folder
+            .get_str("path")
+            .and_then(|path| Path::new(path).strip_prefix(old_prefix).ok())
+        else {
+            continue;
+        };
+        // Joining an empty suffix would add a trailing slash
+        let new_path = if suffix.as_os_str().is_empty() {
+            new_prefix.to_path_buf()
+        } else {
+            new_prefix.join(suffix)
+        };
+        folder.set_str("path", new_path.display().to_string());
+        relocated += 1;
+    }
+
+    Ok(relocated)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,4 +1677,403 @@ mod tests {
         assert_eq!(folder1.path, PathBuf::from("/run/media/mmcblk0p1"));
         assert_eq!(folder1.label, "SD Card");
     }
+
+    #[test]
+    fn test_discover_steam_installs() {
+        let home = tempfile::tempdir().unwrap();
+        let native = home.path().join(".local/share/Steam");
+        let flatpak = home
+            .path()
+            .join(".var/app")
+            .join(FLATPAK_APP_ID)
+            .join(".local/share/Steam");
+        for root in [&native, &flatpak] {
+            fs::create_dir_all(root.join("steamapps")).unwrap();
+            fs::write(root.join("steamapps/libraryfolders.vdf"), SAMPLE_VDF).unwrap();
+        }
+        // ~/.steam/steam points at the Flatpak root and is reported once
+        fs::create_dir_all(home.path().join(".steam")).unwrap();
+        std::os::unix::fs::symlink(&flatpak, home.path().join(".steam/steam")).unwrap();
+
+        let installs = discover_steam_installs_in(home.path());
+        assert_eq!(installs.len(), 2);
+        assert_eq!(installs[0].root, fs::canonicalize(&flatpak).unwrap());
+        assert_eq!(installs[0].flavour, SteamFlavour::Flatpak);
+        assert_eq!(installs[1].flavour, SteamFlavour::Native);
+        assert_eq!(
+            installs[1].vdf_path,
+            fs::canonicalize(&native)
+                .unwrap()
+                .join("steamapps/libraryfolders.vdf")
+        );
+        assert_eq!(
+            SteamInstall::from_vdf_path(&installs[1].vdf_path).as_ref(),
+            Some(&installs[1])
+        );
+    }
+
+    #[test]
+    fn test_relocate_library_folders() {
+        let mut document = KvBlock::parse(SAMPLE_VDF).unwrap();
+        let relocated = relocate_library_folders_in(
+            &mut document,
+            Path::new("/run/media/mmcblk0p1"),
+            Path::new("/home/deck/Drives/SD_Card"),
+        )
+        .unwrap();
+        assert_eq!(relocated, 1);
+        let output = document.to_vdf_string();
+        assert!(output.contains("\t\t\"path\"\t\t\"/home/deck/Drives/SD_Card\"\n"));
+        assert!(output.contains("\"/home/deck/.local/share/Steam\""));
+
+        // Libraries below the mount point move along; lookalike prefixes do not
+        let vdf = "\"libraryfolders\" { \"1\" { \"path\" \"/mnt/Games/SteamLibrary\" } \"2\" { \"path\" \"/mnt/GamesOld\" } }";
+        let mut document = KvBlock::parse(vdf).unwrap();
+        let relocated = relocate_library_folders_in(
+            &mut document,
+            Path::new("/mnt/Games"),
+            Path::new("/mnt/Fast"),
+        )
+        .unwrap();
+        assert_eq!(relocated, 1);
+        let folders = parse_library_folders_content(&document.to_vdf_string()).unwrap();
+        assert_eq!(folders[0].1.path, PathBuf::from("/mnt/Fast/SteamLibrary"));
+        assert_eq!(folders[1].1.path, PathBuf::from("/mnt/GamesOld"));
+    }
+
+    #[test]
+    fn test_keyvalues_unknown_escapes() {
+        let vdf = r#""libraryfolders" { "1" { "path" "D:\Games\Steam" "label" "a\"b\\c\qd" } }"#;
+        let document = KvBlock::parse(vdf).unwrap();
+        let library = document
+            .get_block("libraryfolders")
+            .unwrap()
+            .get_block("1")
+            .unwrap();
+        assert_eq!(library.get_str("path"), Some(r"D:\Games\Steam"));
+        assert_eq!(library.get_str("label"), Some(r#"a"b\c\qd"#));
+
+        // Emitting escapes the kept backslashes, so parsing again is lossless
+        let reparsed = KvBlock::parse(&document.to_vdf_string()).unwrap();
+        assert_eq!(reparsed, document);
+    }
+
+    #[test]
+    fn test_keyvalues_round_trip() {
+        // Steam's own layout is reproduced exactly, unknown keys included
+        let with_stats = format!("{}\n", SAMPLE_VDF).replacen(
+            "\t}\n}\n",
+            "\t}\n\t\"contentstatsid\"\t\t\"-123\"\n}\n",
+            1,
+        );
+        let document = KvBlock::parse(&with_stats).unwrap();
+        assert_eq!(document.to_vdf_string(), with_stats);
+
+        // Quotes and backslashes are escaped
+        let mut document = KvBlock::parse("// comment\n\"a\" { b \"C:\\\\Games\" }").unwrap();
+        assert_eq!(
+            document.get_block("A").unwrap().get_str("b"),
+            Some("C:\\Games")
+        );
+        document
+            .get_block_mut("a")
+            .unwrap()
+            .set_str("b", "say \"hi\"");
+        assert_eq!(
+            document.to_vdf_string(),
+            "\"a\"\n{\n\t\"b\"\t\t\"say \\\"hi\\\"\"\n}\n"
+        );
+        assert!(KvBlock::parse("\"a\" { \"b\" \"c\"").is_err());
+
+        // New libraries go before trailing keys
+        let mut document = KvBlock::parse(&with_stats).unwrap();
+        assert!(
+            inject_library_folder_into(&mut document, Path::new("/mnt/\"Games\""), "", "0")
+                .unwrap()
+        );
+        assert!(
+            !inject_library_folder_into(&mut document, Path::new("/run/media/mmcblk0p1"), "", "0")
+                .unwrap()
+        );
+        let root = document.get_block("libraryfolders").unwrap();
+        let keys: Vec<&str> = root.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["0", "1", "2", "contentstatsid"]);
+        assert_eq!(
+            root.get_block("2").unwrap().get_str("path"),
+            Some("/mnt/\"Games\"")
+        );
+    }
+
+    #[test]
+    fn test_remove_library_folder_and_set_default() {
+        let mut document = KvBlock::parse(SAMPLE_VDF).unwrap();
+        inject_library_folder_into(&mut document, Path::new("/mnt/Games"), "", "0").unwrap();
+
+        assert!(
+            remove_library_folder_from(&mut document, Path::new("/run/media/mmcblk0p1")).unwrap()
+        );
+        assert!(
+            !remove_library_folder_from(&mut document, Path::new("/run/media/mmcblk0p1")).unwrap()
+        );
+        assert!(
+            remove_library_folder_from(&mut document, Path::new("/home/deck/.local/share/Steam"))
+                .is_err()
+        );
+        let folders = parse_library_folders_content(&document.to_vdf_string()).unwrap();
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[1].0, "1");
+        assert_eq!(folders[1].1.path, PathBuf::from("/mnt/Games"));
+
+        let mut config = KvBlock::parse(
+            r#""InstallConfigStore" { "Software" { "valve" { "Steam" {
+                "AutoUpdateWindowEnabled" "0"
+                "BaseInstallFolder_1" "/mnt/Old"
+                "BaseInstallFolder_2" "/mnt/Games"
+            } } } }"#,
+        )
+        .unwrap();
+        set_default_library_in(&mut config, Path::new("/mnt/Games"));
+        let steam = CONFIG_STEAM_BLOCK
+            .iter()
+            .try_fold(&config, |block, key| block.get_block(key))
+            .unwrap();
+        let keys: Vec<(&str, Option<&str>)> = steam
+            .iter()
+            .map(|(key, value)| (key, value.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("AutoUpdateWindowEnabled", Some("0")),
+                ("BaseInstallFolder_1", Some("/mnt/Games")),
+                ("BaseInstallFolder_2", Some("/mnt/Old")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_initialize_library() {
+        let drive = tempfile::tempdir().unwrap();
+        let mut ctx = ExecutionContext::default();
+
+        // A fresh drive gets a new SteamLibrary with a marker
+        let library = initialize_library_with_ctx(drive.path(), None, &mut ctx).unwrap();
+        assert_eq!(library.path, drive.path().join(DEFAULT_LIBRARY_DIR));
+        assert!(!library.adopted);
+        assert!(library.path.join("steamapps").is_dir());
+        let marker = load_vdf(&library.path.join(LIBRARY_MARKER_FILE)).unwrap();
+        assert_eq!(
+            marker
+                .get_block("libraryfolder")
+                .unwrap()
+                .get_str("contentid"),
+            Some(library.contentid.as_str())
+        );
+
+        // A library from another machine is adopted with its content ID
+        let drive = tempfile::tempdir().unwrap();
+        let windows = drive.path().join("Games");
+        fs::create_dir_all(windows.join("steamapps")).unwrap();
+        fs::write(
+            windows.join(LIBRARY_MARKER_FILE),
+            "\"libraryfolder\"\n{\n\t\"contentid\"\t\t\"42\"\n\t\"label\"\t\t\"PC\"\n}\n",
+        )
+        .unwrap();
+        assert_eq!(find_existing_library(drive.path()), Some(windows.clone()));
+        let library = initialize_library_with_ctx(drive.path(), None, &mut ctx).unwrap();
+        assert!(library.adopted);
+        assert_eq!(library.path, windows);
+        assert_eq!(library.contentid, "42");
+        assert_eq!(library.label, "PC");
+
+        // A marker that does not parse is reported and left alone
+        let broken = "\"libraryfolder\"\n{\n\t\"contentid\"\t\t\"42";
+        fs::write(windows.join(LIBRARY_MARKER_FILE), broken).unwrap();
+        assert!(initialize_library_with_ctx(drive.path(), None, &mut ctx).is_err());
+        assert_eq!(
+            fs::read_to_string(windows.join(LIBRARY_MARKER_FILE)).unwrap(),
+            broken
+        );
+    }
+
+    #[test]
+    fn test_list_installed_apps() {
+        let library = tempfile::tempdir().unwrap();
+        let steamapps = library.path().join("steamapps");
+        fs::create_dir_all(&steamapps).unwrap();
+        fs::write(
+            steamapps.join("appmanifest_1145360.acf"),
+            r#""AppState"
+{
+	"appid"		"1145360"
+	"universe"		"1"
+	"name"		"Hades"
+	"StateFlags"		"4"
+	"installdir"		"Hades"
+	"LastUpdated"		"1700000000"
+	"SizeOnDisk"		"15032385536"
+	"buildid"		"12345678"
+	"InstalledDepots"
+	{
+	}
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            steamapps.join("appmanifest_570.acf"),
+            "\"AppState\" { \"appid\" \"570\" \"name\" \"Dota 2\" \"installdir\" \"dota 2 beta\" \"StateFlags\" \"1026\" }",
+        )
+        .unwrap();
+        fs::write(steamapps.join("appmanifest_1.acf"), "\"AppState\" {").unwrap();
+        fs::write(steamapps.join("libraryfolders.vdf"), SAMPLE_VDF).unwrap();
+
+        let apps = list_installed_apps(library.path()).unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].name, "Dota 2");
+        assert!(!apps[0].is_fully_installed());
+        assert_eq!(apps[1].appid, 1145360);
+        assert_eq!(apps[1].size_on_disk, 15032385536);
+        assert_eq!(apps[1].buildid, 12345678);
+        assert_eq!(apps[1].last_updated, 1700000000);
+        assert!(apps[1].is_fully_installed());
+        assert_eq!(
+            apps[1].install_path(),
+            library.path().join("steamapps/common/Hades")
+        );
+    }
+
+    #[test]
+    fn test_move_app() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("internal");
+        let target = dir.path().join("sdcard");
+        let game = source.join("steamapps/common/Hades");
+        fs::create_dir_all(&game).unwrap();
+        fs::create_dir_all(target.join("steamapps")).unwrap();
+        fs::write(game.join("Hades.exe"), vec![0u8; 3000]).unwrap();
+        fs::write(source.join("steamapps/appmanifest_1145360.acf"), "manifest").unwrap();
+        let pfx = source.join("steamapps/compatdata/1145360/pfx/dosdevices");
+        fs::create_dir_all(&pfx).unwrap();
+        std::os::unix::fs::symlink("../drive_c", pfx.join("c:")).unwrap();
+
+        let vdf_path = dir.path().join("libraryfolders.vdf");
+        let mut document = KvBlock::parse(r#""libraryfolders" {}"#).unwrap();
+        for library in [&source, &target] {
+            inject_library_folder_into(&mut document, library, "", "0").unwrap();
+        }
+        document
+            .get_block_mut("libraryfolders")
+            .and_then(|root| root.get_block_mut("1"))
+            .unwrap()
+            .get_or_insert_block("apps")
+            .set_str("1145360", "3000");
+        save_vdf(&vdf_path, &document).unwrap();
+
+        let app = InstalledApp {
+            appid: 1145360,
+            name: "Hades".to_string(),
+            installdir: "Hades".to_string(),
+            size_on_disk: 3000,
+            state_flags: 4,
+            buildid: 1,
+            last_updated: 0,
+            library: source.clone(),
+        };
+        let mut reports = Vec::new();
+        let moved = move_app(&vdf_path, &app, &target, |p| reports.push(p))
+            .unwrap()
+            .app;
+        assert_eq!(moved.library, target);
+        assert_eq!(reports.last().unwrap().bytes_done, 3000 + 8);
+        assert!(!game.exists());
+        assert!(target.join("steamapps/common/Hades/Hades.exe").is_file());
+        assert!(target.join("steamapps/appmanifest_1145360.acf").is_file());
+        assert!(
+            target
+                .join("steamapps/compatdata/1145360/pfx/dosdevices/c:")
+                .is_symlink()
+        );
+
+        let folders = parse_library_folders(&vdf_path).unwrap();
+        assert!(folders[0].1.apps.is_empty());
+        assert_eq!(
+            folders[1].1.apps.get("1145360").map(String::as_str),
+            Some("3000")
+        );
+        assert!(move_app(&vdf_path, &moved, &target, |_| {}).is_err());
+
+        // Nothing is moved before the move is finished
+        let prepared = prepare_move(&vdf_path, &moved, &source, |_| {}).unwrap();
+        prepared.abort();
+        assert!(target.join("steamapps/common/Hades/Hades.exe").is_file());
+        assert!(!game.exists());
+
+        // The cross-filesystem path copies symlinks and file contents
+        let copy = dir.path().join("copy");
+        let mut progress = MoveProgress {
+            bytes_done: 0,
+            bytes_total: 0,
+        };
+        copy_items(
+            &[(target.join("steamapps/compatdata"), copy.clone())],
+            &mut progress,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert!(copy.join("1145360/pfx/dosdevices/c:").is_symlink());
+        assert!(!target.join("steamapps/compatdata").exists());
+    }
+
+    #[test]
+    fn test_check_copy_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("internal/steamapps");
+        let target = dir.path().join("sdcard/steamapps");
+        let game = source.join("common/Celeste");
+        fs::create_dir_all(&game).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(game.join("Celeste.bin.x86_64"), "elf").unwrap();
+        let items = vec![(game.clone(), target.join("common/Celeste"))];
+        let exfat = |_: &Path| Some("exfat".to_string());
+
+        assert!(check_copy_target(&items, exfat).is_ok());
+
+        // A symlink in the game files is refused before anything is copied
+        std::os::unix::fs::symlink("Celeste.bin.x86_64", game.join("Celeste")).unwrap();
+        let error = check_copy_target(&items, exfat).unwrap_err();
+        assert!(error.contains("Celeste is a symlink"));
+        assert!(check_copy_target(&items, |_| Some("ext4".to_string())).is_ok());
+
+        // Prefixes need a redirected compatdata
+        let prefix = source.join("compatdata/504230");
+        fs::create_dir_all(&prefix).unwrap();
+        let items = vec![(prefix, target.join("compatdata/504230"))];
+        let error = check_copy_target(&items, exfat).unwrap_err();
+        assert!(error.contains("redirect"));
+        let redirected = |path: &Path| {
+            let fstype = if path.ends_with("compatdata") {
+                "ext4"
+            } else {
+                "exfat"
+            };
+            Some(fstype.to_string())
+        };
+        fs::create_dir_all(target.join("compatdata")).unwrap();
+        assert!(check_copy_target(&items, redirected).is_ok());
+    }
+
+    #[test]
+    fn test_save_vdf_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("libraryfolders.vdf");
+        fs::write(&path, SAMPLE_VDF).unwrap();
+
+        inject_library_folder(&path, Path::new("/mnt/Games"), "Games").unwrap();
+        let backup = dir.path().join("libraryfolders.vdf.backup.steamos-mount");
+        assert_eq!(fs::read_to_string(backup).unwrap(), SAMPLE_VDF);
+        assert!(!dir.path().join("libraryfolders.vdf.tmp").exists());
+        let folders = parse_library_folders(&path).unwrap();
+        assert_eq!(folders.len(), 3);
+        assert_eq!(folders[2].1.label, "Games");
+    }
 }
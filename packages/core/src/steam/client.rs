@@ -0,0 +1,232 @@
+//! Adding libraries through the Steam client instead of editing its files.
+//!
+//! Steam has no stable command-line handler that adds a library folder, so
+//! the client is asked to open its storage settings (a `steam://` URL, which
+//! a running client receives without restarting) and the user adds the drive
+//! there. Steam writes libraryfolders.vdf as soon as a library is added;
+//! the file is watched with inotify to confirm the new library. The wait
+//! can be cancelled from another thread.
+//!
+//! Unlike [`super::register_library`], this never stops Steam or touches its
+//! configuration.
+
+use std::os::fd::AsFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use super::{LibraryFolder, SteamInstall, parse_library_folders};
+use crate::error::{Error, Result};
+
+/// URL that opens Steam's storage settings.
+pub const STORAGE_SETTINGS_URL: &str = "steam://open/settings/storage";
+
+/// How long the user has to add the library by default.
+pub const DEFAULT_ADD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often a wait checks whether it was cancelled.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Asks the client of `install` to open its storage settings.
+///
+/// Starts Steam if it is not running.
+pub fn open_storage_settings(install: &SteamInstall) -> Result<()> {
+    install
+        .command()
+        .arg(STORAGE_SETTINGS_URL)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| Error::SteamProcess {
+            message: format!("failed to open Steam storage settings: {}", e),
+        })
+}
+
+/// Lets the user add a library on the drive mounted at `mount_point` in
+/// Steam, and waits at most `timeout` for it to show up.
+///
+/// Returns a library already registered below `mount_point` without
+/// opening anything. The wait fails early once `cancelled` is set.
+pub fn add_library_via_client(
+    install: &SteamInstall,
+    mount_point: &Path,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<LibraryFolder> {
+    if let Some(library) = find_library_below(&install.vdf_path, mount_point) {
+        return Ok(library);
+    }
+
+    // Watch before opening the settings so no write is missed
+    let watcher = VdfWatcher::new(&install.vdf_path)?;
+    open_storage_settings(install)?;
+    watcher.wait_for_library(mount_point, timeout, cancelled)
+}
+
+/// Waits at most `timeout` until libraryfolders.vdf lists a library below
+/// `mount_point`, or until `cancelled` is set.
+pub fn wait_for_library(
+    vdf_path: &Path,
+    mount_point: &Path,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<LibraryFolder> {
+    VdfWatcher::new(vdf_path)?.wait_for_library(mount_point, timeout, cancelled)
+}
+
+/// Returns the first library registered below `mount_point`.
+fn find_library_below(vdf_path: &Path, mount_point: &Path) -> Option<LibraryFolder> {
+    // Steam may be in the middle of replacing the file; retried on the next event
+    parse_library_folders(vdf_path)
+        .ok()?
+        .into_iter()
+        .map(|(_, folder)| folder)
+        .find(|folder| folder.path.starts_with(mount_point))
+}
+
+/// inotify watch on the directory holding libraryfolders.vdf.
+///
+/// The directory is watched rather than the file, as Steam replaces the
+/// file instead of rewriting it in place.
+struct VdfWatcher<'a> {
+    inotify: Inotify,
+    vdf_path: &'a Path,
+}
+
+impl<'a> VdfWatcher<'a> {
+    fn new(vdf_path: &'a Path) -> Result<Self> {
+        let watch_error = |e: nix::errno::Errno| Error::SteamLibrary {
+            path: vdf_path.to_path_buf(),
+            message: format!("cannot watch for changes: {}", e),
+        };
+        let dir = vdf_path.parent().unwrap_or(Path::new("."));
+
+        let inotify =
+            Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC).map_err(watch_error)?;
+        inotify
+            .add_watch(
+                dir,
+                AddWatchFlags::IN_CLOSE_WRITE
+                    | AddWatchFlags::IN_MOVED_TO
+                    | AddWatchFlags::IN_CREATE,
+            )
+            .map_err(watch_error)?;
+
+        Ok(Self { inotify, vdf_path })
+    }
+
+    fn wait_for_library(
+        &self,
+        mount_point: &Path,
+        timeout: Duration,
+        cancelled: &AtomicBool,
+    ) -> Result<LibraryFolder> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(library) = find_library_below(self.vdf_path, mount_point) {
+                return Ok(library);
+            }
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(Error::SteamLibrary {
+                    path: mount_point.to_path_buf(),
+                    message: "waiting for Steam to add a library was cancelled".to_string(),
+                });
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::SteamLibrary {
+                    path: mount_point.to_path_buf(),
+                    message: format!("Steam did not add a library within {}s", timeout.as_secs()),
+                });
+            }
+
+            let mut fds = [PollFd::new(self.inotify.as_fd(), PollFlags::POLLIN)];
+            let poll_timeout = PollTimeout::try_from(remaining.min(CANCEL_CHECK_INTERVAL))
+                .unwrap_or(PollTimeout::MAX);
+            match poll(&mut fds, poll_timeout) {
+                Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+                Err(e) => {
+                    return Err(Error::SteamLibrary {
+                        path: self.vdf_path.to_path_buf(),
+                        message: format!("cannot watch for changes: {}", e),
+                    });
+                }
+            }
+            // Events only signal a change; the file is re-read either way
+            while let Ok(events) = self.inotify.read_events() {
+                if events.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_wait_for_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let vdf_path = dir.path().join("libraryfolders.vdf");
+        let home_library = format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+            dir.path().display()
+        );
+        fs::write(&vdf_path, &home_library).unwrap();
+
+        let mount_point = Path::new("/run/media/deck/Games");
+        let cancelled = AtomicBool::new(false);
+        assert!(wait_for_library(&vdf_path, mount_point, Duration::ZERO, &cancelled).is_err());
+
+        // Steam replaces the file when a library is added
+        let writer_path = vdf_path.clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            let added = home_library.replace(
+                "\n}\n",
+                "\n\t\"1\"\n\t{\n\t\t\"path\"\t\t\"/run/media/deck/Games/SteamLibrary\"\n\t}\n}\n",
+            );
+            let tmp_path = writer_path.with_extension("tmp");
+            fs::write(&tmp_path, added).unwrap();
+            fs::rename(&tmp_path, &writer_path).unwrap();
+        });
+
+        let library =
+            wait_for_library(&vdf_path, mount_point, Duration::from_secs(10), &cancelled).unwrap();
+        assert_eq!(
+            library.path,
+            Path::new("/run/media/deck/Games/SteamLibrary")
+        );
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_cancel_wait_for_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let vdf_path = dir.path().join("libraryfolders.vdf");
+        fs::write(&vdf_path, "\"libraryfolders\"\n{\n}\n").unwrap();
+
+        let cancelled = AtomicBool::new(false);
+        let started = Instant::now();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(100));
+                cancelled.store(true, Ordering::Relaxed);
+            });
+            let waited = wait_for_library(
+                &vdf_path,
+                Path::new("/run/media/deck/Games"),
+                DEFAULT_ADD_TIMEOUT,
+                &cancelled,
+            );
+            assert!(waited.unwrap_err().to_string().contains("cancelled"));
+        });
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
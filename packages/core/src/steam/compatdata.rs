@@ -0,0 +1,446 @@
+//! Proton prefix redirection for Steam libraries on NTFS, exFAT and FAT.
+//!
+//! Proton keeps a Wine prefix per game in `steamapps/compatdata/<appid>`.
+//! Prefixes need symlinks, POSIX permissions and file names such as
+//! `dosdevices/c:`, which NTFS and exFAT mounts cannot provide reliably, so
+//! games installed there fail to launch.
+//!
+//! The fix is to keep the prefixes on the internal home filesystem:
+//! `<library>/steamapps/compatdata` is replaced by a symlink to
+//! `~/.local/share/steamos-mount/compatdata/<library>`, after moving any
+//! existing prefixes there. exFAT and FAT cannot hold the symlink itself;
+//! there the directory is bind-mounted instead, by a systemd mount unit that
+//! is bound to the drive's mount: systemd applies it again whenever the drive
+//! is mounted, and stops it before the drive is unmounted. The unit is
+//! removed when the drive is deconfigured.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::executor::ExecutionContext;
+use crate::mount_unit::{self, escape_specifiers};
+use crate::{syscall, sysfs};
+
+use super::{MoveProgress, copy_items, remove_tree};
+
+/// Filesystems that cannot hold Proton prefixes.
+const NON_POSIX_FILESYSTEMS: &[&str] = &["ntfs", "ntfs3", "fuseblk", "exfat", "vfat", "msdos"];
+
+/// Directory below the user's data directory holding redirected prefixes.
+const COMPATDATA_DATA_DIR: &str = "steamos-mount/compatdata";
+
+/// Marker comment identifying the bind mount units written for prefixes.
+pub const BIND_UNIT_MARKER: &str =
+    "# Proton prefixes bound by SteamOS Mount Tool. DO NOT EDIT THIS FILE MANUALLY.";
+
+/// How a library's compatdata directory is redirected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Redirection {
+    /// `compatdata` is a symlink to the internal directory.
+    Symlink,
+    /// The internal directory is bind-mounted onto `compatdata`.
+    BindMount,
+}
+
+/// Proton prefix state of a Steam library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatdataStatus {
+    /// Library root.
+    pub library: PathBuf,
+    /// Filesystem type of the library.
+    pub fstype: String,
+    /// Whether the filesystem cannot hold Proton prefixes.
+    pub needs_redirect: bool,
+    /// How compatdata is currently redirected to the internal directory.
+    pub redirection: Option<Redirection>,
+    /// Internal directory the prefixes are (or would be) kept in.
+    pub target: PathBuf,
+    /// App IDs with a prefix on the library's own filesystem.
+    pub affected_appids: Vec<u32>,
+}
+
+impl CompatdataStatus {
+    /// Returns true if the library needs a redirect it does not have.
+    pub fn is_broken(&self) -> bool {
+        self.needs_redirect && self.redirection.is_none()
+    }
+}
+
+/// Returns true if Proton prefixes work on the given filesystem type.
+pub fn is_posix_filesystem(fstype: &str) -> bool {
+    !NON_POSIX_FILESYSTEMS.contains(&fstype)
+}
+
+/// Returns the directory holding redirected prefixes of all libraries.
+pub fn default_target_root() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().ok_or(Error::HomeDirNotFound)?;
+    Ok(data_dir.join(COMPATDATA_DATA_DIR))
+}
+
+/// Returns the internal directory for a library's prefixes.
+///
+/// The library path is escaped into one name like systemd unit names
+/// (`/run/media/deck/my-games` becomes `run-media-deck-my\x2dgames`), so
+/// distinct libraries never share a directory.
+pub fn target_dir(target_root: &Path, library: &Path) -> Result<PathBuf> {
+    Ok(target_root.join(syscall::escape_path(library)?))
+}
+
+/// Reports the Proton prefix state of a library on a filesystem of type
+/// `fstype`.
+pub fn check_library(library: &Path, fstype: &str, target_root: &Path) -> Result<CompatdataStatus> {
+    let compatdata = library.join("steamapps").join("compatdata");
+    let target = target_dir(target_root, library)?;
+    let redirection = current_redirection(&compatdata, &target);
+
+    let affected_appids = if redirection.is_some() {
+        Vec::new()
+    } else {
+        prefix_appids(&compatdata)
+    };
+
+    Ok(CompatdataStatus {
+        library: library.to_path_buf(),
+        fstype: fstype.to_string(),
+        needs_redirect: !is_posix_filesystem(fstype),
+        redirection,
+        target,
+        affected_appids,
+    })
+}
+
+/// Redirects a library's compatdata to the internal home filesystem.
+///
+/// Does nothing for libraries on POSIX filesystems. A wrong or dangling
+/// symlink is replaced. Prefixes already on the drive are moved to the
+/// internal directory first; a prefix that exists on both sides keeps the
+/// drive's copy and the internal one is renamed to `<appid>.replaced`.
+///
+/// Bind mount units need root and go through the privileged context.
+///
+/// Note: Steam must be shut down before calling this function.
+pub fn redirect_compatdata_with_ctx(
+    library: &Path,
+    target_root: &Path,
+    ctx: &mut ExecutionContext,
+) -> Result<CompatdataStatus> {
+    let fstype = sysfs::filesystem_type(library).ok_or_else(|| Error::SteamLibrary {
+        path: library.to_path_buf(),
+        message: "not on a mounted filesystem".to_string(),
+    })?;
+    if is_posix_filesystem(&fstype) {
+        return check_library(library, &fstype, target_root);
+    }
+
+    redirect(library, &target_dir(target_root, library)?, ctx)?;
+    check_library(library, &fstype, target_root)
+}
+
+/// Moves existing prefixes and links `compatdata` to `target`.
+fn redirect(library: &Path, target: &Path, ctx: &mut ExecutionContext) -> Result<()> {
+    let compatdata = library.join("steamapps").join("compatdata");
+    let library_error = |message: String| Error::SteamLibrary {
+        path: library.to_path_buf(),
+        message,
+    };
+
+    if current_redirection(&compatdata, target).is_some() {
+        return Ok(());
+    }
+    fs::create_dir_all(target)
+        .map_err(|e| library_error(format!("cannot create {}: {}", target.display(), e)))?;
+
+    match fs::symlink_metadata(&compatdata) {
+        // Wrong or dangling link: replace it
+        Ok(metadata) if metadata.is_symlink() => fs::remove_file(&compatdata)
+            .map_err(|e| library_error(format!("cannot remove old link: {}", e)))?,
+        Ok(_) => migrate_prefixes(&compatdata, target)
+            .map_err(|e| library_error(format!("cannot migrate prefixes: {}", e)))?,
+        Err(_) => {}
+    }
+
+    match std::os::unix::fs::symlink(target, &compatdata) {
+        Ok(()) => Ok(()),
+        Err(e) if is_unsupported(&e) => {
+            fs::create_dir_all(&compatdata).map_err(|e| {
+                library_error(format!("cannot create {}: {}", compatdata.display(), e))
+            })?;
+            bind_with_ctx(target, &compatdata, ctx)
+        }
+        Err(e) => Err(library_error(format!("cannot link compatdata: {}", e))),
+    }
+}
+
+/// Installs and starts a mount unit binding `target` onto `compatdata`.
+fn bind_with_ctx(target: &Path, compatdata: &Path, ctx: &mut ExecutionContext) -> Result<()> {
+    let drive = sysfs::mount_point_of(compatdata).ok_or_else(|| Error::SteamLibrary {
+        path: compatdata.to_path_buf(),
+        message: "not on a mounted filesystem".to_string(),
+    })?;
+    let unit_name = syscall::mount_point_to_unit_name(compatdata)?;
+    let content = render_bind_unit(
+        target,
+        compatdata,
+        &syscall::mount_point_to_unit_name(&drive)?,
+    );
+
+    let unit_path = Path::new(mount_unit::SYSTEMD_UNIT_DIR).join(&unit_name);
+    ctx.write_file_privileged(&unit_path.display().to_string(), &content)?;
+    syscall::daemon_reload_with_ctx(ctx)?;
+    syscall::enable_unit_with_ctx(&unit_name, ctx)?;
+    syscall::start_unit_with_ctx(&unit_name, ctx)?;
+    Ok(())
+}
+
+/// Renders the mount unit binding `target` onto `compatdata`.
+///
+/// The unit is pulled in by the drive's mount unit `drive_unit`, even when
+/// the drive is mounted outside systemd, and is stopped along with it.
+pub fn render_bind_unit(target: &Path, compatdata: &Path, drive_unit: &str) -> String {
+    format!(
+        "{BIND_UNIT_MARKER}\n\
+         [Unit]\n\
+         Description=Proton prefixes of {compatdata}\n\
+         BindsTo={drive_unit}\n\
+         After={drive_unit}\n\
+         \n\
+         [Mount]\n\
+         What={target}\n\
+         Where={compatdata}\n\
+         Type=none\n\
+         Options=bind\n\
+         \n\
+         [Install]\n\
+         WantedBy={drive_unit}\n",
+        compatdata = escape_specifiers(&compatdata.to_string_lossy()),
+        target = escape_specifiers(&target.to_string_lossy()),
+    )
+}
+
+/// Lists the prefix bind mount units in `unit_dir` that are bound to the
+/// drive mount unit `drive_unit`.
+///
+/// Only files carrying [`BIND_UNIT_MARKER`] are considered. A missing
+/// directory yields an empty list.
+pub fn list_bind_units(unit_dir: &Path, drive_unit: &str) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(unit_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(Error::MountUnitRead {
+                path: unit_dir.to_path_buf(),
+                source: e,
+            });
+        }
+    };
+
+    let binds_to = format!("BindsTo={}", drive_unit);
+    let mut units: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "mount") && path.is_file())
+        .filter(|path| {
+            fs::read_to_string(path).is_ok_and(|content| {
+                content.lines().any(|l| l.trim() == BIND_UNIT_MARKER)
+                    && content.lines().any(|l| l.trim() == binds_to)
+            })
+        })
+        .collect();
+    units.sort();
+    Ok(units)
+}
+
+/// Disables, stops and deletes the prefix bind mount units bound to the
+/// drive mounted at `mount_point`, then reloads systemd.
+///
+/// Used when the drive is deconfigured, so no unit is left pointing at a
+/// mount point that no longer exists.
+pub fn remove_bind_units_with_ctx(
+    mount_point: &Path,
+    unit_dir: &Path,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let drive_unit = syscall::mount_point_to_unit_name(mount_point)?;
+    let units = list_bind_units(unit_dir, &drive_unit)?;
+    if units.is_empty() {
+        return Ok(());
+    }
+
+    for path in &units {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        syscall::disable_unit_with_ctx(name, ctx)?;
+        syscall::stop_unit_with_ctx(name, ctx)?;
+        ctx.run_privileged_checked("rm", &[&path.display().to_string()])?;
+    }
+    syscall::daemon_reload_with_ctx(ctx)
+}
+
+/// Moves the entries of a compatdata directory into `target`, then removes
+/// the emptied directory.
+fn migrate_prefixes(compatdata: &Path, target: &Path) -> std::io::Result<()> {
+    let mut items = Vec::new();
+    for entry in fs::read_dir(compatdata)? {
+        let entry = entry?;
+        let dst = target.join(entry.file_name());
+        if dst.symlink_metadata().is_ok() {
+            let mut replaced = dst.clone().into_os_string();
+            replaced.push(".replaced");
+            fs::rename(&dst, replaced)?;
+        }
+        items.push((entry.path(), dst));
+    }
+
+    let mut progress = MoveProgress {
+        bytes_done: 0,
+        bytes_total: 0,
+    };
+    copy_items(&items, &mut progress, &mut |_| {})?;
+    remove_tree(compatdata)
+}
+
+/// Returns how `compatdata` is redirected to `target`, if it is.
+fn current_redirection(compatdata: &Path, target: &Path) -> Option<Redirection> {
+    let metadata = fs::symlink_metadata(compatdata).ok()?;
+    if metadata.is_symlink() {
+        let resolved = fs::canonicalize(compatdata).ok()?;
+        return (Some(resolved) == fs::canonicalize(target).ok()).then_some(Redirection::Symlink);
+    }
+    (sysfs::is_mount_point(compatdata) && fs::canonicalize(target).is_ok())
+        .then_some(Redirection::BindMount)
+}
+
+/// Returns the app IDs of the prefixes in a compatdata directory.
+fn prefix_appids(compatdata: &Path) -> Vec<u32> {
+    let mut appids: Vec<u32> = fs::read_dir(compatdata)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    appids.sort_unstable();
+    appids
+}
+
+/// Returns true for errors meaning the filesystem cannot hold symlinks.
+fn is_unsupported(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::PermissionDenied | ErrorKind::Unsupported
+    ) || error.raw_os_error() == Some(nix::libc::EOPNOTSUPP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_compatdata() {
+        assert!(!is_posix_filesystem("exfat"));
+        assert!(is_posix_filesystem("ext4"));
+        assert_eq!(
+            target_dir(Path::new("/data"), Path::new("/run/media/deck/my-games")).unwrap(),
+            PathBuf::from("/data/run-media-deck-my\\x2dgames")
+        );
+        // Not mangled into the same directory as my-games
+        assert_ne!(
+            target_dir(Path::new("/data"), Path::new("/run/media/deck/my_games")).unwrap(),
+            target_dir(Path::new("/data"), Path::new("/run/media/deck/my-games")).unwrap()
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("library");
+        let target_root = dir.path().join("internal");
+        let prefix = library.join("steamapps/compatdata/1145360/pfx");
+        fs::create_dir_all(&prefix).unwrap();
+        fs::write(prefix.join("user.reg"), "registry").unwrap();
+
+        let status = check_library(&library, "exfat", &target_root).unwrap();
+        assert!(status.is_broken());
+        assert_eq!(status.affected_appids, vec![1145360]);
+
+        // Existing prefixes are migrated behind a symlink
+        let target = target_dir(&target_root, &library).unwrap();
+        let mut ctx = ExecutionContext::default();
+        redirect(&library, &target, &mut ctx).unwrap();
+        let status = check_library(&library, "exfat", &target_root).unwrap();
+        assert_eq!(status.redirection, Some(Redirection::Symlink));
+        assert!(status.affected_appids.is_empty());
+        assert!(target.join("1145360/pfx/user.reg").is_file());
+
+        // A link to the wrong place is repaired
+        let compatdata = library.join("steamapps/compatdata");
+        fs::remove_file(&compatdata).unwrap();
+        std::os::unix::fs::symlink("/nonexistent", &compatdata).unwrap();
+        assert!(
+            check_library(&library, "ntfs3", &target_root)
+                .unwrap()
+                .is_broken()
+        );
+        redirect(&library, &target, &mut ctx).unwrap();
+        assert_eq!(fs::read_link(&compatdata).unwrap(), target);
+    }
+
+    #[test]
+    fn test_render_bind_unit() {
+        let compatdata = Path::new("/home/deck/Drives/Games/SteamLibrary/steamapps/compatdata");
+        let target = Path::new("/home/deck/.local/share/steamos-mount/compatdata/100%");
+        let unit = render_bind_unit(target, compatdata, "home-deck-Drives-Games.mount");
+
+        assert!(unit.contains("BindsTo=home-deck-Drives-Games.mount\n"));
+        assert!(unit.contains("After=home-deck-Drives-Games.mount\n"));
+        assert!(unit.contains("\nWantedBy=home-deck-Drives-Games.mount\n"));
+        assert!(unit.contains("What=/home/deck/.local/share/steamos-mount/compatdata/100%%\n"));
+        assert!(unit.contains(&format!("Where={}\n", compatdata.display())));
+        assert!(unit.contains("Type=none\nOptions=bind\n"));
+        // Not a drive configuration
+        assert!(
+            mount_unit::MountUnit::parse_mount_unit(&unit)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_list_bind_units() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = Path::new("/home/deck/.local/share/steamos-mount/compatdata/games");
+        let bind = |drive: &str, library: &str| {
+            let compatdata = Path::new(drive).join(library).join("steamapps/compatdata");
+            let drive_unit = syscall::mount_point_to_unit_name(Path::new(drive)).unwrap();
+            let name = syscall::mount_point_to_unit_name(&compatdata).unwrap();
+            let content = render_bind_unit(target, &compatdata, &drive_unit);
+            fs::write(dir.path().join(&name), &content).unwrap();
+            (name, content)
+        };
+        let (games, _) = bind("/run/media/deck/Games", "SteamLibrary");
+        bind("/run/media/deck/Other", "SteamLibrary");
+        // Bound to the same drive, but not written by this tool
+        let (foreign, content) = bind("/run/media/deck/Games", "Foreign");
+        fs::write(
+            dir.path().join(foreign),
+            content.replace(BIND_UNIT_MARKER, ""),
+        )
+        .unwrap();
+
+        let drive_unit =
+            syscall::mount_point_to_unit_name(Path::new("/run/media/deck/Games")).unwrap();
+        assert_eq!(
+            list_bind_units(dir.path(), &drive_unit).unwrap(),
+            vec![dir.path().join(games)]
+        );
+        assert!(
+            list_bind_units(&dir.path().join("missing"), &drive_unit)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Steam client process detection through `/proc`.
+//!
+//! The client's main process is named `steam` in every packaging. Flatpak
+//! processes are told apart by their cgroup, since their executable path is
+//! only meaningful inside the sandbox. In Game Mode the client runs below
+//! `gamescope-session`, which starts it again whenever it exits.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::{FLATPAK_APP_ID, SteamFlavour};
+use crate::preset;
+
+/// Default path to the proc filesystem.
+pub const PROC_PATH: &str = "/proc";
+
+/// How long a Steam shutdown may take by default.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between checks while waiting for processes to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Ancestors checked for a session supervisor before giving up.
+const MAX_ANCESTORS: usize = 32;
+
+/// The session a Steam client runs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SteamSession {
+    /// Started from a desktop session; nothing restarts it.
+    Desktop,
+    /// Supervised by gamescope-session, which restarts it on exit.
+    GameMode,
+}
+
+/// A running Steam client process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SteamProcess {
+    /// Process ID.
+    pub pid: u32,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Executable path, as seen from the process's own mount namespace.
+    pub exe: Option<PathBuf>,
+    /// How the client is packaged.
+    pub flavour: SteamFlavour,
+    /// The session the client runs in.
+    pub session: SteamSession,
+    /// Start time in clock ticks since boot, to tell a reused PID apart.
+    pub start_time: u64,
+}
+
+impl SteamProcess {
+    /// Returns true if the session supervisor restarts the client on exit.
+    pub fn auto_restarts(&self) -> bool {
+        self.session == SteamSession::GameMode
+    }
+
+    /// Returns true if the process runs the installation at `root`.
+    pub fn belongs_to(&self, root: &Path, flavour: SteamFlavour) -> bool {
+        match flavour {
+            SteamFlavour::Flatpak => self.flavour == SteamFlavour::Flatpak,
+            SteamFlavour::Native => {
+                self.flavour == SteamFlavour::Native
+                    && self.exe.as_ref().is_some_and(|exe| exe.starts_with(root))
+            }
+        }
+    }
+
+    /// Checks whether the process is still running.
+    pub fn is_alive(&self) -> bool {
+        self.is_alive_in(Path::new(PROC_PATH))
+    }
+
+    fn is_alive_in(&self, proc_root: &Path) -> bool {
+        read_stat(&proc_root.join(self.pid.to_string()))
+            .is_some_and(|stat| stat.start_time == self.start_time)
+    }
+}
+
+/// Result of shutting down Steam.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SteamShutdown {
+    /// PIDs of the client processes that exited.
+    pub pids: Vec<u32>,
+    /// Whether the session supervisor will start Steam again by itself.
+    pub auto_restart: bool,
+}
+
+/// Finds the Steam client processes of all users.
+pub fn scan_steam_processes() -> Vec<SteamProcess> {
+    scan_steam_processes_in(Path::new(PROC_PATH))
+}
+
+/// Finds the Steam client processes of the invoking user.
+///
+/// Run through `sudo` or `pkexec`, these are the processes of the user who
+/// ran it (see [`preset::invoking_uid`]).
+pub fn user_steam_processes() -> Vec<SteamProcess> {
+    let uid = preset::invoking_uid();
+    scan_steam_processes()
+        .into_iter()
+        .filter(|process| process.uid == uid)
+        .collect()
+}
+
+/// Finds the Steam client processes in a proc filesystem at `proc_root`.
+pub fn scan_steam_processes_in(proc_root: &Path) -> Vec<SteamProcess> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+
+    let mut processes: Vec<SteamProcess> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let pid_dir = entry.path();
+            if read_comm(&pid_dir)? != "steam" {
+                return None;
+            }
+            let stat = read_stat(&pid_dir)?;
+
+            Some(SteamProcess {
+                pid,
+                uid: read_uid(&pid_dir)?,
+                exe: fs::read_link(pid_dir.join("exe")).ok(),
+                flavour: detect_flavour(&pid_dir),
+                session: detect_session(proc_root, stat.ppid),
+                start_time: stat.start_time,
+            })
+        })
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+/// Waits until all processes have exited.
+///
+/// Returns false if some are still running after `timeout`.
+pub fn wait_for_exit(processes: &[SteamProcess], timeout: Duration) -> bool {
+    wait_for_exit_in(Path::new(PROC_PATH), processes, timeout)
+}
+
+fn wait_for_exit_in(proc_root: &Path, processes: &[SteamProcess], timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !processes.iter().any(|p| p.is_alive_in(proc_root)) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Fields of `/proc/<pid>/stat` used here.
+struct Stat {
+    ppid: u32,
+    start_time: u64,
+}
+
+/// Parses `/proc/<pid>/stat`.
+///
+/// The command name is skipped up to its last `)`, as it may contain spaces
+/// and parentheses.
+fn read_stat(pid_dir: &Path) -> Option<Stat> {
+    let content = fs::read_to_string(pid_dir.join("stat")).ok()?;
+    let (_, rest) = content.rsplit_once(')')?;
+    // Fields after the name start at field 3 (state)
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    Some(Stat {
+        ppid: fields.get(1)?.parse().ok()?,
+        start_time: fields.get(19)?.parse().ok()?,
+    })
+}
+
+fn read_comm(pid_dir: &Path) -> Option<String> {
+    fs::read_to_string(pid_dir.join("comm"))
+        .ok()
+        .map(|comm| comm.trim_end().to_string())
+}
+
+/// Reads the real user ID from `/proc/<pid>/status`.
+fn read_uid(pid_dir: &Path) -> Option<u32> {
+    let status = fs::read_to_string(pid_dir.join("status")).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Flatpak runs each app in a `app-flatpak-<app id>-<n>.scope` cgroup.
+fn detect_flavour(pid_dir: &Path) -> SteamFlavour {
+    let in_flatpak = fs::read_to_string(pid_dir.join("cgroup"))
+        .is_ok_and(|cgroup| cgroup.contains(&format!("app-flatpak-{}", FLATPAK_APP_ID)));
+    if in_flatpak {
+        SteamFlavour::Flatpak
+    } else {
+        SteamFlavour::Native
+    }
+}
+
+/// Walks up the process tree looking for a gamescope session.
+fn detect_session(proc_root: &Path, mut ppid: u32) -> SteamSession {
+    for _ in 0..MAX_ANCESTORS {
+        if ppid <= 1 {
+            break;
+        }
+        let pid_dir = proc_root.join(ppid.to_string());
+        // comm is truncated to 15 characters ("gamescope-sessi")
+        if read_comm(&pid_dir).is_some_and(|comm| comm.starts_with("gamescope")) {
+            return SteamSession::GameMode;
+        }
+        match read_stat(&pid_dir) {
+            Some(stat) => ppid = stat.ppid,
+            None => break,
+        }
+    }
+    SteamSession::Desktop
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_process(proc_root: &Path, pid: u32, ppid: u32, comm: &str, cgroup: &str) {
+        let pid_dir = proc_root.join(pid.to_string());
+        fs::create_dir_all(&pid_dir).unwrap();
+        fs::write(pid_dir.join("comm"), format!("{}\n", comm)).unwrap();
+        let stat = format!(
+            "{} ({}) S {} 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 {} 0 0",
+            pid,
+            comm,
+            ppid,
+            pid * 100
+        );
+        fs::write(pid_dir.join("stat"), stat).unwrap();
+        fs::write(
+            pid_dir.join("status"),
+            "Name:\tx\nUid:\t1000\t1000\t1000\t1000\n",
+        )
+        .unwrap();
+        fs::write(pid_dir.join("cgroup"), cgroup).unwrap();
+    }
+
+    #[test]
+    fn test_scan_steam_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let proc_root = dir.path();
+        add_process(proc_root, 10, 1, "gamescope-sessi", "0::/user.slice\n");
+        add_process(proc_root, 20, 10, "steam", "0::/user.slice\n");
+        add_process(proc_root, 30, 1, "plasmashell", "0::/user.slice\n");
+        let flatpak = "0::/user.slice/app-flatpak-com.valvesoftware.Steam-1234.scope\n";
+        add_process(proc_root, 40, 30, "steam", flatpak);
+        add_process(proc_root, 50, 40, "steamwebhelper", flatpak);
+        std::os::unix::fs::symlink(
+            "/home/deck/.local/share/Steam/ubuntu12_32/steam",
+            proc_root.join("20/exe"),
+        )
+        .unwrap();
+
+        let processes = scan_steam_processes_in(proc_root);
+        assert_eq!(processes.len(), 2);
+
+        let game_mode = &processes[0];
+        assert_eq!(game_mode.pid, 20);
+        assert_eq!(game_mode.uid, 1000);
+        assert_eq!(game_mode.flavour, SteamFlavour::Native);
+        assert!(game_mode.auto_restarts());
+        assert!(game_mode.belongs_to(
+            Path::new("/home/deck/.local/share/Steam"),
+            SteamFlavour::Native
+        ));
+
+        let desktop = &processes[1];
+        assert_eq!(desktop.flavour, SteamFlavour::Flatpak);
+        assert_eq!(desktop.session, SteamSession::Desktop);
+        assert!(!desktop.belongs_to(Path::new("/"), SteamFlavour::Native));
+
+        // A reused PID does not count as the same process
+        assert!(!wait_for_exit_in(proc_root, &processes, Duration::ZERO));
+        fs::remove_dir_all(proc_root.join("20")).unwrap();
+        fs::write(
+            proc_root.join("40/stat"),
+            "40 (bash) S 1 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 9999 0 0",
+        )
+        .unwrap();
+        assert!(wait_for_exit_in(proc_root, &processes, Duration::ZERO));
+    }
+}
@@ -0,0 +1,186 @@
+//! Bringing Steam back after editing its configuration.
+//!
+//! Steam rewrites libraryfolders.vdf on exit, so edits are made while the
+//! client is stopped. How it comes back depends on the session: in Desktop
+//! Mode it is simply launched again, while in Game Mode gamescope-session
+//! starts a new client by itself and launching one would run a second
+//! client outside the session.
+
+use serde::{Deserialize, Serialize};
+
+use super::{SteamInstall, SteamProcess, shutdown_steam_install};
+use crate::error::{Error, Result};
+use crate::executor::ExecutionContext;
+use crate::session::{self, SessionKind};
+use crate::syscall;
+
+/// How Steam is started again after it was stopped for an edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RestartStrategy {
+    /// Launch the client again (Desktop Mode).
+    Relaunch,
+    /// Leave it to the session supervisor, which restarts the client on exit
+    /// (Game Mode under gamescope-session).
+    Supervisor,
+    /// Restart the display manager, which brings up a new session.
+    RestartDisplayManager,
+    /// Leave Steam stopped; the user starts it again.
+    AskUser,
+}
+
+impl RestartStrategy {
+    /// Picks a strategy for the running client processes in a session.
+    ///
+    /// A client started by gamescope-session comes back by itself. A Game
+    /// Mode client without a supervisor takes the session down when it
+    /// exits, so the display manager is restarted.
+    pub fn select(processes: &[SteamProcess], session: SessionKind) -> Self {
+        if processes.iter().any(SteamProcess::auto_restarts) {
+            Self::Supervisor
+        } else if session == SessionKind::GameMode {
+            Self::RestartDisplayManager
+        } else {
+            Self::Relaunch
+        }
+    }
+
+    /// Picks a strategy for an installation in the current session.
+    pub fn detect(install: &SteamInstall) -> Self {
+        let session = session::detect_session()
+            .map(|info| info.kind)
+            .unwrap_or(SessionKind::Unknown);
+        Self::select(&install.processes(), session)
+    }
+
+    /// Starts the client of `install` again according to this strategy.
+    ///
+    /// Restarting the display manager needs root and goes through the
+    /// privileged context.
+    pub fn restart_with_ctx(
+        self,
+        install: &SteamInstall,
+        ctx: &mut ExecutionContext,
+    ) -> Result<()> {
+        match self {
+            Self::Relaunch => {
+                install
+                    .command()
+                    .spawn()
+                    .map(|_| ())
+                    .map_err(|e| Error::SteamProcess {
+                        message: format!("failed to restart Steam: {}", e),
+                    })
+            }
+            Self::RestartDisplayManager => syscall::restart_sddm_with_ctx(ctx),
+            Self::Supervisor | Self::AskUser => Ok(()),
+        }
+    }
+}
+
+/// Runs `edit` while the client of `install` is stopped, then restarts it
+/// with `strategy`.
+///
+/// Steam rewrites libraryfolders.vdf and config.vdf on exit, so changes made
+/// while it runs are lost. In Game Mode gamescope-session starts a new
+/// client shortly after the old one exits; the edit is refused if that
+/// already happened, and [`super::save_vdf`] refuses to save once it does.
+///
+/// Nothing is stopped or restarted if the client is not running. Steam is
+/// restarted even if `edit` fails.
+pub fn edit_while_stopped_with_ctx<T>(
+    install: &SteamInstall,
+    strategy: RestartStrategy,
+    ctx: &mut ExecutionContext,
+    edit: impl FnOnce(&mut ExecutionContext) -> Result<T>,
+) -> Result<T> {
+    if !install.check_running() {
+        return edit(ctx);
+    }
+
+    shutdown_steam_install(install)?;
+    let edited = ensure_stopped(&install.processes()).and_then(|()| edit(ctx));
+    strategy.restart_with_ctx(install, ctx)?;
+    edited
+}
+
+/// Fails if any of `processes`, the clients of an installation, is running.
+///
+/// Checked right before Steam's configuration is written, as a client that
+/// loaded it earlier writes its own copy back on exit.
+pub fn ensure_stopped(processes: &[SteamProcess]) -> Result<()> {
+    let message = if processes.iter().any(SteamProcess::auto_restarts) {
+        "the Game Mode session restarted Steam before the change was saved; \
+         nothing was changed. Switch to Desktop Mode and try again"
+    } else if !processes.is_empty() {
+        "Steam is running and would overwrite the change; nothing was changed"
+    } else {
+        return Ok(());
+    };
+    Err(Error::SteamProcess {
+        message: message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steam::{SteamFlavour, SteamSession};
+
+    #[test]
+    fn test_select_restart_strategy() {
+        let mut process = SteamProcess {
+            pid: 1234,
+            uid: 1000,
+            exe: None,
+            flavour: SteamFlavour::Native,
+            session: SteamSession::GameMode,
+            start_time: 1,
+        };
+        let processes = std::slice::from_ref(&process);
+        assert_eq!(
+            RestartStrategy::select(processes, SessionKind::GameMode),
+            RestartStrategy::Supervisor
+        );
+
+        process.session = SteamSession::Desktop;
+        let processes = std::slice::from_ref(&process);
+        assert_eq!(
+            RestartStrategy::select(processes, SessionKind::GameMode),
+            RestartStrategy::RestartDisplayManager
+        );
+        assert_eq!(
+            RestartStrategy::select(processes, SessionKind::Desktop),
+            RestartStrategy::Relaunch
+        );
+        assert_eq!(
+            RestartStrategy::select(&[], SessionKind::Unknown),
+            RestartStrategy::Relaunch
+        );
+    }
+
+    #[test]
+    fn test_game_mode_restart_aborts_edit() {
+        let restarted = SteamProcess {
+            pid: 4321,
+            uid: 1000,
+            exe: None,
+            flavour: SteamFlavour::Native,
+            session: SteamSession::GameMode,
+            start_time: 2,
+        };
+        // The supervisor is left to restart Steam in Game Mode...
+        assert_eq!(
+            RestartStrategy::select(std::slice::from_ref(&restarted), SessionKind::GameMode),
+            RestartStrategy::Supervisor
+        );
+        // ...and a client it started before the save aborts the edit
+        let error = ensure_stopped(std::slice::from_ref(&restarted)).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Game Mode session restarted Steam")
+        );
+        assert!(ensure_stopped(&[]).is_ok());
+    }
+}
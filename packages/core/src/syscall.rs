@@ -2,85 +2,255 @@
 //!
 //! This module provides functions to interact with systemd for reloading
 //! the daemon, managing mount units, and restarting the display manager.
+//!
+//! Unit operations go through systemd's D-Bus API (see [`crate::systemd_dbus`])
+//! and fall back to spawning `systemctl` when the system bus is unavailable.
 
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::error::{Error, IoResultExt, Result};
-use crate::executor::ExecutionContext;
+use crate::executor::{ExecutionContext, PrivilegeEscalation};
+use crate::systemd_dbus::SystemdManager;
+
+/// State of a systemd unit after an operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitStatus {
+    /// Primary unit name (e.g., "home-deck-Drives-GamesSSD.mount").
+    pub id: String,
+    /// Load state (e.g., "loaded", "not-found").
+    pub load_state: String,
+    /// Active state (e.g., "active", "inactive", "failed").
+    pub active_state: String,
+    /// Type-specific sub state (e.g., "mounted", "running", "dead").
+    pub sub_state: String,
+    /// The unit's `Result` property (e.g., "success", "exit-code", "timeout").
+    ///
+    /// None for unit types without a `Result` property (targets, devices, ...).
+    pub result: Option<String>,
+}
+
+impl UnitStatus {
+    /// Returns true if the unit is in "active" state.
+    pub fn is_active(&self) -> bool {
+        self.active_state == "active"
+    }
+
+    /// Returns true if the unit is in "failed" state.
+    pub fn is_failed(&self) -> bool {
+        self.active_state == "failed"
+    }
+
+    /// Parses the output of `systemctl show --property=Id,LoadState,ActiveState,SubState,Result`.
+    pub fn from_show_output(output: &str) -> Self {
+        let mut status = Self::default();
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Id" => status.id = value,
+                "LoadState" => status.load_state = value,
+                "ActiveState" => status.active_state = value,
+                "SubState" => status.sub_state = value,
+                "Result" if !value.is_empty() => status.result = Some(value),
+                _ => {}
+            }
+        }
+        status
+    }
+
+    /// Converts a failed unit into an error carrying its `Result`.
+    fn ensure_not_failed(self, operation: &str) -> Result<Self> {
+        if self.is_failed() {
+            return Err(Error::Systemd {
+                message: format!(
+                    "{} {} failed: {} ({}), result: {}",
+                    operation,
+                    self.id,
+                    self.active_state,
+                    self.sub_state,
+                    self.result.as_deref().unwrap_or("unknown")
+                ),
+            });
+        }
+        Ok(self)
+    }
+}
 
 /// Reloads the systemd daemon to pick up configuration changes.
 ///
 /// This is equivalent to running `systemctl daemon-reload`.
 pub fn daemon_reload() -> Result<()> {
-    run_systemctl(&["daemon-reload"])
+    with_dbus_fallback(
+        |manager| manager.reload(),
+        || run_systemctl(&["daemon-reload"]),
+    )
 }
 
 /// Reloads the systemd daemon with privilege escalation support.
 pub fn daemon_reload_with_ctx(ctx: &mut ExecutionContext) -> Result<()> {
+    if ctx.escalation() == PrivilegeEscalation::None {
+        return daemon_reload();
+    }
     run_systemctl_with_ctx(&["daemon-reload"], ctx)
 }
 
-/// Starts a systemd mount unit.
+/// Starts a systemd mount unit and waits for the start job to finish.
 ///
 /// # Arguments
 /// * `unit_name` - The name of the mount unit (e.g., "home-deck-Drives-GamesSSD.mount")
-pub fn start_unit(unit_name: &str) -> Result<()> {
-    run_systemctl(&["start", unit_name])
+pub fn start_unit(unit_name: &str) -> Result<UnitStatus> {
+    with_dbus_fallback(
+        |manager| manager.start_unit(unit_name),
+        || {
+            run_systemctl(&["start", unit_name])?;
+            unit_status(unit_name)
+        },
+    )?
+    .ensure_not_failed("start")
 }
 
 /// Starts a systemd mount unit with privilege escalation support.
-pub fn start_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<()> {
-    run_systemctl_with_ctx(&["start", unit_name], ctx)
+pub fn start_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<UnitStatus> {
+    if ctx.escalation() == PrivilegeEscalation::None {
+        return start_unit(unit_name);
+    }
+    run_systemctl_with_ctx(&["start", unit_name], ctx)?;
+    unit_status(unit_name)?.ensure_not_failed("start")
 }
 
-/// Stops a systemd mount unit.
+/// Stops a systemd mount unit and waits for the stop job to finish.
 ///
 /// # Arguments
 /// * `unit_name` - The name of the mount unit
-pub fn stop_unit(unit_name: &str) -> Result<()> {
-    run_systemctl(&["stop", unit_name])
+pub fn stop_unit(unit_name: &str) -> Result<UnitStatus> {
+    with_dbus_fallback(
+        |manager| manager.stop_unit(unit_name),
+        || {
+            run_systemctl(&["stop", unit_name])?;
+            unit_status(unit_name)
+        },
+    )
 }
 
 /// Stops a systemd mount unit with privilege escalation support.
-pub fn stop_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<()> {
-    run_systemctl_with_ctx(&["stop", unit_name], ctx)
+pub fn stop_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<UnitStatus> {
+    if ctx.escalation() == PrivilegeEscalation::None {
+        return stop_unit(unit_name);
+    }
+    run_systemctl_with_ctx(&["stop", unit_name], ctx)?;
+    unit_status(unit_name)
 }
 
-/// Restarts a systemd unit.
+/// Restarts a systemd unit and waits for the restart job to finish.
 ///
 /// # Arguments
 /// * `unit_name` - The name of the unit to restart
-pub fn restart_unit(unit_name: &str) -> Result<()> {
-    run_systemctl(&["restart", unit_name])
+pub fn restart_unit(unit_name: &str) -> Result<UnitStatus> {
+    with_dbus_fallback(
+        |manager| manager.restart_unit(unit_name),
+        || {
+            run_systemctl(&["restart", unit_name])?;
+            unit_status(unit_name)
+        },
+    )?
+    .ensure_not_failed("restart")
 }
 
 /// Restarts a systemd unit with privilege escalation support.
-pub fn restart_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<()> {
-    run_systemctl_with_ctx(&["restart", unit_name], ctx)
+pub fn restart_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<UnitStatus> {
+    if ctx.escalation() == PrivilegeEscalation::None {
+        return restart_unit(unit_name);
+    }
+    run_systemctl_with_ctx(&["restart", unit_name], ctx)?;
+    unit_status(unit_name)?.ensure_not_failed("restart")
+}
+
+/// Enables a unit so that it is started at boot.
+///
+/// This is equivalent to running `systemctl enable <unit>`.
+pub fn enable_unit(unit_name: &str) -> Result<()> {
+    with_dbus_fallback(
+        |manager| manager.enable_unit(unit_name),
+        || run_systemctl(&["enable", unit_name]),
+    )
+}
+
+/// Enables a unit with privilege escalation support.
+pub fn enable_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<()> {
+    if ctx.escalation() == PrivilegeEscalation::None {
+        return enable_unit(unit_name);
+    }
+    run_systemctl_with_ctx(&["enable", unit_name], ctx)
+}
+
+/// Disables a unit so that it is no longer started at boot.
+///
+/// This is equivalent to running `systemctl disable <unit>`.
+pub fn disable_unit(unit_name: &str) -> Result<()> {
+    with_dbus_fallback(
+        |manager| manager.disable_unit(unit_name),
+        || run_systemctl(&["disable", unit_name]),
+    )
+}
+
+/// Disables a unit with privilege escalation support.
+pub fn disable_unit_with_ctx(unit_name: &str, ctx: &mut ExecutionContext) -> Result<()> {
+    if ctx.escalation() == PrivilegeEscalation::None {
+        return disable_unit(unit_name);
+    }
+    run_systemctl_with_ctx(&["disable", unit_name], ctx)
+}
+
+/// Queries the current state of a unit.
+///
+/// Reading unit properties does not require privileges.
+pub fn unit_status(unit_name: &str) -> Result<UnitStatus> {
+    with_dbus_fallback(
+        |manager| manager.unit_status(unit_name),
+        || {
+            let output = Command::new("systemctl")
+                .args([
+                    "show",
+                    "--property=Id,LoadState,ActiveState,SubState,Result",
+                    unit_name,
+                ])
+                .output()
+                .command_context(format!("systemctl show {}", unit_name))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                return Err(Error::Systemd { message: stderr });
+            }
+
+            Ok(UnitStatus::from_show_output(&String::from_utf8_lossy(
+                &output.stdout,
+            )))
+        },
+    )
 }
 
 /// Checks if a unit is active.
 ///
 /// Returns true if the unit is in "active" state.
 pub fn is_unit_active(unit_name: &str) -> Result<bool> {
-    let output = Command::new("systemctl")
-        .args(["is-active", unit_name])
-        .output()
-        .command_context(format!("systemctl is-active {}", unit_name))?;
-
-    Ok(output.status.success())
+    Ok(unit_status(unit_name)?.is_active())
 }
 
 /// Restarts the SDDM display manager.
 ///
 /// This is used to restart the Steam UI after VDF injection.
 pub fn restart_sddm() -> Result<()> {
-    run_systemctl(&["restart", "sddm"])
+    restart_unit("sddm.service").map(|_| ())
 }
 
 /// Restarts the SDDM display manager with privilege escalation support.
 pub fn restart_sddm_with_ctx(ctx: &mut ExecutionContext) -> Result<()> {
-    run_systemctl_with_ctx(&["restart", "sddm"], ctx)
+    restart_unit_with_ctx("sddm.service", ctx).map(|_| ())
 }
 
 /// Runs `steamos-session-select` to switch session.
@@ -113,6 +283,33 @@ pub fn switch_to_game_mode() -> Result<()> {
     session_select("gamescope")
 }
 
+/// Runs an operation through the D-Bus backend, falling back to `systemctl`.
+///
+/// The fallback is only used when connecting to systemd fails (no system
+/// bus, access denied, ...). Once connected, every error is returned as-is:
+/// a method may already have queued a job, and running the operation again
+/// would repeat it.
+fn with_dbus_fallback<T>(
+    dbus: impl FnOnce(&SystemdManager) -> Result<T>,
+    fallback: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    with_fallback(SystemdManager::system, dbus, fallback)
+}
+
+/// Connects with `connect` and runs `operation`, or runs `fallback` if
+/// connecting fails with a D-Bus error.
+fn with_fallback<M, T>(
+    connect: impl FnOnce() -> Result<M>,
+    operation: impl FnOnce(&M) -> Result<T>,
+    fallback: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    match connect() {
+        Ok(manager) => operation(&manager),
+        Err(Error::DBus { .. }) => fallback(),
+        Err(e) => Err(e),
+    }
+}
+
 /// Helper function to run systemctl commands.
 fn run_systemctl(args: &[&str]) -> Result<()> {
     let output = Command::new("systemctl")
@@ -149,60 +346,374 @@ fn run_systemctl_with_ctx(args: &[&str], ctx: &mut ExecutionContext) -> Result<(
     Ok(())
 }
 
-/// Converts a mount point path to a systemd unit name.
+/// Maximum length of a unit name (`UNIT_NAME_MAX - 1` in systemd).
+const UNIT_NAME_MAX_LEN: usize = 255;
+
+/// Escapes a path exactly like `systemd-escape --path`.
 ///
-/// Implements systemd path escaping logic:
-/// 1. Removes leading slashes
-/// 2. Replaces slashes with dashes
-/// 3. Escapes other special characters (like spaces and dashes) as \xNN
+/// The path is simplified first (repeated slashes, `.` components and
+/// trailing slashes are dropped), then:
+/// 1. The root directory becomes "-"
+/// 2. Leading and trailing slashes are removed, remaining slashes become dashes
+/// 3. A leading `.` and every byte outside `[A-Za-z0-9:_.]` is escaped as `\xNN`
+///    (multi-byte UTF-8 characters are escaped byte by byte)
 ///
-/// Example: "/home/deck/Drives/GamesSSD" -> "home-deck-Drives-GamesSSD.mount"
-/// Example: "/home/deck/Drives/My Drive" -> "home-deck-Drives-My\x20Drive.mount"
-pub fn mount_point_to_unit_name(mount_point: &std::path::Path) -> String {
-    let path_str = mount_point.to_string_lossy();
-    let trimmed = path_str.trim_start_matches('/');
+/// Paths containing `..` components cannot be escaped.
+///
+/// Example: "/home/deck/Drives/My Drive" -> "home-deck-Drives-My\x20Drive"
+pub fn escape_path(path: &Path) -> Result<String> {
+    let bytes = path.as_os_str().as_bytes();
+    if bytes.is_empty() {
+        return Err(Error::InvalidMountPath {
+            path: path.to_path_buf(),
+            reason: "path is empty".to_string(),
+        });
+    }
 
-    if trimmed.is_empty() {
-        return "-.mount".to_string();
+    let mut components: Vec<&[u8]> = Vec::new();
+    for component in bytes.split(|&b| b == b'/') {
+        match component {
+            b"" | b"." => {}
+            b".." => {
+                return Err(Error::InvalidMountPath {
+                    path: path.to_path_buf(),
+                    reason: "path contains '..' components".to_string(),
+                });
+            }
+            _ => components.push(component),
+        }
+    }
+
+    if components.is_empty() {
+        return Ok("-".to_string());
     }
 
-    let mut escaped = String::with_capacity(trimmed.len());
-    for c in trimmed.chars() {
-        if c == '/' {
+    let joined = components.join(&b'/');
+    let mut escaped = String::with_capacity(joined.len());
+    for (i, &b) in joined.iter().enumerate() {
+        if b == b'/' {
             escaped.push('-');
-        } else if c.is_ascii_alphanumeric() || c == ':' || c == '_' || c == '.' {
-            escaped.push(c);
+        } else if (i == 0 && b == b'.') || !is_valid_unit_char(b) {
+            escaped.push_str(&format!("\\x{:02x}", b));
         } else {
-            escaped.push_str(&format!("\\x{:02x}", c as u32));
+            escaped.push(b as char);
         }
     }
 
-    format!("{}.mount", escaped)
+    Ok(escaped)
+}
+
+/// Reverses [`escape_path`], like `systemd-escape --unescape --path`.
+///
+/// Rejects strings that cannot have been produced by [`escape_path`]
+/// (leading, trailing or repeated dashes, invalid escapes, or results that
+/// are not normalized absolute paths).
+pub fn unescape_path(escaped: &str) -> Result<PathBuf> {
+    let invalid = |reason: &str| Error::InvalidUnitName {
+        name: escaped.to_string(),
+        reason: reason.to_string(),
+    };
+
+    if escaped == "-" {
+        return Ok(PathBuf::from("/"));
+    }
+    if escaped.is_empty() {
+        return Err(invalid("name is empty"));
+    }
+    if escaped.starts_with('-') || escaped.ends_with('-') || escaped.contains("--") {
+        return Err(invalid("name contains empty path components"));
+    }
+
+    let input = escaped.as_bytes();
+    let mut bytes = Vec::with_capacity(input.len() + 1);
+    bytes.push(b'/');
+
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'-' => {
+                bytes.push(b'/');
+                i += 1;
+            }
+            b'\\' => {
+                let hex = input
+                    .get(i + 1..i + 4)
+                    .filter(|h| h[0] == b'x')
+                    .and_then(|h| std::str::from_utf8(&h[1..]).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| invalid("invalid escape sequence"))?;
+                bytes.push(hex);
+                i += 4;
+            }
+            b => {
+                bytes.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    let is_normalized = bytes[1..]
+        .split(|&b| b == b'/')
+        .all(|c| !c.is_empty() && c != b"." && c != b"..");
+    if !is_normalized {
+        return Err(invalid("result is not a normalized path"));
+    }
+
+    Ok(PathBuf::from(OsStr::from_bytes(&bytes)))
+}
+
+/// Converts a mount point path to a systemd mount unit name.
+///
+/// Uses [`escape_path`], so the result matches `systemd-escape --path --suffix=mount`.
+///
+/// Example: "/home/deck/Drives/GamesSSD" -> "home-deck-Drives-GamesSSD.mount"
+/// Example: "/home/deck/Drives/My Drive" -> "home-deck-Drives-My\x20Drive.mount"
+pub fn mount_point_to_unit_name(mount_point: &Path) -> Result<String> {
+    mount_point_unit_name(mount_point, "mount")
+}
+
+/// Converts a mount point path to a systemd automount unit name.
+///
+/// Like [`mount_point_to_unit_name`], with the `.automount` suffix.
+pub fn mount_point_to_automount_unit_name(mount_point: &Path) -> Result<String> {
+    mount_point_unit_name(mount_point, "automount")
+}
+
+/// Builds a unit name for a mount point, enforcing systemd's length limit.
+fn mount_point_unit_name(mount_point: &Path, suffix: &str) -> Result<String> {
+    let name = format!("{}.{}", escape_path(mount_point)?, suffix);
+    if name.len() > UNIT_NAME_MAX_LEN {
+        return Err(Error::InvalidMountPath {
+            path: mount_point.to_path_buf(),
+            reason: format!("unit name exceeds {} characters", UNIT_NAME_MAX_LEN),
+        });
+    }
+    Ok(name)
+}
+
+/// Converts a `.mount` or `.automount` unit name back to its mount point.
+///
+/// Example: "home-deck-Drives-GamesSSD.mount" -> "/home/deck/Drives/GamesSSD"
+pub fn unit_name_to_mount_point(unit_name: &str) -> Result<PathBuf> {
+    let escaped = unit_name
+        .strip_suffix(".mount")
+        .or_else(|| unit_name.strip_suffix(".automount"))
+        .ok_or_else(|| Error::InvalidUnitName {
+            name: unit_name.to_string(),
+            reason: "not a .mount or .automount unit".to_string(),
+        })?;
+
+    if unit_name.len() > UNIT_NAME_MAX_LEN {
+        return Err(Error::InvalidUnitName {
+            name: unit_name.to_string(),
+            reason: format!("unit name exceeds {} characters", UNIT_NAME_MAX_LEN),
+        });
+    }
+
+    unescape_path(escaped)
+}
+
+/// Returns true if a byte may appear unescaped in a unit name.
+fn is_valid_unit_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b':' | b'_' | b'.')
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::Path;
+
+    fn dbus_error(method: &str) -> Error {
+        Error::DBus {
+            method: method.to_string(),
+            source: Box::new(zbus::Error::Failure(
+                "org.freedesktop.systemd1.NoSuchUnit".into(),
+            )),
+        }
+    }
+
+    #[test]
+    fn test_dbus_fallback_only_on_connect() {
+        // Connection failures use systemctl
+        let result = with_fallback(
+            || Err::<(), _>(dbus_error("connect system bus")),
+            |_| Ok("dbus"),
+            || Ok("systemctl"),
+        );
+        assert_eq!(result.unwrap(), "systemctl");
+
+        // Method errors are passed through without running the operation twice
+        let result: Result<&str> = with_fallback(
+            || Ok(()),
+            |_| Err(dbus_error("StopUnit")),
+            || panic!("fallback must not run after a method error"),
+        );
+        assert!(matches!(result, Err(Error::DBus { method, .. }) if method == "StopUnit"));
+    }
 
     #[test]
     fn test_mount_point_to_unit_name() {
         assert_eq!(
-            mount_point_to_unit_name(Path::new("/home/deck/Drives/GamesSSD")),
+            mount_point_to_unit_name(Path::new("/home/deck/Drives/GamesSSD")).unwrap(),
             "home-deck-Drives-GamesSSD.mount"
         );
         assert_eq!(
-            mount_point_to_unit_name(Path::new("/mnt/test")),
+            mount_point_to_unit_name(Path::new("/mnt/test")).unwrap(),
             "mnt-test.mount"
         );
     }
 
+    #[test]
+    fn test_escape_path_systemd_vectors() {
+        // Vectors from systemd's src/test/test-unit-name.c, plus non-ASCII cases
+        let cases: &[(&str, Option<&str>)] = &[
+            ("/waldo", Some("waldo")),
+            ("/waldo/quuix", Some("waldo-quuix")),
+            ("/waldo/quuix/", Some("waldo-quuix")),
+            ("waldo", Some("waldo")),
+            ("waldo/quuix", Some("waldo-quuix")),
+            ("waldo/quuix/", Some("waldo-quuix")),
+            ("/", Some("-")),
+            ("///", Some("-")),
+            ("///.", Some("-")),
+            ("/foo/./bar", Some("foo-bar")),
+            ("//foo//bar//", Some("foo-bar")),
+            ("/foo-bar", Some("foo\\x2dbar")),
+            ("/ab+-c.a-bc", Some("ab\\x2b\\x2dc.a\\x2dbc")),
+            ("/.foo", Some("\\x2efoo")),
+            ("/foo/.bar", Some("foo-.bar")),
+            ("/foo\\bar", Some("foo\\x5cbar")),
+            ("/föö", Some("f\\xc3\\xb6\\xc3\\xb6")),
+            ("/a:b_c", Some("a:b_c")),
+            ("/foo/../bar", None),
+            ("/..", None),
+            ("", None),
+        ];
+
+        for (path, expected) in cases {
+            let result = escape_path(Path::new(path));
+            match expected {
+                Some(expected) => assert_eq!(result.unwrap(), *expected, "escaping {:?}", path),
+                None => assert!(result.is_err(), "escaping {:?} should fail", path),
+            }
+        }
+    }
+
+    #[test]
+    fn test_unescape_path_systemd_vectors() {
+        // Vectors from systemd's src/test/test-unit-name.c, plus escape sequences
+        let cases: &[(&str, Option<&str>)] = &[
+            ("foo", Some("/foo")),
+            ("foo-bar", Some("/foo/bar")),
+            ("foo-.bar", Some("/foo/.bar")),
+            ("foo-bar-baz", Some("/foo/bar/baz")),
+            ("-", Some("/")),
+            ("--", None),
+            ("-foo-bar", None),
+            ("foo--bar", None),
+            ("foo-bar-", None),
+            (".-bar", None),
+            ("foo-..", None),
+            ("", None),
+            ("foo\\x2dbar", Some("/foo-bar")),
+            ("\\x2efoo", Some("/.foo")),
+            ("f\\xc3\\xb6\\xc3\\xb6", Some("/föö")),
+            ("foo\\x2", None),
+            ("foo\\y20bar", None),
+        ];
+
+        for (escaped, expected) in cases {
+            let result = unescape_path(escaped);
+            match expected {
+                Some(expected) => assert_eq!(
+                    result.unwrap(),
+                    Path::new(expected),
+                    "unescaping {:?}",
+                    escaped
+                ),
+                None => assert!(result.is_err(), "unescaping {:?} should fail", escaped),
+            }
+        }
+    }
+
+    #[test]
+    fn test_unit_name_to_mount_point() {
+        assert_eq!(
+            unit_name_to_mount_point("home-lennart.mount").unwrap(),
+            Path::new("/home/lennart")
+        );
+        assert_eq!(
+            unit_name_to_mount_point("home-deck-Drives-SD.automount").unwrap(),
+            Path::new("/home/deck/Drives/SD")
+        );
+        assert_eq!(unit_name_to_mount_point("-.mount").unwrap(), Path::new("/"));
+        assert!(unit_name_to_mount_point("home-lennart-.mount").is_err());
+        assert!(unit_name_to_mount_point("-home-lennart.mount").is_err());
+        assert!(unit_name_to_mount_point("-home--lennart.mount").is_err());
+        assert!(unit_name_to_mount_point("home-..-lennart.mount").is_err());
+        assert!(unit_name_to_mount_point("home.service").is_err());
+    }
+
+    #[test]
+    fn test_unit_name_round_trip() {
+        for path in [
+            "/home/deck/Drives/My Drive",
+            "/run/media/deck/Ünïcødé",
+            "/home/deck/.hidden/a-b",
+            "/mnt/100%",
+        ] {
+            let name = mount_point_to_unit_name(Path::new(path)).unwrap();
+            assert_eq!(unit_name_to_mount_point(&name).unwrap(), Path::new(path));
+        }
+    }
+
+    #[test]
+    fn test_unit_name_too_long() {
+        let path = format!("/{}", "a".repeat(250));
+        assert!(mount_point_to_unit_name(Path::new(&path)).is_err());
+        let path = format!("/{}", "a".repeat(249));
+        assert_eq!(
+            mount_point_to_unit_name(Path::new(&path)).unwrap().len(),
+            255
+        );
+    }
+
+    #[test]
+    fn test_unit_status_from_show_output() {
+        let status = UnitStatus::from_show_output(
+            "Id=home-deck-Drives-Games.mount\nLoadState=loaded\nActiveState=failed\nSubState=failed\nResult=exit-code\n",
+        );
+        assert_eq!(status.id, "home-deck-Drives-Games.mount");
+        assert_eq!(status.load_state, "loaded");
+        assert!(status.is_failed());
+        assert!(!status.is_active());
+        assert_eq!(status.result.as_deref(), Some("exit-code"));
+        assert!(status.ensure_not_failed("start").is_err());
+
+        // Targets have no Result property
+        let status = UnitStatus::from_show_output(
+            "Id=local-fs.target\nActiveState=active\nSubState=active\n",
+        );
+        assert!(status.is_active());
+        assert_eq!(status.result, None);
+    }
+
     #[test]
     fn test_mount_point_to_unit_name_escaped() {
         // "My Drive" -> "My\x20Drive"
         assert_eq!(
-            mount_point_to_unit_name(Path::new("/home/deck/Drives/My Drive")),
+            mount_point_to_unit_name(Path::new("/home/deck/Drives/My Drive")).unwrap(),
             "home-deck-Drives-My\\x20Drive.mount"
         );
     }
+
+    #[test]
+    fn test_automount_unit_name_length() {
+        // 246 characters fit with ".mount" but not with ".automount"
+        let mount_point = PathBuf::from(format!("/{}", "a".repeat(246)));
+        assert!(mount_point_to_unit_name(&mount_point).is_ok());
+        assert!(matches!(
+            mount_point_to_automount_unit_name(&mount_point),
+            Err(Error::InvalidMountPath { .. })
+        ));
+    }
 }
@@ -0,0 +1,781 @@
+//! Block device scanning via sysfs and the udev database.
+//!
+//! This is a pure-Rust alternative to `lsblk` for systems whose util-linux is
+//! too old to support every column [`crate::disk::list_block_devices`] asks
+//! for. It walks `/sys/class/block`, reads filesystem properties from
+//! `/run/udev/data/b<major>:<minor>` and mount points from `/proc/self/mounts`,
+//! and produces the same [`BlockDevice`] records as the lsblk scanner,
+//! including whole-disk filesystems and dm-crypt/LVM/md devices.
+//!
+//! All three roots are configurable so the scanner can be tested against
+//! fixture directories.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use snafu::ResultExt;
+
+use crate::disk::{BlockDevice, DeviceKind};
+use crate::error::{Result, SysfsSnafu};
+
+/// Default sysfs mount point.
+pub const SYS_ROOT: &str = "/sys";
+
+/// Default udev database directory.
+pub const UDEV_DATA_DIR: &str = "/run/udev/data";
+
+/// Default mount table.
+pub const MOUNTS_PATH: &str = "/proc/self/mounts";
+
+/// sysfs reports sizes in 512-byte sectors regardless of the logical block size.
+const SECTOR_SIZE: u64 = 512;
+
+/// Scans block devices from sysfs and the udev database.
+#[derive(Debug, Clone)]
+pub struct SysfsScanner {
+    sys_root: PathBuf,
+    udev_data_dir: PathBuf,
+    mounts_path: PathBuf,
+}
+
+impl Default for SysfsScanner {
+    fn default() -> Self {
+        Self {
+            sys_root: PathBuf::from(SYS_ROOT),
+            udev_data_dir: PathBuf::from(UDEV_DATA_DIR),
+            mounts_path: PathBuf::from(MOUNTS_PATH),
+        }
+    }
+}
+
+impl SysfsScanner {
+    /// Creates a scanner using the system's sysfs, udev database and mount table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sysfs root (the directory containing `class/block`).
+    pub fn with_sys_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sys_root = path.into();
+        self
+    }
+
+    /// Sets the udev database directory.
+    pub fn with_udev_data_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.udev_data_dir = path.into();
+        self
+    }
+
+    /// Sets the mount table to read mount points from.
+    pub fn with_mounts_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.mounts_path = path.into();
+        self
+    }
+
+    /// Lists all devices carrying a filesystem, like [`crate::disk::list_block_devices`].
+    ///
+    /// Includes partitions, dm-crypt and LVM mappings, md arrays, and whole
+    /// disks that udev found a filesystem on. Virtual devices (loop, zram)
+    /// are skipped.
+    pub fn list_block_devices(&self) -> Result<Vec<BlockDevice>> {
+        let class_dir = self.sys_root.join("class/block");
+        let entries = fs::read_dir(&class_dir).context(SysfsSnafu { path: &class_dir })?;
+        let mounts = self.read_mounts();
+
+        let mut devices = Vec::new();
+        for entry in entries {
+            let entry = entry.context(SysfsSnafu { path: &class_dir })?;
+            let Some(mut name) = entry.file_name().to_str().map(|s| s.to_string()) else {
+                continue;
+            };
+
+            let device_dir = entry.path();
+            let resolved =
+                fs::canonicalize(&device_dir).context(SysfsSnafu { path: &device_dir })?;
+            let udev = read_attr(&device_dir, "dev")
+                .map(|dev| self.read_udev_properties(&dev))
+                .unwrap_or_default();
+
+            let Some(device_kind) = device_kind(&device_dir, &resolved, &udev) else {
+                continue;
+            };
+            if device_kind.is_device_mapper() {
+                // lsblk lists device-mapper devices by their mapper name
+                match read_attr(&device_dir, "dm/name") {
+                    Some(dm_name) => name = dm_name,
+                    None => continue,
+                }
+            }
+
+            // Removable, transport and the drive identity are properties of
+            // the physical disk underneath partitions and mapped devices
+            let backing = backing_device(&resolved);
+            let backing_disk = if backing.join("partition").exists() {
+                backing.parent().unwrap_or(&backing)
+            } else {
+                &backing
+            };
+            let disk_udev = read_attr(backing_disk, "dev")
+                .map(|dev| self.read_udev_properties(&dev))
+                .unwrap_or_default();
+            let disk_value =
+                |key: &str| udev_value(&udev, key).or_else(|| udev_value(&disk_udev, key));
+            let queue_attr = |name: &str| {
+                read_attr(&resolved, &format!("queue/{}", name))
+                    .or_else(|| read_attr(backing_disk, &format!("queue/{}", name)))
+            };
+            let removable = read_attr(backing_disk, "removable").as_deref() == Some("1");
+            let path = device_kind.device_path(&name);
+
+            devices.push(BlockDevice {
+                label: udev_value(&udev, "ID_FS_LABEL"),
+                uuid: udev_value(&udev, "ID_FS_UUID"),
+                partuuid: udev_value(&udev, "ID_PART_ENTRY_UUID"),
+                fstype: udev_value(&udev, "ID_FS_TYPE"),
+                mountpoints: mounts.get(&path).cloned().unwrap_or_default(),
+                size: read_attr(&device_dir, "size")
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .map(|sectors| sectors * SECTOR_SIZE)
+                    .unwrap_or(0),
+                rota: queue_attr("rotational").as_deref() == Some("1"),
+                removable,
+                transport: transport_from_sysfs_path(&backing),
+                device_kind,
+                model: disk_value("ID_MODEL")
+                    .map(|v| v.trim().to_string())
+                    .or_else(|| read_attr(backing_disk, "device/model")),
+                serial: disk_value("ID_SERIAL_SHORT"),
+                vendor: disk_value("ID_VENDOR")
+                    .map(|v| v.trim().to_string())
+                    .or_else(|| read_attr(backing_disk, "device/vendor")),
+                partlabel: udev_value(&udev, "ID_PART_ENTRY_NAME"),
+                parttype: udev_value(&udev, "ID_PART_ENTRY_TYPE"),
+                pkname: parent_name(&resolved),
+                wwn: disk_value("ID_WWN_WITH_EXTENSION").or_else(|| disk_value("ID_WWN")),
+                hotplug: removable || is_hotpluggable(&backing),
+                log_sec: queue_attr("logical_block_size").and_then(|v| v.parse().ok()),
+                phy_sec: queue_attr("physical_block_size").and_then(|v| v.parse().ok()),
+                disc_gran: queue_attr("discard_granularity").and_then(|v| v.parse().ok()),
+                holders: holder_names(&resolved),
+                path,
+                name,
+            });
+        }
+
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(devices)
+    }
+
+    /// Reads the udev properties (`E:` lines) of a device, e.g. "8:1".
+    fn read_udev_properties(&self, dev: &str) -> HashMap<String, String> {
+        let path = self.udev_data_dir.join(format!("b{}", dev));
+        fs::read_to_string(path)
+            .map(|content| parse_udev_data(&content))
+            .unwrap_or_default()
+    }
+
+    /// Maps device paths to their mount points, in mount order.
+    fn read_mounts(&self) -> HashMap<PathBuf, Vec<PathBuf>> {
+        let mut mounts: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        let Ok(content) = fs::read_to_string(&self.mounts_path) else {
+            return mounts;
+        };
+
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(source), Some(target)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            mounts
+                .entry(PathBuf::from(unescape_mount_field(source)))
+                .or_default()
+                .push(PathBuf::from(unescape_mount_field(target)));
+        }
+
+        mounts
+    }
+}
+
+/// Parses the property lines (`E:KEY=VALUE`) of a udev database file.
+pub fn parse_udev_data(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("E:"))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Returns a udev property, preferring the unmangled `<KEY>_ENC` variant.
+///
+/// udev replaces unsafe characters in `ID_FS_LABEL` (e.g. spaces become
+/// '_'), while `ID_FS_LABEL_ENC` keeps them as `\xNN` escapes.
+fn udev_value(properties: &HashMap<String, String>, key: &str) -> Option<String> {
+    properties
+        .get(&format!("{}_ENC", key))
+        .map(|v| decode_hex_escapes(v))
+        .or_else(|| properties.get(key).cloned())
+        .filter(|v| !v.is_empty())
+}
+
+/// Decodes `\xNN` escapes as used by udev's `*_ENC` properties.
+fn decode_hex_escapes(value: &str) -> String {
+    let input = value.as_bytes();
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'\\' && input.get(i + 1) == Some(&b'x') {
+            let decoded = input
+                .get(i + 2..i + 4)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            if let Some(b) = decoded {
+                bytes.push(b);
+                i += 4;
+                continue;
+            }
+        }
+        bytes.push(input[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Returns true if `path` is currently a mount point according to `/proc/self/mounts`.
+pub(crate) fn is_mount_point(path: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(MOUNTS_PATH) else {
+        // Cannot tell; let umount decide
+        return true;
+    };
+    let target = path.display().to_string();
+    content.lines().any(|line| {
+        line.split_whitespace()
+            .nth(1)
+            .is_some_and(|mount_point| unescape_mount_field(mount_point) == target)
+    })
+}
+
+/// Returns the type of the filesystem holding `path`, from the longest
+/// matching mount point in `/proc/self/mounts`.
+pub(crate) fn filesystem_type(path: &Path) -> Option<String> {
+    mount_entry_of(path).map(|(_, fstype)| fstype)
+}
+
+/// Returns the mount point of the filesystem holding `path`.
+pub(crate) fn mount_point_of(path: &Path) -> Option<PathBuf> {
+    mount_entry_of(path).map(|(mount_point, _)| mount_point)
+}
+
+/// Returns the longest mount point containing `path` and its filesystem type.
+fn mount_entry_of(path: &Path) -> Option<(PathBuf, String)> {
+    let path = fs::canonicalize(path).ok()?;
+    let content = fs::read_to_string(MOUNTS_PATH).ok()?;
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = PathBuf::from(unescape_mount_field(fields.nth(1)?));
+            let fstype = fields.next()?;
+            path.starts_with(&mount_point)
+                .then(|| (mount_point, fstype.to_string()))
+        })
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+}
+
+/// Returns the mount points below `path`, deepest first.
+///
+/// `path` itself is not included.
+pub(crate) fn nested_mount_points(path: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(MOUNTS_PATH)
+        .map(|content| nested_mount_points_in(&content, path))
+        .unwrap_or_default()
+}
+
+fn nested_mount_points_in(content: &str, path: &Path) -> Vec<PathBuf> {
+    let mut nested: Vec<PathBuf> = content
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|field| PathBuf::from(unescape_mount_field(field)))
+        .filter(|mount_point| mount_point != path && mount_point.starts_with(path))
+        .collect();
+    nested.sort_by_key(|mount_point| std::cmp::Reverse(mount_point.components().count()));
+    nested.dedup();
+    nested
+}
+
+/// Decodes the octal escapes (`\040` for space, ...) of /proc/self/mounts fields.
+pub(crate) fn unescape_mount_field(field: &str) -> String {
+    let input = field.as_bytes();
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'\\' {
+            let decoded = input
+                .get(i + 1..i + 4)
+                .and_then(|o| std::str::from_utf8(o).ok())
+                .and_then(|o| u8::from_str_radix(o, 8).ok());
+            if let Some(b) = decoded {
+                bytes.push(b);
+                i += 4;
+                continue;
+            }
+        }
+        bytes.push(input[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Classifies a sysfs block device. Returns None for devices the lsblk
+/// scanner would skip.
+fn device_kind(
+    device_dir: &Path,
+    resolved: &Path,
+    udev: &HashMap<String, String>,
+) -> Option<DeviceKind> {
+    if device_dir.join("partition").exists() {
+        return Some(DeviceKind::Partition);
+    }
+    if let Some(dm_uuid) = read_attr(device_dir, "dm/uuid") {
+        // dm-crypt and LVM prefix their mapping UUIDs with the subsystem
+        return if dm_uuid.starts_with("CRYPT-") {
+            Some(DeviceKind::Crypt)
+        } else if dm_uuid.starts_with("LVM-") {
+            Some(DeviceKind::Lvm)
+        } else {
+            None
+        };
+    }
+    if device_dir.join("md").is_dir() {
+        return Some(DeviceKind::Raid);
+    }
+
+    // Whole disks only count if they carry a filesystem directly
+    let is_virtual = resolved
+        .components()
+        .any(|c| c == Component::Normal("virtual".as_ref()));
+    (!is_virtual && udev_value(udev, "ID_FS_TYPE").is_some()).then_some(DeviceKind::Disk)
+}
+
+/// Follows `slaves/` links from a mapped device down to the device it is
+/// stacked on (e.g. LUKS volume -> partition). Returns the path itself for
+/// partitions and disks.
+fn backing_device(path: &Path) -> PathBuf {
+    let mut current = path.to_path_buf();
+    // Bounded to guard against malformed trees
+    for _ in 0..8 {
+        let next = fs::read_dir(current.join("slaves"))
+            .ok()
+            .and_then(|mut entries| entries.next())
+            .and_then(|entry| entry.ok())
+            .and_then(|entry| fs::canonicalize(entry.path()).ok());
+        match next {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+    current
+}
+
+/// Returns the kernel name of a device's parent, like lsblk's PKNAME: the
+/// disk of a partition, or the device a mapped device is stacked on.
+fn parent_name(path: &Path) -> Option<String> {
+    let parent = if path.join("partition").exists() {
+        path.parent().map(Path::to_path_buf)
+    } else {
+        fs::read_dir(path.join("slaves"))
+            .ok()?
+            .next()?
+            .ok()
+            .map(|entry| entry.path())
+    }?;
+    parent.file_name()?.to_str().map(|s| s.to_string())
+}
+
+/// Returns the mapper names of the device-mapper devices in `holders/`,
+/// like the crypt/LVM children lsblk lists below a device.
+fn holder_names(path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(path.join("holders")) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| read_attr(&entry.path(), "dm/name"))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Returns true if the device sits behind a hot-pluggable port.
+///
+/// Like lsblk, this walks up the device hierarchy looking for a device whose
+/// `removable` attribute (not the block device's 0/1 flag) is "removable",
+/// as set for USB ports and SD card slots.
+fn is_hotpluggable(path: &Path) -> bool {
+    path.ancestors()
+        .any(|dir| read_attr(dir, "removable").as_deref() == Some("removable"))
+}
+
+/// Reads a trimmed sysfs attribute.
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// Derives the transport (lsblk's TRAN column) from a resolved sysfs device path.
+fn transport_from_sysfs_path(path: &Path) -> Option<String> {
+    let components: Vec<&str> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => s.to_str(),
+            _ => None,
+        })
+        .collect();
+
+    let transport = if components.iter().any(|c| c.starts_with("usb")) {
+        "usb"
+    } else if components.iter().any(|c| c.starts_with("nvme")) {
+        "nvme"
+    } else if components
+        .iter()
+        .any(|c| c.starts_with("mmc_host") || c.starts_with("mmcblk"))
+    {
+        "mmc"
+    } else if components.iter().any(|c| {
+        c.strip_prefix("ata")
+            .is_some_and(|n| n.chars().all(|ch| ch.is_ascii_digit()))
+    }) {
+        "sata"
+    } else {
+        return None;
+    };
+
+    Some(transport.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::Encryption;
+    use std::os::unix::fs::symlink;
+
+    /// Creates a block device (and optional partitions) in a fixture sysfs tree.
+    fn add_disk(
+        root: &Path,
+        device_path: &str,
+        name: &str,
+        rotational: bool,
+        removable: bool,
+        partitions: &[(&str, &str, u64)],
+    ) {
+        let disk_dir = root
+            .join("sys/devices")
+            .join(device_path)
+            .join("block")
+            .join(name);
+        fs::create_dir_all(disk_dir.join("queue")).unwrap();
+        fs::write(
+            disk_dir.join("queue/rotational"),
+            if rotational { "1\n" } else { "0\n" },
+        )
+        .unwrap();
+        fs::write(
+            disk_dir.join("removable"),
+            if removable { "1\n" } else { "0\n" },
+        )
+        .unwrap();
+
+        let class_dir = root.join("sys/class/block");
+        fs::create_dir_all(&class_dir).unwrap();
+        symlink(&disk_dir, class_dir.join(name)).unwrap();
+
+        for (part, dev, sectors) in partitions {
+            let part_dir = disk_dir.join(part);
+            fs::create_dir_all(&part_dir).unwrap();
+            fs::write(part_dir.join("partition"), "1\n").unwrap();
+            fs::write(part_dir.join("dev"), format!("{}\n", dev)).unwrap();
+            fs::write(part_dir.join("size"), format!("{}\n", sectors)).unwrap();
+            symlink(&part_dir, class_dir.join(part)).unwrap();
+        }
+    }
+
+    fn scanner(root: &Path) -> SysfsScanner {
+        SysfsScanner::new()
+            .with_sys_root(root.join("sys"))
+            .with_udev_data_dir(root.join("udev"))
+            .with_mounts_path(root.join("mounts"))
+    }
+
+    #[test]
+    fn test_list_block_devices_from_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        add_disk(
+            root,
+            "pci0000:00/0000:00:01.2/0000:01:00.0/nvme/nvme0",
+            "nvme0n1",
+            false,
+            false,
+            &[("nvme0n1p1", "259:1", 2048), ("nvme0n1p2", "259:2", 4096)],
+        );
+        add_disk(
+            root,
+            "pci0000:00/0000:00:08.1/usb4/4-1/4-1:1.0/host0/target0:0:0/0:0:0:0",
+            "sda",
+            true,
+            true,
+            &[("sda1", "8:1", 1_000_000)],
+        );
+
+        fs::create_dir_all(root.join("udev")).unwrap();
+        fs::write(
+            root.join("udev/b259:2"),
+            "S:disk/by-uuid/1234-5678\nE:ID_FS_UUID=1234-5678\nE:ID_FS_UUID_ENC=1234-5678\n\
+             E:ID_FS_TYPE=exfat\nE:ID_FS_LABEL=SD_Card\nE:ID_FS_LABEL_ENC=SD\\x20Card\n\
+             E:ID_PART_ENTRY_UUID=aaaa-bbbb\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("udev/b8:1"),
+            "E:ID_FS_UUID=ABCD\nE:ID_FS_TYPE=ntfs\nE:ID_PART_ENTRY_UUID=cccc-dddd\n\
+             E:ID_PART_ENTRY_NAME=Games\nE:ID_MODEL=Portable_SSD\n\
+             E:ID_MODEL_ENC=Portable\\x20SSD\\x20\\x20\nE:ID_SERIAL_SHORT=XYZ123\n",
+        )
+        .unwrap();
+        // USB port attribute read for HOTPLUG
+        fs::write(
+            root.join("sys/devices/pci0000:00/0000:00:08.1/usb4/4-1/removable"),
+            "removable\n",
+        )
+        .unwrap();
+        let nvme_dir = root.join("sys/devices/pci0000:00/0000:00:01.2/0000:01:00.0/nvme/nvme0");
+        fs::create_dir_all(nvme_dir.join("block/nvme0n1/device")).unwrap();
+        fs::write(
+            nvme_dir.join("block/nvme0n1/device/model"),
+            "Phison ESMP1TB\n",
+        )
+        .unwrap();
+        fs::write(
+            nvme_dir.join("block/nvme0n1/queue/logical_block_size"),
+            "512\n",
+        )
+        .unwrap();
+        fs::write(
+            nvme_dir.join("block/nvme0n1/queue/discard_granularity"),
+            "512\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("mounts"),
+            "/dev/sda1 /home/deck/Drives/Games ntfs3 rw 0 0\n\
+             /dev/sda1 /run/media/deck/My\\040Games ntfs3 rw 0 0\n",
+        )
+        .unwrap();
+
+        let devices = scanner(root).list_block_devices().unwrap();
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        // Whole disks are skipped
+        assert_eq!(names, vec!["nvme0n1p1", "nvme0n1p2", "sda1"]);
+
+        let p1 = &devices[0];
+        assert_eq!(p1.uuid, None);
+        assert_eq!(p1.fstype, None);
+        assert_eq!(p1.size, 2048 * 512);
+
+        let p2 = &devices[1];
+        assert_eq!(p2.uuid.as_deref(), Some("1234-5678"));
+        assert_eq!(p2.label.as_deref(), Some("SD Card"));
+        assert_eq!(p2.fstype.as_deref(), Some("exfat"));
+        assert_eq!(p2.partuuid.as_deref(), Some("aaaa-bbbb"));
+        assert_eq!(p2.path, PathBuf::from("/dev/nvme0n1p2"));
+        assert_eq!(p2.transport.as_deref(), Some("nvme"));
+        assert!(!p2.rota);
+        assert!(!p2.removable);
+        assert!(!p2.is_mounted());
+        assert!(!p2.hotplug);
+        assert_eq!(p2.model.as_deref(), Some("Phison ESMP1TB"));
+        assert_eq!(p2.pkname.as_deref(), Some("nvme0n1"));
+        assert_eq!(p2.log_sec, Some(512));
+        assert!(p2.supports_discard());
+        assert_eq!(p2.disk_id(), devices[0].disk_id());
+
+        let sda1 = &devices[2];
+        assert!(sda1.is_ntfs());
+        assert_eq!(sda1.transport.as_deref(), Some("usb"));
+        assert!(sda1.rota);
+        assert!(sda1.removable);
+        assert_eq!(
+            sda1.mountpoints,
+            vec![
+                PathBuf::from("/home/deck/Drives/Games"),
+                PathBuf::from("/run/media/deck/My Games")
+            ]
+        );
+        assert_eq!(sda1.fstab_spec().as_deref(), Some("UUID=ABCD"));
+        assert!(sda1.hotplug);
+        assert_eq!(sda1.model.as_deref(), Some("Portable SSD"));
+        assert_eq!(sda1.serial.as_deref(), Some("XYZ123"));
+        assert_eq!(sda1.partlabel.as_deref(), Some("Games"));
+        assert_eq!(sda1.disk_id().as_deref(), Some("Portable_SSD_XYZ123"));
+    }
+
+    #[test]
+    fn test_list_whole_disk_and_mapped_devices() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        // SD card formatted without a partition table
+        add_disk(
+            root,
+            "platform/AMDI0040:00/mmc_host/mmc0/mmc0:0001",
+            "mmcblk0",
+            false,
+            true,
+            &[],
+        );
+        let sd_dir =
+            root.join("sys/devices/platform/AMDI0040:00/mmc_host/mmc0/mmc0:0001/block/mmcblk0");
+        fs::write(sd_dir.join("dev"), "179:0\n").unwrap();
+        fs::write(sd_dir.join("size"), "2048\n").unwrap();
+
+        // LUKS volume on a USB partition
+        add_disk(
+            root,
+            "pci0000:00/0000:00:08.1/usb4/4-1/4-1:1.0/host0/target0:0:0/0:0:0:0",
+            "sda",
+            true,
+            true,
+            &[("sda1", "8:1", 4096)],
+        );
+        let dm_dir = root.join("sys/devices/virtual/block/dm-0");
+        fs::create_dir_all(dm_dir.join("dm")).unwrap();
+        fs::create_dir_all(dm_dir.join("slaves")).unwrap();
+        fs::write(dm_dir.join("dev"), "254:0\n").unwrap();
+        fs::write(dm_dir.join("size"), "4000\n").unwrap();
+        fs::write(dm_dir.join("dm/name"), "luks-abcd\n").unwrap();
+        fs::write(dm_dir.join("dm/uuid"), "CRYPT-LUKS2-abcd-luks-abcd\n").unwrap();
+        symlink(
+            root.join("sys/class/block/sda1"),
+            dm_dir.join("slaves/sda1"),
+        )
+        .unwrap();
+        symlink(&dm_dir, root.join("sys/class/block/dm-0")).unwrap();
+        let holders_dir = root.join("sys/class/block/sda1/holders");
+        fs::create_dir_all(&holders_dir).unwrap();
+        symlink(&dm_dir, holders_dir.join("dm-0")).unwrap();
+
+        // Loop devices are skipped even with a filesystem
+        let loop_dir = root.join("sys/devices/virtual/block/loop0");
+        fs::create_dir_all(&loop_dir).unwrap();
+        fs::write(loop_dir.join("dev"), "7:0\n").unwrap();
+        symlink(&loop_dir, root.join("sys/class/block/loop0")).unwrap();
+
+        fs::create_dir_all(root.join("udev")).unwrap();
+        fs::write(
+            root.join("udev/b179:0"),
+            "E:ID_FS_TYPE=exfat\nE:ID_FS_UUID=1111-2222\n",
+        )
+        .unwrap();
+        fs::write(root.join("udev/b8:1"), "E:ID_FS_TYPE=crypto_LUKS\n").unwrap();
+        fs::write(
+            root.join("udev/b254:0"),
+            "E:ID_FS_TYPE=ntfs\nE:ID_FS_UUID=ABCD\n",
+        )
+        .unwrap();
+        fs::write(root.join("udev/b7:0"), "E:ID_FS_TYPE=squashfs\n").unwrap();
+        fs::write(
+            root.join("mounts"),
+            "/dev/mapper/luks-abcd /run/media/deck/Games ntfs3 rw 0 0\n",
+        )
+        .unwrap();
+
+        let devices = scanner(root).list_block_devices().unwrap();
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["luks-abcd", "mmcblk0", "sda1"]);
+
+        let crypt = &devices[0];
+        assert_eq!(crypt.device_kind, DeviceKind::Crypt);
+        assert_eq!(crypt.path, PathBuf::from("/dev/mapper/luks-abcd"));
+        assert!(crypt.is_ntfs());
+        assert_eq!(crypt.mountpoint(), Some(Path::new("/run/media/deck/Games")));
+        // Inherited from the USB disk underneath
+        assert_eq!(crypt.transport.as_deref(), Some("usb"));
+        assert!(crypt.removable);
+        assert_eq!(crypt.pkname.as_deref(), Some("sda1"));
+
+        let sd = &devices[1];
+        assert_eq!(sd.device_kind, DeviceKind::Disk);
+        assert_eq!(sd.path, PathBuf::from("/dev/mmcblk0"));
+        assert_eq!(sd.transport.as_deref(), Some("mmc"));
+        assert!(sd.removable);
+        assert_eq!(sd.size, 2048 * 512);
+
+        assert_eq!(devices[2].device_kind, DeviceKind::Partition);
+        assert_eq!(devices[2].encryption(), Some(Encryption::Luks));
+        assert_eq!(devices[2].holders, vec!["luks-abcd"]);
+        assert!(!devices[2].is_locked());
+    }
+
+    #[test]
+    fn test_missing_sysfs_root() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(scanner(dir.path()).list_block_devices().is_err());
+    }
+
+    #[test]
+    fn test_transport_from_sysfs_path() {
+        let cases = [
+            (
+                "/sys/devices/pci0000:00/0000:00:17.0/ata3/host2/target2:0:0/2:0:0:0/block/sda/sda1",
+                Some("sata"),
+            ),
+            (
+                "/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host4/block/sdb/sdb1",
+                Some("usb"),
+            ),
+            (
+                "/sys/devices/pci0000:00/0000:00:1d.0/nvme/nvme0/nvme0n1/nvme0n1p1",
+                Some("nvme"),
+            ),
+            (
+                "/sys/devices/platform/AMDI0040:00/mmc_host/mmc0/mmc0:0001/block/mmcblk0/mmcblk0p1",
+                Some("mmc"),
+            ),
+            ("/sys/devices/virtual/block/loop0", None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(
+                transport_from_sysfs_path(Path::new(path)).as_deref(),
+                expected,
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_escapes() {
+        assert_eq!(decode_hex_escapes("My\\x20Games\\x2fX"), "My Games/X");
+        assert_eq!(decode_hex_escapes("trailing\\x2"), "trailing\\x2");
+        assert_eq!(unescape_mount_field("/mnt/a\\040b\\011c"), "/mnt/a b\tc");
+    }
+
+    #[test]
+    fn test_nested_mount_points() {
+        let mounts = "/dev/sda1 /home/deck/Drives/Games exfat rw 0 0\n\
+                      /dev/nvme0n1p8 /home/deck/Drives/Games/SteamLibrary/steamapps/compatdata ext4 rw 0 0\n\
+                      /dev/sdb1 /home/deck/Drives/Games2 ntfs3 rw 0 0\n\
+                      /dev/sdc1 /home/deck/Drives/Games/My\\040Disk exfat rw 0 0\n";
+        assert_eq!(
+            nested_mount_points_in(mounts, Path::new("/home/deck/Drives/Games")),
+            vec![
+                PathBuf::from("/home/deck/Drives/Games/SteamLibrary/steamapps/compatdata"),
+                PathBuf::from("/home/deck/Drives/Games/My Disk"),
+            ]
+        );
+        assert!(nested_mount_points_in(mounts, Path::new("/home/deck/Drives/Games2")).is_empty());
+    }
+}
@@ -0,0 +1,353 @@
+//! D-Bus backend for systemd.
+//!
+//! This module talks to `org.freedesktop.systemd1.Manager` on the system bus
+//! instead of spawning `systemctl`. Unit jobs are waited for until completion
+//! and the resulting unit state (including the type-specific `Result`
+//! property) is returned to the caller.
+//!
+//! Most callers should use the functions in [`crate::syscall`], which use this
+//! backend first and fall back to `systemctl` when the bus is unavailable.
+
+use std::time::{Duration, Instant};
+
+use snafu::ResultExt;
+use zbus::blocking::Connection;
+use zbus::blocking::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+use crate::error::{DBusSnafu, Error, Result};
+use crate::syscall::UnitStatus;
+
+/// Well-known bus name of the systemd manager.
+const SYSTEMD_SERVICE: &str = "org.freedesktop.systemd1";
+
+/// Default time to wait for a unit job to complete.
+pub const DEFAULT_JOB_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Interval between job state polls.
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A unit file change reported by `EnableUnitFiles`/`DisableUnitFiles`: (type, file, destination).
+type UnitFileChange = (String, String, String);
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+trait Manager {
+    fn reload(&self) -> zbus::Result<()>;
+
+    fn start_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn stop_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn restart_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn get_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn load_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn enable_unit_files(
+        &self,
+        files: &[&str],
+        runtime: bool,
+        force: bool,
+    ) -> zbus::Result<(bool, Vec<UnitFileChange>)>;
+
+    fn disable_unit_files(
+        &self,
+        files: &[&str],
+        runtime: bool,
+    ) -> zbus::Result<Vec<UnitFileChange>>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Unit",
+    default_service = "org.freedesktop.systemd1"
+)]
+trait Unit {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn load_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn active_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn sub_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn job(&self) -> zbus::Result<(u32, OwnedObjectPath)>;
+}
+
+/// Client for the systemd manager on the system bus.
+pub struct SystemdManager {
+    connection: Connection,
+    job_timeout: Duration,
+}
+
+impl std::fmt::Debug for SystemdManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SystemdManager")
+            .field("job_timeout", &self.job_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SystemdManager {
+    /// Connects to the systemd manager on the system bus.
+    ///
+    /// The manager proxy is built once here, so that a bus without systemd
+    /// fails before any method is called.
+    pub fn system() -> Result<Self> {
+        let connection = Connection::system().context(DBusSnafu {
+            method: "connect system bus",
+        })?;
+
+        let manager = Self {
+            connection,
+            job_timeout: DEFAULT_JOB_TIMEOUT,
+        };
+        manager.manager()?;
+        Ok(manager)
+    }
+
+    /// Sets how long unit jobs are waited for before giving up.
+    pub fn with_job_timeout(mut self, timeout: Duration) -> Self {
+        self.job_timeout = timeout;
+        self
+    }
+
+    fn manager(&self) -> Result<ManagerProxyBlocking<'_>> {
+        ManagerProxyBlocking::new(&self.connection).context(DBusSnafu {
+            method: "Manager proxy",
+        })
+    }
+
+    fn unit(&self, path: &ObjectPath<'_>) -> Result<UnitProxyBlocking<'_>> {
+        UnitProxyBlocking::builder(&self.connection)
+            .path(path.to_owned())
+            .and_then(|builder| builder.cache_properties(CacheProperties::No).build())
+            .context(DBusSnafu {
+                method: "Unit proxy",
+            })
+    }
+
+    /// Reloads the systemd manager configuration (`daemon-reload`).
+    ///
+    /// The call returns once the reload has finished.
+    pub fn reload(&self) -> Result<()> {
+        self.manager()?
+            .reload()
+            .context(DBusSnafu { method: "Reload" })
+    }
+
+    /// Starts a unit and waits for the start job to complete.
+    pub fn start_unit(&self, name: &str) -> Result<UnitStatus> {
+        let job = self
+            .manager()?
+            .start_unit(name, "replace")
+            .context(DBusSnafu {
+                method: "StartUnit",
+            })?;
+        self.wait_for_job(name, &job)
+    }
+
+    /// Stops a unit and waits for the stop job to complete.
+    pub fn stop_unit(&self, name: &str) -> Result<UnitStatus> {
+        let job = self
+            .manager()?
+            .stop_unit(name, "replace")
+            .context(DBusSnafu { method: "StopUnit" })?;
+        self.wait_for_job(name, &job)
+    }
+
+    /// Restarts a unit and waits for the restart job to complete.
+    pub fn restart_unit(&self, name: &str) -> Result<UnitStatus> {
+        let job = self
+            .manager()?
+            .restart_unit(name, "replace")
+            .context(DBusSnafu {
+                method: "RestartUnit",
+            })?;
+        self.wait_for_job(name, &job)
+    }
+
+    /// Enables a unit file and reloads the manager configuration.
+    ///
+    /// Existing conflicting symlinks are replaced (`force`).
+    pub fn enable_unit(&self, name: &str) -> Result<()> {
+        self.manager()?
+            .enable_unit_files(&[name], false, true)
+            .context(DBusSnafu {
+                method: "EnableUnitFiles",
+            })?;
+        self.reload()
+    }
+
+    /// Disables a unit file and reloads the manager configuration.
+    pub fn disable_unit(&self, name: &str) -> Result<()> {
+        self.manager()?
+            .disable_unit_files(&[name], false)
+            .context(DBusSnafu {
+                method: "DisableUnitFiles",
+            })?;
+        self.reload()
+    }
+
+    /// Returns the object path of a loaded unit.
+    ///
+    /// Fails if the unit is not currently loaded.
+    pub fn get_unit(&self, name: &str) -> Result<OwnedObjectPath> {
+        self.manager()?
+            .get_unit(name)
+            .context(DBusSnafu { method: "GetUnit" })
+    }
+
+    /// Queries the current state of a unit.
+    ///
+    /// Uses `LoadUnit` so that inactive units which are not loaded yet can
+    /// still be queried.
+    pub fn unit_status(&self, name: &str) -> Result<UnitStatus> {
+        let path = self
+            .manager()?
+            .load_unit(name)
+            .context(DBusSnafu { method: "LoadUnit" })?;
+        self.read_unit_status(name, &path)
+    }
+
+    /// Polls the unit until the given job is no longer pending.
+    ///
+    /// A stopped unit may be garbage-collected while it is polled, so the
+    /// unit is looked up with `LoadUnit` and a vanished unit object counts as
+    /// a finished job.
+    fn wait_for_job(&self, name: &str, job: &OwnedObjectPath) -> Result<UnitStatus> {
+        let path = self
+            .manager()?
+            .load_unit(name)
+            .context(DBusSnafu { method: "LoadUnit" })?;
+        let unit = self.unit(&path)?;
+        let deadline = Instant::now() + self.job_timeout;
+
+        loop {
+            let Ok((job_id, job_path)) = unit.job() else {
+                break;
+            };
+            if job_id == 0 || job_path != *job {
+                break;
+            }
+
+            if Instant::now() >= deadline {
+                return Err(Error::Systemd {
+                    message: format!(
+                        "timed out after {}s waiting for job {} of {}",
+                        self.job_timeout.as_secs(),
+                        job.as_str(),
+                        name
+                    ),
+                });
+            }
+
+            std::thread::sleep(JOB_POLL_INTERVAL);
+        }
+
+        self.unit_status(name)
+    }
+
+    fn read_unit_status(&self, name: &str, path: &OwnedObjectPath) -> Result<UnitStatus> {
+        let unit = self.unit(path)?;
+
+        let id = unit.id().context(DBusSnafu { method: "Unit.Id" })?;
+        let load_state = unit.load_state().context(DBusSnafu {
+            method: "Unit.LoadState",
+        })?;
+        let active_state = unit.active_state().context(DBusSnafu {
+            method: "Unit.ActiveState",
+        })?;
+        let sub_state = unit.sub_state().context(DBusSnafu {
+            method: "Unit.SubState",
+        })?;
+
+        let result = match unit_type_interface(name) {
+            Some(interface) => self.read_result_property(path, interface)?,
+            None => None,
+        };
+
+        Ok(UnitStatus {
+            id,
+            load_state,
+            active_state,
+            sub_state,
+            result,
+        })
+    }
+
+    /// Reads the type-specific `Result` property (e.g. of `org.freedesktop.systemd1.Mount`).
+    fn read_result_property(
+        &self,
+        path: &OwnedObjectPath,
+        interface: &'static str,
+    ) -> Result<Option<String>> {
+        let properties = PropertiesProxy::builder(&self.connection)
+            .destination(SYSTEMD_SERVICE)
+            .and_then(|builder| builder.path(path.clone()))
+            .and_then(|builder| builder.cache_properties(CacheProperties::No).build())
+            .context(DBusSnafu {
+                method: "Properties proxy",
+            })?;
+
+        let interface = InterfaceName::from_static_str_unchecked(interface);
+        let value = properties
+            .get(interface, "Result")
+            .map_err(zbus::Error::from)
+            .context(DBusSnafu {
+                method: "Properties.Get(Result)",
+            })?;
+
+        Ok(String::try_from(value).ok())
+    }
+}
+
+/// Returns the type-specific D-Bus interface of a unit that carries a `Result` property.
+fn unit_type_interface(name: &str) -> Option<&'static str> {
+    let (_, suffix) = name.rsplit_once('.')?;
+    match suffix {
+        "service" => Some("org.freedesktop.systemd1.Service"),
+        "mount" => Some("org.freedesktop.systemd1.Mount"),
+        "automount" => Some("org.freedesktop.systemd1.Automount"),
+        "socket" => Some("org.freedesktop.systemd1.Socket"),
+        "swap" => Some("org.freedesktop.systemd1.Swap"),
+        "timer" => Some("org.freedesktop.systemd1.Timer"),
+        "path" => Some("org.freedesktop.systemd1.Path"),
+        "scope" => Some("org.freedesktop.systemd1.Scope"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_type_interface() {
+        assert_eq!(
+            unit_type_interface("home-deck-Drives-Games.mount"),
+            Some("org.freedesktop.systemd1.Mount")
+        );
+        assert_eq!(
+            unit_type_interface("home-deck-Drives-Games.automount"),
+            Some("org.freedesktop.systemd1.Automount")
+        );
+        assert_eq!(
+            unit_type_interface("sddm.service"),
+            Some("org.freedesktop.systemd1.Service")
+        );
+        assert_eq!(unit_type_interface("local-fs.target"), None);
+        assert_eq!(unit_type_interface("no-suffix"), None);
+    }
+}
@@ -0,0 +1,263 @@
+//! Coexistence with udisks2 and the SteamOS automounter.
+//!
+//! SteamOS automounts SD cards and USB drives through udisks2 under
+//! `/run/media/<user>/<label>`. For managed devices this second mount fights
+//! with ours, so this module can:
+//!
+//! - Unmount a udisks mount through the udisks2 D-Bus API
+//!   (`org.freedesktop.UDisks2.Filesystem.Unmount`), falling back to `umount`
+//!   when udisks2 is not reachable.
+//! - Maintain a udev rules file that sets `UDISKS_IGNORE=1` for managed
+//!   filesystem UUIDs, so the system automounter leaves them alone.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use snafu::ResultExt;
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedObjectPath, Value};
+
+use crate::error::{DBusSnafu, Error, Result, UdevRulesSnafu};
+use crate::executor::ExecutionContext;
+use crate::mount;
+use crate::sysfs;
+
+/// Path of the udev rules file holding the managed `UDISKS_IGNORE` rules.
+pub const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/90-steamos-mount-udisks-ignore.rules";
+
+/// Header written at the top of the managed udev rules file.
+const UDEV_RULES_HEADER: &str = "# Managed by steamos-mount. Do not edit.\n\
+# Keeps udisks2 from automounting filesystems mounted via /etc/fstab.";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.Manager",
+    default_service = "org.freedesktop.UDisks2",
+    default_path = "/org/freedesktop/UDisks2/Manager"
+)]
+trait Manager {
+    fn resolve_device(
+        &self,
+        devspec: HashMap<&str, Value<'_>>,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.Filesystem",
+    default_service = "org.freedesktop.UDisks2"
+)]
+trait Filesystem {
+    fn unmount(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+}
+
+/// Unmounts the udisks mount of a block device via udisks2 D-Bus.
+///
+/// Going through udisks2 (instead of `umount`) also clears its record of
+/// the mount, and is allowed for the active session user by polkit.
+pub fn unmount_filesystem(device_path: &Path) -> Result<()> {
+    let connection = Connection::system().context(DBusSnafu {
+        method: "connect system bus",
+    })?;
+    let manager = ManagerProxyBlocking::new(&connection).context(DBusSnafu {
+        method: "UDisks2.Manager proxy",
+    })?;
+
+    let device = device_path.display().to_string();
+    let objects = manager
+        .resolve_device(
+            HashMap::from([("path", Value::from(device.as_str()))]),
+            HashMap::new(),
+        )
+        .context(DBusSnafu {
+            method: "ResolveDevice",
+        })?;
+    let object = objects.into_iter().next().ok_or_else(|| Error::Unmount {
+        path: device_path.to_path_buf(),
+        message: "device is not known to udisks2".to_string(),
+    })?;
+
+    FilesystemProxyBlocking::builder(&connection)
+        .path(object)
+        .and_then(|builder| builder.build())
+        .context(DBusSnafu {
+            method: "UDisks2.Filesystem proxy",
+        })?
+        .unmount(HashMap::new())
+        .context(DBusSnafu {
+            method: "Filesystem.Unmount",
+        })
+}
+
+/// Unmounts the udisks mount points of a device before our mount is activated.
+///
+/// Uses udisks2 D-Bus first; if the bus or udisks2 is unavailable, udisks2
+/// does not know the device, or the udisks mount is still present afterwards,
+/// each remaining mount point under `/run/media` is unmounted with `umount`
+/// through `ctx`.
+pub fn release_mounts_with_ctx(
+    device_path: &Path,
+    mountpoints: &[PathBuf],
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    if !mountpoints.iter().any(|p| mount::is_udisks_mount_point(p)) {
+        return Ok(());
+    }
+
+    // An empty ResolveDevice result (Error::Unmount) falls back like an
+    // unreachable udisks2
+    match unmount_filesystem(device_path) {
+        Ok(()) | Err(Error::DBus { .. }) | Err(Error::Unmount { .. }) => {}
+        Err(e) => return Err(e),
+    }
+
+    for path in mountpoints {
+        if mount::is_udisks_mount_point(path) && sysfs::is_mount_point(path) {
+            mount::unmount_device_with_ctx(path, ctx)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the udev rule that hides the filesystem with `uuid` from udisks2.
+///
+/// Fails if the UUID contains characters that could break out of the rule.
+pub fn ignore_rule(uuid: &str) -> Result<String> {
+    if uuid.is_empty() || !uuid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(Error::InvalidUuid {
+            uuid: uuid.to_string(),
+        });
+    }
+    Ok(format!(
+        "SUBSYSTEM==\"block\", ENV{{ID_FS_UUID}}==\"{}\", ENV{{UDISKS_IGNORE}}=\"1\"",
+        uuid
+    ))
+}
+
+/// Extracts the UUIDs of the `UDISKS_IGNORE` rules in a rules file.
+pub fn parse_ignore_rules(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#') && line.contains("UDISKS_IGNORE"))
+        .filter_map(|line| {
+            let (_, rest) = line.split_once("ENV{ID_FS_UUID}==\"")?;
+            let (uuid, _) = rest.split_once('"')?;
+            Some(uuid.to_string())
+        })
+        .collect()
+}
+
+/// Renders the managed rules file for the given UUIDs.
+pub fn render_ignore_rules(uuids: &[String]) -> Result<String> {
+    let mut content = String::from(UDEV_RULES_HEADER);
+    content.push('\n');
+    for uuid in uuids {
+        content.push_str(&ignore_rule(uuid)?);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Lists the UUIDs currently hidden from udisks2. A missing file yields none.
+pub fn list_ignored_uuids(rules_path: &Path) -> Result<Vec<String>> {
+    match fs::read_to_string(rules_path) {
+        Ok(content) => Ok(parse_ignore_rules(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).context(UdevRulesSnafu { path: rules_path }),
+    }
+}
+
+/// Adds a `UDISKS_IGNORE` rule for `uuid` and reloads udev.
+///
+/// Does nothing if the rule already exists.
+pub fn add_ignore_rule_with_ctx(
+    rules_path: &Path,
+    uuid: &str,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let mut uuids = list_ignored_uuids(rules_path)?;
+    if uuids.iter().any(|u| u.eq_ignore_ascii_case(uuid)) {
+        return Ok(());
+    }
+    uuids.push(uuid.to_string());
+    write_rules_with_ctx(rules_path, &uuids, ctx)
+}
+
+/// Removes the `UDISKS_IGNORE` rule for `uuid` and reloads udev.
+///
+/// The rules file is deleted once it holds no rules.
+pub fn remove_ignore_rule_with_ctx(
+    rules_path: &Path,
+    uuid: &str,
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let mut uuids = list_ignored_uuids(rules_path)?;
+    let before = uuids.len();
+    uuids.retain(|u| !u.eq_ignore_ascii_case(uuid));
+    if uuids.len() == before {
+        return Ok(());
+    }
+
+    if uuids.is_empty() {
+        ctx.run_privileged_checked("rm", &["-f", &rules_path.display().to_string()])?;
+        return reload_udev_rules_with_ctx(ctx);
+    }
+    write_rules_with_ctx(rules_path, &uuids, ctx)
+}
+
+fn write_rules_with_ctx(
+    rules_path: &Path,
+    uuids: &[String],
+    ctx: &mut ExecutionContext,
+) -> Result<()> {
+    let content = render_ignore_rules(uuids)?;
+    ctx.write_file_privileged(&rules_path.display().to_string(), &content)?;
+    reload_udev_rules_with_ctx(ctx)
+}
+
+/// Reloads the udev rules and re-triggers block devices so the change applies
+/// to connected devices.
+pub fn reload_udev_rules_with_ctx(ctx: &mut ExecutionContext) -> Result<()> {
+    ctx.run_privileged_checked("udevadm", &["control", "--reload"])?;
+    ctx.run_privileged_checked(
+        "udevadm",
+        &["trigger", "--action=change", "--subsystem-match=block"],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules_round_trip() {
+        let uuids = vec!["01D9A8B7C6E5F400".to_string(), "1234-ABCD".to_string()];
+        let content = render_ignore_rules(&uuids).unwrap();
+        assert!(content.starts_with("# Managed by steamos-mount"));
+        assert!(content.contains(
+            "SUBSYSTEM==\"block\", ENV{ID_FS_UUID}==\"1234-ABCD\", ENV{UDISKS_IGNORE}=\"1\""
+        ));
+        assert_eq!(parse_ignore_rules(&content), uuids);
+
+        // Injection attempts are rejected
+        assert!(ignore_rule("1234\", RUN+=\"/bin/sh").is_err());
+        assert!(ignore_rule("").is_err());
+    }
+
+    #[test]
+    fn test_list_ignored_uuids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("90-test.rules");
+        assert!(list_ignored_uuids(&path).unwrap().is_empty());
+
+        fs::write(
+            &path,
+            "# comment with UDISKS_IGNORE\n\
+             SUBSYSTEM==\"block\", ENV{ID_FS_UUID}==\"AAAA-BBBB\", ENV{UDISKS_IGNORE}=\"1\"\n\
+             SUBSYSTEM==\"block\", ENV{ID_FS_TYPE}==\"vfat\", ENV{UDISKS_AUTO}=\"0\"\n",
+        )
+        .unwrap();
+        assert_eq!(list_ignored_uuids(&path).unwrap(), vec!["AAAA-BBBB"]);
+    }
+}
@@ -0,0 +1,301 @@
+//! Free/used space reporting for devices and Steam libraries.
+//!
+//! Usage of mounted filesystems is queried with `statvfs`. Because offline
+//! devices cannot be queried, the last-known size and usage are cached per
+//! fs_spec in the user's cache directory, so that the UI can still show how
+//! full a disconnected drive was.
+//!
+//! Per-library usage compares the sizes Steam records in `libraryfolders.vdf`
+//! with the actual disk usage of the library's `steamapps/common` directory,
+//! which reveals leftovers from uninstalled games.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::error::{Result, UsageCacheSnafu, UsageSnafu};
+use crate::steam::LibraryFolder;
+
+/// File name of the usage cache inside [`default_cache_path`]'s directory.
+pub const USAGE_CACHE_FILE: &str = "usage.json";
+
+/// Space usage of a filesystem, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpaceUsage {
+    /// Total size of the filesystem.
+    pub total: u64,
+    /// Space in use, including space reserved for root.
+    pub used: u64,
+    /// Space available to unprivileged users.
+    pub available: u64,
+}
+
+impl SpaceUsage {
+    /// Returns the used fraction of the filesystem in percent (0-100).
+    pub fn used_percent(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.used as f64 * 100.0 / self.total as f64
+    }
+}
+
+/// Queries the space usage of the filesystem containing `path`.
+pub fn filesystem_usage(path: &Path) -> Result<SpaceUsage> {
+    let stat = nix::sys::statvfs::statvfs(path)
+        .map_err(std::io::Error::from)
+        .context(UsageSnafu { path })?;
+
+    let fragment_size = stat.fragment_size() as u64;
+    let total = stat.blocks() as u64 * fragment_size;
+    let free = stat.blocks_free() as u64 * fragment_size;
+    let available = stat.blocks_available() as u64 * fragment_size;
+
+    Ok(SpaceUsage {
+        total,
+        used: total.saturating_sub(free),
+        available,
+    })
+}
+
+/// Returns the disk usage of a directory tree in bytes, like `du -s`.
+///
+/// Counts allocated blocks rather than file sizes, does not follow symlinks
+/// and counts hard-linked files once. Entries that cannot be read are skipped.
+pub fn directory_usage(path: &Path) -> Result<u64> {
+    let metadata = fs::symlink_metadata(path).context(UsageSnafu { path })?;
+    let mut seen = HashSet::new();
+    let mut total = 0;
+    let mut pending = vec![(path.to_path_buf(), metadata)];
+
+    while let Some((path, metadata)) = pending.pop() {
+        if metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino())) {
+            continue;
+        }
+        // st_blocks is always in 512-byte units
+        total += metadata.blocks() * 512;
+
+        if metadata.is_dir() {
+            let Ok(entries) = fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if let Ok(metadata) = entry.metadata() {
+                    pending.push((entry.path(), metadata));
+                }
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+/// Usage of a Steam library folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryUsage {
+    /// Path to the library folder.
+    pub path: PathBuf,
+    /// Number of apps Steam lists in the library.
+    pub app_count: usize,
+    /// Sum of the app sizes recorded by Steam in `libraryfolders.vdf`.
+    pub reported_bytes: u64,
+    /// Actual disk usage of `steamapps/common`. None if it cannot be read.
+    pub actual_bytes: Option<u64>,
+}
+
+impl LibraryUsage {
+    /// Computes the usage of a library folder.
+    pub fn of(library: &LibraryFolder) -> Self {
+        let common = library.path.join("steamapps").join("common");
+        Self {
+            path: library.path.clone(),
+            app_count: library.apps.len(),
+            reported_bytes: library
+                .apps
+                .values()
+                .filter_map(|size| size.trim().parse::<u64>().ok())
+                .sum(),
+            actual_bytes: directory_usage(&common).ok(),
+        }
+    }
+
+    /// Returns the space used in `steamapps/common` beyond what Steam accounts
+    /// for (e.g. leftovers of uninstalled games).
+    pub fn untracked_bytes(&self) -> u64 {
+        self.actual_bytes
+            .map(|actual| actual.saturating_sub(self.reported_bytes))
+            .unwrap_or(0)
+    }
+}
+
+/// Last-known size and usage of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedUsage {
+    /// Device size in bytes.
+    pub size: u64,
+    /// Filesystem usage, if the device was mounted when last seen.
+    pub usage: Option<SpaceUsage>,
+    /// When the values were recorded (seconds since the Unix epoch).
+    pub updated_at: u64,
+}
+
+/// Cache of the last-known size and usage per fs_spec.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCache {
+    #[serde(default)]
+    entries: HashMap<String, CachedUsage>,
+}
+
+impl UsageCache {
+    /// Loads the cache from `path`.
+    ///
+    /// A missing or unreadable cache is treated as empty, since it only holds
+    /// information that will be recorded again.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the cache to `path`, creating parent directories as needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(UsageCacheSnafu { path })?;
+        }
+        let content = serde_json::to_string_pretty(self).expect("Failed to serialize usage cache");
+
+        // Write to a temporary file first so a crash never leaves a truncated cache
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).context(UsageCacheSnafu { path: &tmp_path })?;
+        fs::rename(&tmp_path, path).context(UsageCacheSnafu { path })?;
+        Ok(())
+    }
+
+    /// Returns the cached values for an fs_spec.
+    pub fn get(&self, fs_spec: &str) -> Option<&CachedUsage> {
+        self.entries.get(fs_spec)
+    }
+
+    /// Records the current size and usage of a device.
+    ///
+    /// A previously cached usage is kept if `usage` is None (device not
+    /// mounted). Returns true if the cache changed.
+    pub fn record(&mut self, fs_spec: &str, size: u64, usage: Option<SpaceUsage>) -> bool {
+        let usage = usage.or_else(|| self.get(fs_spec).and_then(|cached| cached.usage));
+        if let Some(cached) = self.get(fs_spec)
+            && cached.size == size
+            && cached.usage == usage
+        {
+            return false;
+        }
+
+        self.entries.insert(
+            fs_spec.to_string(),
+            CachedUsage {
+                size,
+                usage,
+                updated_at: unix_now(),
+            },
+        );
+        true
+    }
+}
+
+/// Returns the default usage cache path (`~/.cache/steamos-mount/usage.json`).
+pub fn default_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("steamos-mount").join(USAGE_CACHE_FILE))
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filesystem_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let usage = filesystem_usage(dir.path()).unwrap();
+        assert!(usage.total > 0);
+        assert!(usage.used <= usage.total);
+        assert!(usage.available <= usage.total);
+
+        assert!(filesystem_usage(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_library_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let common = dir.path().join("steamapps/common/Game");
+        fs::create_dir_all(&common).unwrap();
+        fs::write(common.join("data.bin"), vec![1u8; 64 * 1024]).unwrap();
+        // Hard links are only counted once
+        fs::hard_link(common.join("data.bin"), common.join("link.bin")).unwrap();
+
+        let library = LibraryFolder {
+            path: dir.path().to_path_buf(),
+            apps: HashMap::from([
+                ("10".to_string(), "1000".to_string()),
+                ("20".to_string(), "24".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let usage = LibraryUsage::of(&library);
+        assert_eq!(usage.app_count, 2);
+        assert_eq!(usage.reported_bytes, 1024);
+        let actual = usage.actual_bytes.unwrap();
+        assert!(actual >= 64 * 1024);
+        assert!(actual < 2 * 64 * 1024);
+        assert_eq!(usage.untracked_bytes(), actual - 1024);
+
+        let missing = LibraryUsage::of(&LibraryFolder {
+            path: dir.path().join("missing"),
+            ..Default::default()
+        });
+        assert_eq!(missing.actual_bytes, None);
+        assert_eq!(missing.untracked_bytes(), 0);
+    }
+
+    #[test]
+    fn test_usage_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/usage.json");
+        let usage = SpaceUsage {
+            total: 1000,
+            used: 250,
+            available: 700,
+        };
+
+        let mut cache = UsageCache::load(&path);
+        assert!(cache.record("UUID=1234", 1024, Some(usage)));
+        assert!(!cache.record("UUID=1234", 1024, Some(usage)));
+        // Unmounted: size is updated, last usage kept
+        assert!(cache.record("UUID=1234", 2048, None));
+        cache.save(&path).unwrap();
+
+        let loaded = UsageCache::load(&path);
+        let cached = loaded.get("UUID=1234").unwrap();
+        assert_eq!(cached.size, 2048);
+        assert_eq!(cached.usage, Some(usage));
+        assert_eq!(usage.used_percent(), 25.0);
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(UsageCache::load(&path), UsageCache::default());
+    }
+}